@@ -0,0 +1,144 @@
+//! Shared embedding helpers for the candle examples
+//!
+//! Loads BAAI/bge-base-en-v1.5 from the HuggingFace Hub and turns texts into
+//! L2-normalized 768-d embeddings (mean pooling over the last hidden state).
+//!
+//! The unit tests here don't need the network; run them with
+//! `cargo test --examples`.
+
+use candle_core::{DType, Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config};
+use hf_hub::{Repo, RepoType, api::sync::Api};
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+
+pub const MODEL_ID: &str = "BAAI/bge-base-en-v1.5";
+const MAX_TOKENS: usize = 128;
+
+pub type Error = Box<dyn std::error::Error>;
+
+/// Loads the bge model and its tokenizer, downloading them on first use.
+///
+/// Picks the first CUDA device if available, otherwise the CPU. The tokenizer
+/// is configured to pad batches to the longest input and truncate to
+/// `MAX_TOKENS` tokens.
+pub fn load_bge_model() -> Result<(BertModel, Tokenizer, Device), Error> {
+    let device = Device::cuda_if_available(0)?;
+
+    let api = Api::new()?;
+    let repo = api.repo(Repo::new(MODEL_ID.to_string(), RepoType::Model));
+
+    let tokenizer_path = repo.get("tokenizer.json")?;
+    let config_path = repo.get("config.json")?;
+    let weights_path = repo.get("model.safetensors")?;
+
+    let config: Config = serde_json::from_str(&std::fs::read_to_string(config_path)?)?;
+    let mut tokenizer = Tokenizer::from_file(tokenizer_path).map_err(|e| e.to_string())?;
+
+    // Set up padding and truncation for batch processing
+    tokenizer.with_padding(Some(PaddingParams::default()));
+    tokenizer
+        .with_truncation(Some(TruncationParams {
+            max_length: MAX_TOKENS,
+            ..Default::default()
+        }))
+        .map_err(|e| e.to_string())?;
+
+    let vb = unsafe { VarBuilder::from_mmaped_safetensors(&[weights_path], DType::F32, &device)? };
+    let model = BertModel::load(vb, &config)?;
+
+    Ok((model, tokenizer, device))
+}
+
+/// Embeds a batch of texts into L2-normalized vectors, one per input text.
+pub fn embed_texts(
+    model: &BertModel,
+    tokenizer: &Tokenizer,
+    device: &Device,
+    texts: &[&str],
+) -> Result<Vec<Vec<f32>>, Error> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let encodings = tokenizer
+        .encode_batch(texts.to_vec(), true)
+        .map_err(|e| e.to_string())?;
+
+    let batch_len = encodings.len();
+    let seq_len = encodings[0].get_ids().len();
+
+    let token_ids: Vec<u32> = encodings
+        .iter()
+        .flat_map(|e| e.get_ids().iter().copied())
+        .collect();
+    let attention_mask: Vec<u32> = encodings
+        .iter()
+        .flat_map(|e| e.get_attention_mask().iter().copied())
+        .collect();
+
+    let token_ids = Tensor::from_vec(token_ids, (batch_len, seq_len), device)?;
+    let attention_mask = Tensor::from_vec(attention_mask, (batch_len, seq_len), device)?;
+    let token_type_ids = token_ids.zeros_like()?;
+
+    let hidden_states = model.forward(&token_ids, &token_type_ids, Some(&attention_mask))?;
+
+    Ok(pool_and_normalize(&hidden_states, &attention_mask)?)
+}
+
+/// Mean-pools hidden states over the unmasked tokens and L2-normalizes the result.
+///
+/// `hidden_states` has shape `(batch, seq_len, hidden)` and `attention_mask`
+/// has shape `(batch, seq_len)`.
+pub fn pool_and_normalize(
+    hidden_states: &Tensor,
+    attention_mask: &Tensor,
+) -> candle_core::Result<Vec<Vec<f32>>> {
+    let pooled = mean_pooling(hidden_states, attention_mask)?;
+    l2_normalize(&pooled)?.to_vec2()
+}
+
+fn mean_pooling(hidden_states: &Tensor, attention_mask: &Tensor) -> candle_core::Result<Tensor> {
+    let mask_expanded = attention_mask
+        .unsqueeze(2)?
+        .broadcast_as(hidden_states.shape())?
+        .to_dtype(hidden_states.dtype())?;
+
+    let sum_embeddings = (hidden_states * &mask_expanded)?.sum(1)?;
+    let sum_mask = mask_expanded.sum(1)?.clamp(1e-9, f64::MAX)?;
+    sum_embeddings.broadcast_div(&sum_mask)
+}
+
+fn l2_normalize(tensor: &Tensor) -> candle_core::Result<Tensor> {
+    let norm = tensor.sqr()?.sum_keepdim(1)?.sqrt()?;
+    tensor.broadcast_div(&norm.clamp(1e-12, f64::MAX)?)
+}
+
+#[cfg(test)]
+mod common_test {
+    use super::*;
+
+    #[test]
+    fn test_pool_and_normalize_produces_unit_vectors() {
+        // Stand-in for the model output: batch of 2, 3 tokens, 4 hidden dims
+        #[rustfmt::skip]
+        let hidden: Vec<f32> = vec![
+            1.0, 2.0, 3.0, 4.0,   2.0, 2.0, 2.0, 2.0,   9.0, 9.0, 9.0, 9.0,
+            0.0, 1.0, 0.0, 0.0,   0.0, 3.0, 0.0, 0.0,   5.0, 5.0, 5.0, 5.0,
+        ];
+        let hidden = Tensor::from_vec(hidden, (2, 3, 4), &Device::Cpu).unwrap();
+        // The last token of each row is padding
+        let mask = Tensor::from_vec(vec![1u32, 1, 0, 1, 1, 0], (2, 3), &Device::Cpu).unwrap();
+
+        let embeddings = pool_and_normalize(&hidden, &mask).unwrap();
+
+        assert_eq!(embeddings.len(), 2);
+        for embedding in &embeddings {
+            assert_eq!(embedding.len(), 4);
+            let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-5);
+        }
+        // Padding is ignored: the second row pools to [0, 2, 0, 0]
+        assert!((embeddings[1][1] - 1.0).abs() < 1e-5);
+    }
+}
@@ -40,8 +40,8 @@ fn main() {
             i + 1,
             elapsed.as_secs_f64() * 1000.0
         );
-        for (rank, (id, _vec, score)) in results.iter().enumerate() {
-            println!("  {}. {} (score: {:.6})", rank + 1, id, score);
+        for (rank, result) in results.iter().enumerate() {
+            println!("  {}. {} (score: {:.6})", rank + 1, result.id, result.score);
         }
         println!();
     }
@@ -71,7 +71,7 @@ fn main() {
     for i in 0..10 {
         let id = format!("vec_{}", i * 1000); // Delete vec_0, vec_1000, vec_2000, ...
         let start = Instant::now();
-        let result = db.delete(&id);
+        let result = db.delete_fast(&id);
         let elapsed = start.elapsed();
         match result {
             Ok(msg) => println!(
@@ -105,8 +105,8 @@ fn main() {
             i + 1,
             elapsed.as_secs_f64() * 1000.0
         );
-        for (rank, (id, _vec, score)) in results.iter().enumerate() {
-            println!("  {}. {} (score: {:.6})", rank + 1, id, score);
+        for (rank, result) in results.iter().enumerate() {
+            println!("  {}. {} (score: {:.6})", rank + 1, result.id, result.score);
         }
         println!();
     }
@@ -20,12 +20,16 @@ fn main() {
 
     let start = Instant::now();
     let mut db = VecDB::new();
-    for i in 0..num_vectors {
-        let vec = random_vector(dim, i as u64);
-        db.insert(format!("vec_{}", i), vec).unwrap();
-        if (i + 1) % 10_000 == 0 {
-            println!("  inserted {}/{}", i + 1, num_vectors);
+    let batch_size = 10_000;
+    for batch_start in (0..num_vectors).step_by(batch_size) {
+        let batch_end = (batch_start + batch_size).min(num_vectors);
+        let batch = (batch_start..batch_end)
+            .map(|i| (format!("vec_{}", i), random_vector(dim, i as u64)))
+            .collect();
+        for result in db.insert_many(batch) {
+            result.unwrap();
         }
+        println!("  inserted {}/{}", batch_end, num_vectors);
     }
     let insert_time = start.elapsed();
     println!(
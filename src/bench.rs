@@ -0,0 +1,118 @@
+//! The benchmarking module
+//! Measure how closely an approximate index follows brute-force search
+
+use crate::VecDB;
+use std::collections::HashSet;
+
+/// Average recall@k of [`VecDB::search`] against [`VecDB::search_exact`].
+///
+/// For each query, recall is the fraction of the exact top-`k` IDs that the
+/// configured search also returns. A database without an index always
+/// scores 1.0.
+///
+/// # Returns
+///
+/// * `Ok(f32)` - Mean recall over the queries, between 0.0 and 1.0
+/// * `Err(String)` - Error if there are no queries, `k` is 0, or a search fails
+///
+/// # Examples
+///
+/// ```
+/// use kvdb::{IndexKind, VecDB, bench::recall_at_k};
+///
+/// let mut db = VecDB::new();
+/// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+/// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+/// let db = db.with_index(IndexKind::default());
+///
+/// let recall = recall_at_k(&db, &[vec![0.9, 0.1]], 1).unwrap();
+/// assert_eq!(recall, 1.0);
+/// ```
+pub fn recall_at_k(db: &VecDB, queries: &[Vec<f32>], k: usize) -> Result<f32, String> {
+    if queries.is_empty() {
+        return Err("No queries to measure recall with".to_string());
+    }
+    if k == 0 {
+        return Err("k must be at least 1".to_string());
+    }
+
+    let mut total = 0.0;
+    for query in queries {
+        let truth: HashSet<String> = db
+            .search_exact(query.clone(), k)?
+            .into_iter()
+            .map(|result| result.id)
+            .collect();
+        let found = db
+            .search(query.clone(), k)?
+            .iter()
+            .filter(|result| truth.contains(&result.id))
+            .count();
+        total += found as f32 / truth.len() as f32;
+    }
+
+    Ok(total / queries.len() as f32)
+}
+
+#[cfg(test)]
+mod bench_test {
+    use super::*;
+    use crate::IndexKind;
+
+    fn grid_db() -> VecDB {
+        let mut db = VecDB::new();
+        for i in 0..200 {
+            let angle = i as f32 * 0.1;
+            db.insert(format!("vec{}", i), vec![angle.cos(), angle.sin(), 0.5])
+                .unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_brute_force_recall_is_exact() {
+        let db = grid_db();
+        let queries = vec![
+            vec![1.0, 0.0, 0.0],
+            vec![0.0, 1.0, 1.0],
+            vec![-1.0, 0.2, 0.5],
+        ];
+        assert_eq!(recall_at_k(&db, &queries, 10).unwrap(), 1.0);
+        // Fewer vectors than k still counts as a perfect match
+        assert_eq!(recall_at_k(&db, &queries, 500).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_recall_of_a_coarse_index() {
+        let db = grid_db().with_index(IndexKind::Ivf {
+            nlist: 20,
+            nprobe: 1,
+        });
+        let queries: Vec<Vec<f32>> = (0..20)
+            .map(|i| {
+                let angle = i as f32 * 0.33;
+                vec![angle.cos(), angle.sin(), 0.5]
+            })
+            .collect();
+
+        let recall = recall_at_k(&db, &queries, 20).unwrap();
+        assert!(recall > 0.0 && recall < 1.0, "recall was {}", recall);
+    }
+
+    #[test]
+    fn test_recall_rejects_bad_input() {
+        let db = grid_db();
+        assert_eq!(
+            recall_at_k(&db, &[], 10).err().unwrap(),
+            "No queries to measure recall with"
+        );
+        assert_eq!(
+            recall_at_k(&db, &[vec![1.0, 0.0, 0.0]], 0).err().unwrap(),
+            "k must be at least 1"
+        );
+        assert_eq!(
+            recall_at_k(&db, &[vec![1.0, 0.0]], 10).err().unwrap(),
+            "Wrong query dimension: expected 3, got 2"
+        );
+    }
+}
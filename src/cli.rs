@@ -1,16 +1,70 @@
-use kvdb::VecDB;
+use kvdb::{DbSnapshot, VecDB};
+use std::collections::{HashMap, VecDeque};
 use std::env;
 use std::io::{self, Write};
 
+/// Names of the built-in commands, in the order they are listed
+const BUILTIN_COMMANDS: [&str; 11] = [
+    "get", "insert", "search", "list", "count", "stats", "delete", "save", "load", "export",
+    "import",
+];
+
+/// Built-in commands that never change the database, so the REPL doesn't
+/// snapshot before running them
+const READ_ONLY_COMMANDS: [&str; 7] = ["get", "search", "list", "count", "stats", "save", "export"];
+
+/// Global flag switching search, get, list and count output to JSON
+const JSON_FLAG: &str = "--json";
+
+/// Number of snapshots the REPL keeps for `undo`; each one is a full copy of
+/// the database
+const UNDO_HISTORY: usize = 10;
+
+/// Memory the REPL may spend on snapshots for `undo`; the oldest are dropped
+/// to stay under it, and a database larger than this can't be undone at all
+const UNDO_MEMORY: usize = 256 * 1024 * 1024;
+
 pub enum Command {
     Insert { id: String, vec: Vec<f32> },
     Search { vec: Vec<f32>, k_top: usize },
     Get { id: String },
     List,
     Count,
+    Stats,
     Delete { id: String },
     Save { path: String },
     Load { path: String },
+    Export { path: String, format: FileFormat },
+    Import { path: String, format: FileFormat },
+}
+
+/// Options that apply to every command, taken from the global flags
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CommandContext {
+    /// Print results as JSON instead of human-readable text
+    pub json: bool,
+}
+
+impl CommandContext {
+    /// Takes the global flags out of `args`, wherever they appear
+    pub fn from_args(args: &[String]) -> (Self, Vec<String>) {
+        let context = CommandContext {
+            json: args.iter().any(|arg| arg == JSON_FLAG),
+        };
+        let rest = args
+            .iter()
+            .filter(|arg| *arg != JSON_FLAG)
+            .cloned()
+            .collect();
+        (context, rest)
+    }
+}
+
+/// File formats understood by `export` and `import`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Csv,
 }
 
 /// Parse a command from a provided argument vector
@@ -18,7 +72,7 @@ pub enum Command {
 pub fn parse_command_from_args(args: &[String]) -> Result<Command, String> {
     if args.len() < 2 {
         return Err(
-            "No command provided. Use: get, insert, search, list, count, delete, save, load"
+            "No command provided. Use: get, insert, search, list, count, stats, delete, save, load, export, import"
                 .to_string(),
         );
     }
@@ -31,11 +85,14 @@ pub fn parse_command_from_args(args: &[String]) -> Result<Command, String> {
         "search" => parse_search(args),
         "list" => parse_list(args),
         "count" => parse_count(args),
+        "stats" => parse_stats(args),
         "delete" => parse_delete(args),
         "save" => parse_save(args),
         "load" => parse_load(args),
+        "export" => parse_export(args),
+        "import" => parse_import(args),
         _ => Err(format!(
-            "Unknown command: {}. Available: get, insert, search, list, count, delete, save, load",
+            "Unknown command: {}. Available: get, insert, search, list, count, stats, delete, save, load, export, import",
             command
         )),
     }
@@ -150,6 +207,17 @@ fn parse_count(args: &[String]) -> Result<Command, String> {
     Ok(Command::Count)
 }
 
+/// Parse the 'stats' command
+/// Usage: kvdb stats
+fn parse_stats(args: &[String]) -> Result<Command, String> {
+    // Stats takes no arguments
+    if args.len() > 2 {
+        eprintln!("Warning: 'stats' command takes no arguments, ignoring extras");
+    }
+
+    Ok(Command::Stats)
+}
+
 /// Parse the 'delete' command
 /// Usage: kvdb delete
 fn parse_delete(args: &[String]) -> Result<Command, String> {
@@ -183,11 +251,120 @@ fn parse_load(args: &[String]) -> Result<Command, String> {
     Ok(Command::Load { path })
 }
 
+/// Parse the 'export' command
+/// Usage: kvdb export <path> [json|csv]
+fn parse_export(args: &[String]) -> Result<Command, String> {
+    if args.len() < 3 {
+        return Err(
+            "'export' command requires a file path. Usage: export <path> [json|csv]".to_string(),
+        );
+    }
+    let path = args[2].clone();
+    let format = parse_format(args.get(3))?;
+    Ok(Command::Export { path, format })
+}
+
+/// Parse the 'import' command
+/// Usage: kvdb import <path> [json|csv]
+fn parse_import(args: &[String]) -> Result<Command, String> {
+    if args.len() < 3 {
+        return Err(
+            "'import' command requires a file path. Usage: import <path> [json|csv]".to_string(),
+        );
+    }
+    let path = args[2].clone();
+    let format = parse_format(args.get(3))?;
+    Ok(Command::Import { path, format })
+}
+
+/// Parse the optional format argument of 'export' and 'import' (default json)
+fn parse_format(arg: Option<&String>) -> Result<FileFormat, String> {
+    match arg.map(String::as_str) {
+        None | Some("json") => Ok(FileFormat::Json),
+        Some("csv") => Ok(FileFormat::Csv),
+        Some(other) => Err(format!("Unknown format: '{}'. Must be json or csv.", other)),
+    }
+}
+
+/// Handler for a command. Receives the full argument vector: `args[0]` is the
+/// program name, `args[1]` the command name, and the command's own arguments follow.
+pub type CommandFn = Box<dyn Fn(&mut VecDB, &[String]) -> Result<(), String>>;
+
+/// Table of the commands understood by the REPL and single-command mode.
+///
+/// Starts out with the built-in commands; more can be added with
+/// [`register`](CommandRegistry::register) before the REPL is launched.
+pub struct CommandRegistry {
+    commands: HashMap<String, CommandFn>,
+}
+
+impl CommandRegistry {
+    /// Creates a registry holding the built-in commands
+    pub fn new() -> Self {
+        let mut registry = CommandRegistry {
+            commands: HashMap::new(),
+        };
+
+        for name in BUILTIN_COMMANDS {
+            registry.register(name, |db, args| {
+                let (context, args) = CommandContext::from_args(args);
+                let command = parse_command_from_args(&args)?;
+                execute_command(db, command, &context)
+            });
+        }
+
+        registry
+    }
+
+    /// Adds a command, replacing any existing command with the same name
+    pub fn register(
+        &mut self,
+        name: &str,
+        handler: impl Fn(&mut VecDB, &[String]) -> Result<(), String> + 'static,
+    ) {
+        self.commands.insert(name.to_string(), Box::new(handler));
+    }
+
+    /// Runs the command named by `args[1]`
+    pub fn dispatch(&self, db: &mut VecDB, args: &[String]) -> Result<(), String> {
+        if args.len() < 2 {
+            return Err(format!(
+                "No command provided. Use: {}",
+                self.names().join(", ")
+            ));
+        }
+
+        match self.commands.get(&args[1]) {
+            Some(handler) => handler(db, args),
+            None => Err(format!(
+                "Unknown command: {}. Available: {}",
+                args[1],
+                self.names().join(", ")
+            )),
+        }
+    }
+
+    /// Returns the built-in command names followed by the custom ones, sorted
+    fn names(&self) -> Vec<&str> {
+        let mut custom: Vec<&str> = self
+            .commands
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !BUILTIN_COMMANDS.contains(name))
+            .collect();
+        custom.sort();
+
+        BUILTIN_COMMANDS.iter().copied().chain(custom).collect()
+    }
+}
+
 /// REPL mode - interactive session with persistent database
-pub fn run_repl(db: &mut VecDB) {
+pub fn run_repl(db: &mut VecDB, registry: &CommandRegistry) {
     println!("KVDB - Vector Database");
     println!("Type 'help' for commands, 'exit' or 'quit' to quit\n");
 
+    let mut history = History::new();
+
     loop {
         print!("kvdb> ");
         io::stdout().flush().unwrap();
@@ -212,29 +389,126 @@ pub fn run_repl(db: &mut VecDB) {
         }
 
         if input == "help" {
-            print_help();
+            print_help(registry);
             continue;
         }
 
         let mut args: Vec<String> = vec!["kvdb".to_string()];
         args.extend(input.split_whitespace().map(|s| s.to_string()));
 
-        let command = match parse_command_from_args(&args) {
-            Ok(cmd) => cmd,
-            Err(error) => {
-                eprintln!("Error: {}", error);
-                continue;
-            }
-        };
+        if let Err(error) = run_repl_line(registry, db, &mut history, &args) {
+            eprintln!("Error: {}", error);
+        }
+    }
+}
+
+/// Stack of snapshots behind the REPL's `snapshot` and `undo`, bounded both
+/// in count and in memory
+struct History {
+    snapshots: VecDeque<DbSnapshot>,
+    /// Memory held by `snapshots`
+    bytes: usize,
+    /// Most memory the snapshots may hold
+    budget: usize,
+}
+
+impl History {
+    fn new() -> Self {
+        History {
+            snapshots: VecDeque::new(),
+            bytes: 0,
+            budget: UNDO_MEMORY,
+        }
+    }
+
+    /// Remembers the current state, forgetting the oldest ones to make room.
+    /// A database over the whole budget clears the history instead, since
+    /// undo would otherwise skip back past this change; returns false then
+    fn push(&mut self, db: &VecDB) -> bool {
+        let size = db.memory_bytes();
+        if size > self.budget {
+            self.snapshots.clear();
+            self.bytes = 0;
+            return false;
+        }
 
-        execute_command(db, command);
+        while self.snapshots.len() == UNDO_HISTORY || self.bytes + size > self.budget {
+            self.pop_front();
+        }
+        self.snapshots.push_back(db.snapshot_memory());
+        self.bytes += size;
+        true
+    }
+
+    /// Forgets the oldest snapshot
+    fn pop_front(&mut self) {
+        if let Some(snapshot) = self.snapshots.pop_front() {
+            self.bytes -= snapshot.memory_bytes();
+        }
+    }
+
+    /// Takes the most recent snapshot off the stack
+    fn pop_back(&mut self) -> Option<DbSnapshot> {
+        let snapshot = self.snapshots.pop_back()?;
+        self.bytes -= snapshot.memory_bytes();
+        Some(snapshot)
+    }
+
+    /// Restores the most recent snapshot
+    fn undo(&mut self, db: &mut VecDB) -> Result<(), String> {
+        let snapshot = self
+            .pop_back()
+            .ok_or_else(|| "Nothing to undo".to_string())?;
+        db.restore(snapshot);
+        Ok(())
+    }
+}
+
+/// Runs one REPL line. `snapshot` and `undo` work on the history; any other
+/// command goes through the registry, snapshotting first unless it is read-only
+fn run_repl_line(
+    registry: &CommandRegistry,
+    db: &mut VecDB,
+    history: &mut History,
+    args: &[String],
+) -> Result<(), String> {
+    match args.get(1).map(String::as_str) {
+        Some("snapshot") => {
+            if !history.push(db) {
+                return Err("Database is too large to snapshot".to_string());
+            }
+            println!("Snapshot taken ({} vectors)", db.count());
+            Ok(())
+        }
+        Some("undo") => {
+            history.undo(db)?;
+            println!("Undone ({} vectors)", db.count());
+            Ok(())
+        }
+        Some(name) if !READ_ONLY_COMMANDS.contains(&name) => {
+            let saved = history.push(db);
+            if !saved {
+                eprintln!(
+                    "Warning: database is too large to snapshot, this change can't be undone"
+                );
+            }
+            let result = registry.dispatch(db, args);
+            if result.is_err() && saved {
+                // Nothing changed, so there is nothing to undo
+                history.pop_back();
+            }
+            result
+        }
+        _ => registry.dispatch(db, args),
     }
 }
 
 /// Single-command mode - load db from path, execute command, save back
 /// Usage: kvdb <db_path> <command> [args...]
-pub fn run_single_command() {
-    let args: Vec<String> = env::args().collect();
+pub fn run_single_command(registry: &CommandRegistry) {
+    // Global flags may come before the db path, so take them out first and
+    // hand them on to the command
+    let (context, args) = CommandContext::from_args(&env::args().collect::<Vec<_>>());
 
     if args.len() < 3 {
         eprintln!("Usage: kvdb <db_path> <command> [args...]");
@@ -257,19 +531,17 @@ pub fn run_single_command() {
     };
 
     // Rebuild args: shift so args[1] becomes the command
-    let shifted_args: Vec<String> = std::iter::once(args[0].clone())
+    let mut shifted_args: Vec<String> = std::iter::once(args[0].clone())
         .chain(args[2..].iter().cloned())
         .collect();
+    if context.json {
+        shifted_args.push(JSON_FLAG.to_string());
+    }
 
-    let command = match parse_command_from_args(&shifted_args) {
-        Ok(cmd) => cmd,
-        Err(error) => {
-            eprintln!("Error: {}", error);
-            std::process::exit(1);
-        }
-    };
-
-    execute_command(&mut db, command);
+    if let Err(error) = registry.dispatch(&mut db, &shifted_args) {
+        eprintln!("Error: {}", error);
+        std::process::exit(1);
+    }
 
     // Save db back to path
     if let Err(e) = db.save(db_path) {
@@ -278,11 +550,24 @@ pub fn run_single_command() {
     }
 }
 
-fn execute_command(db: &mut VecDB, command: Command) {
+/// Runs a parsed built-in command, printing its output.
+/// Failures are returned instead of printed, so the caller knows nothing changed
+fn execute_command(
+    db: &mut VecDB,
+    command: Command,
+    context: &CommandContext,
+) -> Result<(), String> {
+    if context.json
+        && let Some(output) = json_output(db, &command)
+    {
+        println!("{}", output?);
+        return Ok(());
+    }
+
     match command {
         Command::Get { id } => match db.get(&id) {
             Some(vector) => println!("Vector '{}': {:?}", id, vector),
-            None => eprintln!("Error: Vector '{}' not found", id),
+            None => return Err(format!("Vector '{}' not found", id)),
         },
 
         Command::List => {
@@ -300,62 +585,397 @@ fn execute_command(db: &mut VecDB, command: Command) {
 
         Command::Count => println!("{}", db.count()),
 
-        Command::Insert { id, vec } => match db.insert(id.clone(), vec) {
-            Ok(message) => println!("{}", message),
-            Err(error) => eprintln!("Error: {}", error),
-        },
+        Command::Stats => {
+            let stats = db.stats();
+            println!("Count: {}", stats.count);
+            match stats.dimension {
+                Some(dimension) => println!("Dimension: {}", dimension),
+                None => println!("Dimension: none"),
+            }
+            let file = db
+                .persisted_path()
+                .and_then(|path| std::fs::metadata(&path).ok().map(|meta| (path, meta.len())));
+            if let Some((path, size)) = file {
+                println!("File: {} ({} bytes)", path, size);
+            }
+            println!(
+                "Norm: mean {:.4}, min {:.4}, max {:.4}",
+                stats.mean_norm, stats.min_norm, stats.max_norm
+            );
+        }
 
-        Command::Search { vec, k_top } => match db.search(vec, k_top) {
-            Ok(results) => {
-                if results.is_empty() {
-                    println!("No results found");
-                } else {
-                    println!("Top {} results:", results.len());
-                    for (rank, (id, vector, score)) in results.iter().enumerate() {
-                        println!(
-                            "{}. ID: {}, Score: {:.4}, Vector: {:?}",
-                            rank + 1,
-                            id,
-                            score,
-                            vector
-                        );
-                    }
+        Command::Insert { id, vec } => println!("{}", db.insert(id, vec)?),
+
+        Command::Search { vec, k_top } => {
+            let results = db.search(vec, k_top)?;
+            if results.is_empty() {
+                println!("No results found");
+            } else {
+                println!("Top {} results:", results.len());
+                for (rank, result) in results.iter().enumerate() {
+                    println!(
+                        "{}. ID: {}, Score: {:.4}, Vector: {:?}",
+                        rank + 1,
+                        result.id,
+                        result.score,
+                        result.vector
+                    );
                 }
             }
-            Err(error) => eprintln!("Error: {}", error),
-        },
+        }
 
-        Command::Delete { id } => match db.delete(&id) {
-            Ok(message) => println!("{}", message),
-            Err(error) => eprintln!("Error: {}", error),
-        },
+        Command::Delete { id } => println!("{}", db.delete(&id)?),
 
-        Command::Save { path } => match db.save(&path) {
-            Ok(()) => println!("Database saved to '{}'", path),
-            Err(error) => eprintln!("Error: {}", error),
-        },
+        Command::Save { path } => {
+            db.save(&path)?;
+            println!("Database saved to '{}'", path);
+        }
 
-        Command::Load { path } => match VecDB::load(&path) {
-            Ok(loaded_db) => {
-                let count = loaded_db.count();
-                *db = loaded_db;
-                println!("Database loaded from '{}' ({} vectors)", path, count);
+        Command::Load { path } => {
+            let loaded_db = VecDB::load(&path)?;
+            let count = loaded_db.count();
+            *db = loaded_db;
+            println!("Database loaded from '{}' ({} vectors)", path, count);
+        }
+
+        Command::Export { path, format } => {
+            match format {
+                FileFormat::Json => db.export_json(&path)?,
+                FileFormat::Csv => db.export_csv(&path)?,
             }
-            Err(error) => eprintln!("Error: {}", error),
-        },
+            println!("Database exported to '{}'", path);
+        }
+
+        Command::Import { path, format } => {
+            let imported_db = match format {
+                FileFormat::Json => VecDB::import_json(&path)?,
+                FileFormat::Csv => VecDB::import_csv(&path, false)?,
+            };
+            let count = imported_db.count();
+            *db = imported_db;
+            println!("Database imported from '{}' ({} vectors)", path, count);
+        }
     }
+
+    Ok(())
+}
+
+/// Renders the result of a search, get, list or count as JSON.
+/// Returns `None` for the other commands, which keep their text output
+fn json_output(db: &VecDB, command: &Command) -> Option<Result<String, String>> {
+    let value = match command {
+        Command::Search { vec, k_top } => match db.search(vec.clone(), *k_top) {
+            Ok(results) => serde_json::json!(results),
+            Err(error) => return Some(Err(error)),
+        },
+        Command::Get { id } => match db.get(id) {
+            Some(vector) => serde_json::json!({"id": id, "vector": vector}),
+            None => return Some(Err(format!("Vector '{}' not found", id))),
+        },
+        Command::List => serde_json::Value::Array(
+            db.iter()
+                .map(|(id, vector)| serde_json::json!({"id": id, "vector": vector}))
+                .collect(),
+        ),
+        Command::Count => serde_json::json!({"count": db.count()}),
+        _ => return None,
+    };
+    Some(Ok(value.to_string()))
 }
 
-fn print_help() {
+fn print_help(registry: &CommandRegistry) {
     println!("Available commands:");
     println!("  insert <id> <v1> <v2> ...        - Insert a vector");
     println!("  search <v1> <v2> ... [--k_top N] - Search for similar vectors (default k=5)");
     println!("  get <id>                         - Retrieve a vector by ID");
     println!("  list                             - List all vectors");
     println!("  count                            - Show vector count");
+    println!("  stats                            - Show count, dimension, file size and norms");
     println!("  delete <id>                      - Delete a vector");
     println!("  save <path>                      - Save database to file");
     println!("  load <path>                      - Load database from file");
+    println!("  export <path> [json|csv]         - Write all vectors to a JSON or CSV file");
+    println!("  import <path> [json|csv]         - Replace the database with a JSON or CSV file");
+    println!("  snapshot                         - Remember the current state for undo");
+    println!("  undo                             - Revert the last change (REPL only)");
     println!("  help                             - Show this help");
     println!("  exit, quit                       - Exit the program");
+    println!("Add --json to search, get, list or count for JSON output");
+
+    let custom: Vec<&str> = registry
+        .names()
+        .into_iter()
+        .filter(|name| !BUILTIN_COMMANDS.contains(name))
+        .collect();
+    if !custom.is_empty() {
+        println!("Custom commands: {}", custom.join(", "));
+    }
+}
+
+#[cfg(test)]
+mod cli_test {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    fn args(line: &str) -> Vec<String> {
+        std::iter::once("kvdb")
+            .chain(line.split_whitespace())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_custom_command_receives_args() {
+        let seen: Rc<RefCell<Vec<String>>> = Rc::new(RefCell::new(Vec::new()));
+        let mut registry = CommandRegistry::new();
+        let recorder = Rc::clone(&seen);
+        registry.register("norms", move |db, args| {
+            recorder.borrow_mut().extend(args.iter().cloned());
+            db.insert(args[2].clone(), vec![1.0, 0.0])?;
+            Ok(())
+        });
+
+        let mut db = VecDB::new();
+        registry
+            .dispatch(&mut db, &args("norms vec9 --top 3"))
+            .unwrap();
+
+        assert_eq!(*seen.borrow(), args("norms vec9 --top 3"));
+        assert!(db.contains("vec9"));
+    }
+
+    #[test]
+    fn test_builtin_commands_still_dispatch() {
+        let registry = CommandRegistry::new();
+        let mut db = VecDB::new();
+
+        registry
+            .dispatch(&mut db, &args("insert vec1 1.0 0.0"))
+            .unwrap();
+        assert_eq!(db.count(), 1);
+
+        assert!(registry.dispatch(&mut db, &args("insert vec2")).is_err());
+        let err = registry.dispatch(&mut db, &args("frobnicate")).unwrap_err();
+        assert!(err.contains("Unknown command: frobnicate"));
+    }
+
+    #[test]
+    fn test_parse_stats() {
+        assert!(matches!(
+            parse_command_from_args(&args("stats")),
+            Ok(Command::Stats)
+        ));
+        // Extra arguments are ignored with a warning
+        assert!(matches!(
+            parse_command_from_args(&args("stats --verbose 3")),
+            Ok(Command::Stats)
+        ));
+    }
+
+    #[test]
+    fn test_parse_export() {
+        assert!(matches!(
+            parse_command_from_args(&args("export out.json")),
+            Ok(Command::Export { path, format: FileFormat::Json }) if path == "out.json"
+        ));
+        assert!(matches!(
+            parse_command_from_args(&args("export out.csv csv")),
+            Ok(Command::Export { path, format: FileFormat::Csv }) if path == "out.csv"
+        ));
+
+        let err = parse_command_from_args(&args("export")).err().unwrap();
+        assert!(err.starts_with("'export' command requires a file path"));
+        let err = parse_command_from_args(&args("export out.xml xml"))
+            .err()
+            .unwrap();
+        assert_eq!(err, "Unknown format: 'xml'. Must be json or csv.");
+    }
+
+    #[test]
+    fn test_parse_import() {
+        assert!(matches!(
+            parse_command_from_args(&args("import in.json json")),
+            Ok(Command::Import { path, format: FileFormat::Json }) if path == "in.json"
+        ));
+        assert!(matches!(
+            parse_command_from_args(&args("import in.csv csv")),
+            Ok(Command::Import { path, format: FileFormat::Csv }) if path == "in.csv"
+        ));
+
+        let err = parse_command_from_args(&args("import")).err().unwrap();
+        assert!(err.starts_with("'import' command requires a file path"));
+        assert!(parse_command_from_args(&args("import in.tsv tsv")).is_err());
+    }
+
+    #[test]
+    fn test_export_then_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.csv");
+        let path_str = path.to_str().unwrap();
+        let registry = CommandRegistry::new();
+
+        let mut db = VecDB::new();
+        registry
+            .dispatch(&mut db, &args("insert vec1 3.0 4.0"))
+            .unwrap();
+        registry
+            .dispatch(&mut db, &args(&format!("export {} csv", path_str)))
+            .unwrap();
+
+        let mut imported = VecDB::new();
+        registry
+            .dispatch(&mut imported, &args(&format!("import {} csv", path_str)))
+            .unwrap();
+        assert_eq!(imported.list(), db.list());
+    }
+
+    #[test]
+    fn test_json_flag_is_taken_out() {
+        let (context, rest) = CommandContext::from_args(&args("search 1.0 0.0 --json"));
+        assert!(context.json);
+        assert_eq!(rest, args("search 1.0 0.0"));
+
+        let (context, rest) = CommandContext::from_args(&args("count"));
+        assert!(!context.json);
+        assert_eq!(rest, args("count"));
+    }
+
+    #[test]
+    fn test_search_json_output() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let (context, rest) = CommandContext::from_args(&args("search 1.0 0.0 --json"));
+        assert!(context.json);
+        let command = parse_command_from_args(&rest).unwrap();
+        let output = json_output(&db, &command).unwrap().unwrap();
+
+        let json: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(json.as_array().unwrap().len(), 2);
+        assert_eq!(json[0]["id"], "vec1");
+        assert!((json[0]["score"].as_f64().unwrap() - 1.0).abs() < 1e-6);
+
+        let command = parse_command_from_args(&args("count")).unwrap();
+        assert_eq!(
+            json_output(&db, &command).unwrap().unwrap(),
+            r#"{"count":2}"#
+        );
+        let command = parse_command_from_args(&args("get missing")).unwrap();
+        assert!(json_output(&db, &command).unwrap().is_err());
+        let command = parse_command_from_args(&args("stats")).unwrap();
+        assert!(json_output(&db, &command).is_none());
+    }
+
+    #[test]
+    fn test_undo_reverts_last_change() {
+        let registry = CommandRegistry::new();
+        let mut history = History::new();
+        let mut db = VecDB::new();
+
+        run_repl_line(
+            &registry,
+            &mut db,
+            &mut history,
+            &args("insert vec1 1.0 0.0"),
+        )
+        .unwrap();
+        run_repl_line(
+            &registry,
+            &mut db,
+            &mut history,
+            &args("insert vec2 0.0 1.0"),
+        )
+        .unwrap();
+        run_repl_line(&registry, &mut db, &mut history, &args("count")).unwrap();
+
+        run_repl_line(&registry, &mut db, &mut history, &args("undo")).unwrap();
+        assert_eq!(db.count(), 1);
+        assert!(!db.contains("vec2"));
+
+        run_repl_line(&registry, &mut db, &mut history, &args("undo")).unwrap();
+        assert_eq!(db.count(), 0);
+        let err = run_repl_line(&registry, &mut db, &mut history, &args("undo")).unwrap_err();
+        assert_eq!(err, "Nothing to undo");
+    }
+
+    #[test]
+    fn test_failed_command_leaves_nothing_to_undo() {
+        let registry = CommandRegistry::new();
+        let mut history = History::new();
+        let mut db = VecDB::new();
+
+        run_repl_line(
+            &registry,
+            &mut db,
+            &mut history,
+            &args("insert vec1 1.0 0.0"),
+        )
+        .unwrap();
+        // Wrong dimension: the insert fails and its snapshot is dropped
+        let err = run_repl_line(
+            &registry,
+            &mut db,
+            &mut history,
+            &args("insert vec9 1.0 2.0 3.0"),
+        )
+        .unwrap_err();
+        assert_eq!(err, "Different dimension");
+        assert!(run_repl_line(&registry, &mut db, &mut history, &args("delete missing")).is_err());
+
+        run_repl_line(&registry, &mut db, &mut history, &args("undo")).unwrap();
+        assert_eq!(db.count(), 0);
+    }
+
+    #[test]
+    fn test_undo_history_is_bounded() {
+        let registry = CommandRegistry::new();
+        let mut history = History::new();
+        let mut db = VecDB::new();
+
+        for i in 0..UNDO_HISTORY + 5 {
+            let line = format!("insert vec{} 1.0 0.0", i);
+            run_repl_line(&registry, &mut db, &mut history, &args(&line)).unwrap();
+        }
+        assert_eq!(history.snapshots.len(), UNDO_HISTORY);
+
+        while history.undo(&mut db).is_ok() {}
+        assert_eq!(db.count(), 5);
+    }
+
+    #[test]
+    fn test_undo_history_is_bounded_by_memory() {
+        let registry = CommandRegistry::new();
+        let mut db = VecDB::new();
+        run_repl_line(
+            &registry,
+            &mut db,
+            &mut History::new(),
+            &args("insert vec0 1.0 0.0"),
+        )
+        .unwrap();
+        let mut history = History {
+            budget: 9 * db.memory_bytes() + db.memory_bytes() / 2,
+            ..History::new()
+        };
+
+        // Snapshots grow with the database, so only the newest two fit
+        for i in 1..6 {
+            let line = format!("insert vec{} 1.0 0.0", i);
+            run_repl_line(&registry, &mut db, &mut history, &args(&line)).unwrap();
+        }
+        assert_eq!(history.snapshots.len(), 2);
+        assert!(history.bytes <= history.budget);
+        while history.undo(&mut db).is_ok() {}
+        assert_eq!(db.count(), 4);
+        assert_eq!(history.bytes, 0);
+
+        // A database over the whole budget can't be snapshotted
+        history.budget = db.memory_bytes() - 1;
+        let err = run_repl_line(&registry, &mut db, &mut history, &args("snapshot")).unwrap_err();
+        assert_eq!(err, "Database is too large to snapshot");
+        run_repl_line(&registry, &mut db, &mut history, &args("delete vec0")).unwrap();
+        assert!(history.snapshots.is_empty());
+    }
 }
@@ -1,18 +1,547 @@
 //! The database module
 //! Provide CRUD method for the vector database
 
-use crate::vector::{dot_product, l2_norm};
+use crate::index::{Hnsw, IndexKind, Ivf};
+use crate::vector::{
+    Metric, check_finite, check_finite_f64, dequantize_binary, dequantize_int8, euclidean_distance,
+    hamming_distance, l2_norm, l2_norm_f64, quantize_binary, quantize_int8,
+};
+use crate::wal::{self, WalRecord};
+use bincode::Options;
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
 use serde::{Deserialize, Serialize};
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{BTreeMap, BinaryHeap, HashMap, HashSet},
     fs::File,
-    io::{BufReader, BufWriter},
+    io::{BufRead, BufReader, BufWriter, Read, Write},
+    sync::{Mutex, RwLock},
 };
 
+/// Free-form payload stored alongside a vector, e.g. `{"source": "wiki", "lang": "en"}`.
+pub type Metadata = HashMap<String, serde_json::Value>;
+
+/// A single match returned by [`VecDB::search`] and the other search methods.
+///
+/// [`VecDB::search_f64`] returns `SearchResult<f64>`, with the vector and
+/// score in full precision.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult<T = f32> {
+    /// ID of the matched vector
+    pub id: String,
+    /// The stored (normalized) vector
+    pub vector: Vec<T>,
+    /// Similarity score, or distance under a distance metric
+    pub score: T,
+}
+
+impl From<(String, Vec<f32>, f32)> for SearchResult {
+    fn from((id, vector, score): (String, Vec<f32>, f32)) -> Self {
+        SearchResult { id, vector, score }
+    }
+}
+
+/// Search results, best first.
+type SearchResults = Vec<SearchResult>;
+
+/// Search results, each paired with that result's own nearest neighbors.
+type ExpandedResults = Vec<(SearchResult, SearchResults)>;
+
+/// Search results, each paired with its squared L2 distance to the query.
+type L2Results = Vec<(SearchResult, f32)>;
+
+/// The bincode configuration used for every file kvdb writes.
+///
+/// Pinned explicitly (fixed-width little-endian integers, trailing bytes
+/// allowed) instead of relying on library defaults, so files stay portable
+/// across platforms and bincode versions. It matches what `bincode::serialize`
+/// produces, which is how kvdb 0.3 wrote its headerless files.
+pub(crate) fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .with_little_endian()
+        .allow_trailing_bytes()
+}
+
+/// (De)serializes metadata payloads.
+///
+/// JSON values can't be read back by bincode (it doesn't support self-describing
+/// types), so binary formats store each payload as a JSON string. JSON files
+/// embed the payloads as plain objects.
+mod metadata_format {
+    use super::Metadata;
+    use serde::{
+        Deserialize, Deserializer, Serialize, Serializer, de::Error as _, ser::Error as _,
+    };
+    use std::collections::BTreeMap;
+
+    pub fn serialize<S: Serializer>(
+        metadata: &BTreeMap<String, Metadata>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return metadata.serialize(serializer);
+        }
+        let encoded = metadata
+            .iter()
+            .map(|(id, meta)| Ok((id, serde_json::to_string(meta).map_err(S::Error::custom)?)))
+            .collect::<Result<BTreeMap<_, _>, S::Error>>()?;
+        encoded.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<BTreeMap<String, Metadata>, D::Error> {
+        if deserializer.is_human_readable() {
+            return BTreeMap::deserialize(deserializer);
+        }
+        BTreeMap::<String, String>::deserialize(deserializer)?
+            .into_iter()
+            .map(|(id, json)| Ok((id, serde_json::from_str(&json).map_err(D::Error::custom)?)))
+            .collect()
+    }
+}
+
+/// Performance counters collected during a search.
+///
+/// Returned by [`VecDB::search_instrumented`] to help tune and validate the
+/// search loop.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SearchMetrics {
+    /// Number of similarity computations (dot products) performed
+    pub comparisons: usize,
+    /// Number of candidates that entered the top-k result set
+    pub heap_updates: usize,
+}
+
+/// Heap entry for a scored vector, ordered by score (ties broken by index).
+#[derive(PartialEq)]
+struct Candidate {
+    score: f32,
+    index: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then(self.index.cmp(&other.index))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Normalization applied to IDs before they are stored or looked up.
+///
+/// Helps when ingestion produces IDs with stray whitespace or inconsistent
+/// case, which would otherwise create duplicates and failed lookups. The
+/// policy is saved with the database.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IdPolicy {
+    /// Strip leading and trailing whitespace
+    pub trim: bool,
+    /// Convert to lowercase
+    pub lowercase: bool,
+}
+
+/// Magic bytes starting every bincode file (inside the gzip stream for
+/// [`Format::BincodeGz`]).
+const MAGIC: &[u8; 4] = b"KVDB";
+
+/// Version of the bincode file layout written by this build.
+///
+/// Bincode files start with a 16-byte header: [`MAGIC`], this version as a
+/// little-endian `u32`, and the dimension as a little-endian `u64` (0 for an
+/// empty database). Loading a file with any other version fails cleanly
+/// instead of misreading its fields. Files without the header, written by
+/// kvdb 0.3 before versioning, are read as version 0.
+pub const FORMAT_VERSION: u32 = 4;
+
+/// Size of the bincode file header.
+const HEADER_LEN: usize = 16;
+
+/// On-disk format used by [`VecDB::save_as`] and [`VecDB::load_as`].
+///
+/// [`VecDB::load`] detects the format from the first bytes of the file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// Compact binary encoding (the default)
+    #[default]
+    Bincode,
+    /// Human-readable JSON
+    Json,
+    /// Bincode compressed with gzip
+    BincodeGz,
+}
+
+impl Format {
+    /// Guesses the format of a saved database from its first bytes.
+    ///
+    /// Bincode files start with [`MAGIC`], gzip files with `1f 8b` and JSON
+    /// with `{`. Anything else is read as a headerless bincode file.
+    fn detect(bytes: &[u8]) -> Format {
+        if bytes.starts_with(MAGIC) {
+            Format::Bincode
+        } else if bytes.starts_with(&[0x1f, 0x8b]) {
+            Format::BincodeGz
+        } else if bytes.first() == Some(&b'{') {
+            Format::Json
+        } else {
+            Format::Bincode
+        }
+    }
+}
+
+/// How stored vectors are held in memory.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quantization {
+    /// Full-precision `f32` components
+    #[default]
+    None,
+    /// One `i8` per component plus a per-vector `f32` scale, about 4× smaller;
+    /// vectors are dequantized when read or searched
+    Int8,
+    /// One sign bit per component, 32× smaller, searched by Hamming distance.
+    /// Only the signs survive, so recall drops sharply; vectors read back as
+    /// unit vectors with the stored signs
+    Binary,
+}
+
+/// Floating-point precision of stored vectors.
+///
+/// Only applies without quantization: quantized vectors are held as their
+/// codes either way.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Precision {
+    /// `f32` components
+    #[default]
+    F32,
+    /// `f64` components, twice the memory of `f32`. [`VecDB::insert_f64`],
+    /// [`VecDB::get_f64`] and [`VecDB::search_f64`] work in full precision;
+    /// every other method sees the vectors rounded to `f32`
+    F64,
+}
+
+/// How the vectors of a database are laid out, from its quantization and
+/// precision.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Storage {
+    F32,
+    F64,
+    Int8,
+    Binary,
+}
+
+/// Settings that decide how vectors are stored and scored.
+///
+/// Saved with the database, so a loaded database searches exactly like the one
+/// that was saved. Databases can only be combined (see [`VecDB::merge`] and
+/// [`search_multi`]) when their configurations are equal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DbConfig {
+    /// Metric used to score and rank vectors
+    pub metric: Metric,
+    /// Whether the cosine metric L2-normalizes vectors and queries
+    pub normalize: bool,
+    /// How vectors are held in memory
+    pub quantization: Quantization,
+    /// Precision of unquantized vectors
+    pub precision: Precision,
+}
+
+impl Default for DbConfig {
+    fn default() -> Self {
+        DbConfig {
+            metric: Metric::default(),
+            normalize: true,
+            quantization: Quantization::default(),
+            precision: Precision::default(),
+        }
+    }
+}
+
+impl DbConfig {
+    /// Returns the storage layout these settings select.
+    fn storage(&self) -> Storage {
+        match (self.quantization, self.precision) {
+            (Quantization::None, Precision::F32) => Storage::F32,
+            (Quantization::None, Precision::F64) => Storage::F64,
+            (Quantization::Int8, _) => Storage::Int8,
+            (Quantization::Binary, _) => Storage::Binary,
+        }
+    }
+}
+
+/// Changes made by [`VecDB::repair`] to restore storage consistency.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RepairReport {
+    /// IDs dropped because they had no complete vector
+    pub dropped_ids: Vec<String>,
+    /// Number of trailing `f32` values dropped because no ID owned them
+    pub truncated_values: usize,
+}
+
+/// Summary of a database, returned by [`VecDB::stats`].
+///
+/// Under the cosine metric every stored vector is normalized, so the norms
+/// should all be close to 1.0; a large deviation points to corrupted storage.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DbStats {
+    /// Number of vectors
+    pub count: usize,
+    /// Dimension of the vectors, or `None` if the database is empty
+    pub dimension: Option<usize>,
+    /// Smallest L2 norm of a stored vector (0.0 if empty)
+    pub min_norm: f32,
+    /// Mean L2 norm of the stored vectors (0.0 if empty)
+    pub mean_norm: f32,
+    /// Largest L2 norm of a stored vector (0.0 if empty)
+    pub max_norm: f32,
+}
+
+/// Layout of the headerless bincode files written by kvdb 0.3 (version 0).
+#[derive(Deserialize)]
+struct LegacyDb {
+    ids: Vec<String>,
+    vectors: Vec<f32>,
+    dimension: Option<usize>,
+}
+
+/// The file a database was last loaded from or saved to.
+#[derive(Debug, Clone, PartialEq)]
+struct Persisted {
+    path: String,
+    /// How many of the vectors the file (with its WAL) holds, or `None` once
+    /// the database changed in a way an append can't express
+    count: Option<usize>,
+}
+
+/// One vector in the file written by [`VecDB::export_json`].
+#[derive(Serialize, Deserialize)]
+struct ExportEntry {
+    id: String,
+    values: Vec<f32>,
+}
+
+/// An in-memory copy of a database, taken by [`VecDB::snapshot_memory`] and
+/// brought back with [`VecDB::restore`].
+pub struct DbSnapshot(VecDB);
+
+impl DbSnapshot {
+    /// Returns roughly how many bytes of heap memory the snapshot holds, as
+    /// [`VecDB::memory_bytes`] does.
+    pub fn memory_bytes(&self) -> usize {
+        self.0.memory_bytes()
+    }
+}
+
+/// Iterator over `(id, vector)` pairs, returned by [`VecDB::iter`] and by
+/// iterating over `&VecDB`.
+pub struct Iter<'a> {
+    db: &'a VecDB,
+    next: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, Cow<'a, [f32]>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.db.ids.get(self.next)?;
+        let vector = self.db.get_vector(self.next);
+        self.next += 1;
+        Some((id.as_str(), vector))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = self.db.ids.len() - self.next;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// Returns a stable 64-bit hash of an ID (64-bit FNV-1a over its UTF-8 bytes).
+///
+/// Unlike `std`'s `DefaultHasher`, the result is the same on every run and
+/// platform, so clients can key on it instead of the string ID.
+///
+/// # Examples
+///
+/// ```
+/// use kvdb::id_hash;
+///
+/// assert_eq!(id_hash("a"), 0xaf63dc4c8601ec8c);
+/// ```
+pub fn id_hash(id: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    id.bytes().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// Appends a vector to a saved database without loading it.
+///
+/// The vector is written as an insert record to the `<path>.wal` sidecar, so
+/// the cost doesn't depend on the size of the database. It shows up, together
+/// with the existing vectors, the next time the file is loaded with
+/// [`VecDB::load`]. Only the vector itself is checked here; a dimension
+/// mismatch is reported when the WAL is replayed.
+///
+/// # Arguments
+///
+/// * `path` - Path of an existing database file
+/// * `id` - Unique identifier for the vector
+/// * `vector` - Vector to insert (normalized on load)
+///
+/// # Returns
+///
+/// * `Ok(())` - The record was appended
+/// * `Err(String)` - Error if the file doesn't exist, the vector is empty or
+///   not finite, or the WAL cannot be written
+///
+/// # Examples
+///
+/// ```no_run
+/// use kvdb::{VecDB, append_to_file};
+///
+/// append_to_file("my_database.db", "doc42".to_string(), vec![0.1, 0.2, 0.3]).unwrap();
+///
+/// let db = VecDB::load("my_database.db").unwrap();
+/// assert!(db.contains("doc42"));
+/// ```
+pub fn append_to_file(path: &str, id: String, vector: Vec<f32>) -> Result<(), String> {
+    if !std::path::Path::new(path).exists() {
+        return Err("File not found!".to_string());
+    }
+    if vector.is_empty() {
+        return Err("Cannot insert an empty vector".to_string());
+    }
+    check_finite(&vector)?;
+
+    wal::append(path, &WalRecord::Insert { id, vector })
+}
+
+/// Searches several databases and merges the results into one global top-k.
+///
+/// Meant for sharded setups where vectors are split across databases. All
+/// non-empty databases must share the same dimension, metric and
+/// normalization setting; empty ones are skipped. Results are ordered best
+/// first, as in [`VecDB::search`].
+///
+/// # Arguments
+///
+/// * `dbs` - Databases to search
+/// * `query` - Query vector
+/// * `top_k` - Number of results to return in total
+///
+/// # Returns
+///
+/// * `Ok(Vec<SearchResult>)` - The best matches across all databases
+/// * `Err(String)` - Error if every database is empty, the databases are
+///   configured differently, or the query is invalid
+///
+/// # Examples
+///
+/// ```
+/// use kvdb::{VecDB, search_multi};
+///
+/// let mut shard1 = VecDB::new();
+/// shard1.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+/// let mut shard2 = VecDB::new();
+/// shard2.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+///
+/// let results = search_multi(&[&shard1, &shard2], &[0.1, 1.0], 1).unwrap();
+/// assert_eq!(results[0].id, "vec2");
+/// ```
+pub fn search_multi(dbs: &[&VecDB], query: &[f32], top_k: usize) -> Result<SearchResults, String> {
+    let shards: Vec<&VecDB> = dbs.iter().copied().filter(|db| db.count() > 0).collect();
+    let Some(first) = shards.first() else {
+        return Err("Empty database".to_string());
+    };
+
+    for db in &shards[1..] {
+        if db.dimension != first.dimension {
+            return Err(format!(
+                "Different dimension across databases: {:?} and {:?}",
+                first.dimension, db.dimension
+            ));
+        }
+        if db.config != first.config {
+            return Err("Databases use different configurations".to_string());
+        }
+    }
+
+    let mut merged = Vec::new();
+    for db in &shards {
+        merged.extend(db.search_with_metrics(
+            query.to_vec(),
+            top_k,
+            &mut SearchMetrics::default(),
+        )?);
+    }
+
+    let metric = first.config.metric;
+    merged.sort_by(|a, b| {
+        metric
+            .rank_key(b.score)
+            .total_cmp(&metric.rank_key(a.score))
+    });
+    merged.truncate(top_k);
+
+    Ok(merged)
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct VecDB {
     ids: Vec<String>,
     vectors: Vec<f32>,
+    /// Int8 codes and per-vector scales, used instead of `vectors` under
+    /// [`Quantization::Int8`]
+    codes: Vec<i8>,
+    scales: Vec<f32>,
+    /// Packed sign bits, used instead of `vectors` under [`Quantization::Binary`]
+    bits: Vec<u64>,
+    /// Full-precision components, used instead of `vectors` under [`Precision::F64`]
+    wide: Vec<f64>,
     dimension: Option<usize>,
+    config: DbConfig,
+    id_policy: IdPolicy,
+    /// Payloads by ID; only IDs that have one are present. Ordered so the
+    /// saved bytes don't depend on hash order
+    #[serde(with = "metadata_format")]
+    metadata: BTreeMap<String, Metadata>,
+    /// Approximate index used by `search`, if any
+    index_kind: Option<IndexKind>,
+    /// Centroids and assignments under [`IndexKind::Ivf`]
+    ivf: Option<Ivf>,
+    /// Custom normalizer applied after `id_policy`; functions can't be saved,
+    /// so it must be set again after loading
+    #[serde(skip)]
+    id_normalizer: Option<fn(&str) -> String>,
+    /// Maximum number of vectors before the oldest is evicted; like the
+    /// normalizer, it is not saved
+    #[serde(skip)]
+    capacity: Option<usize>,
+    /// The index graph, `None` until built or after a change it can't absorb.
+    /// Behind a lock so a search can rebuild it while only borrowing the database
+    #[serde(skip)]
+    graph: RwLock<Option<Hnsw>>,
+    /// ID to storage index, for O(1) lookups; rebuilt on load instead of saved
+    #[serde(skip)]
+    index: HashMap<String, usize>,
+    /// Set by save and load so `save_append` can write only newer inserts.
+    /// Behind a mutex because saving only borrows the database
+    #[serde(skip)]
+    persisted: Mutex<Option<Persisted>>,
 }
 
 impl Default for VecDB {
@@ -39,727 +568,7127 @@ impl VecDB {
         VecDB {
             ids: Vec::new(),
             vectors: Vec::new(),
+            codes: Vec::new(),
+            scales: Vec::new(),
+            bits: Vec::new(),
+            wide: Vec::new(),
             dimension: None,
+            config: DbConfig::default(),
+            id_policy: IdPolicy::default(),
+            metadata: BTreeMap::new(),
+            id_normalizer: None,
+            capacity: None,
+            index_kind: None,
+            ivf: None,
+            graph: RwLock::new(None),
+            index: HashMap::new(),
+            persisted: Mutex::new(None),
         }
     }
 
-    /// Inserts or updates a vector in the database.
+    /// Sets the whole storage and scoring configuration at once.
     ///
-    /// The vector is automatically L2-normalized before storage. If the ID already
-    /// exists, the existing vector is updated. If the database is empty, the
-    /// dimension is set based on the first vector inserted.
+    /// Equivalent to calling [`with_metric`](VecDB::with_metric) and
+    /// [`with_normalization`](VecDB::with_normalization). Set it before inserting.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `id` - Unique identifier for the vector
-    /// * `vector` - Vector to insert (will be normalized)
+    /// ```
+    /// use kvdb::{DbConfig, Metric, VecDB};
     ///
-    /// # Returns
+    /// let config = DbConfig {
+    ///     metric: Metric::Euclidean,
+    ///     normalize: false,
+    ///     ..DbConfig::default()
+    /// };
+    /// let db = VecDB::new().with_config(config);
+    /// assert_eq!(db.config(), config);
+    /// ```
+    pub fn with_config(mut self, config: DbConfig) -> Self {
+        self.config = config;
+        self.forget_persisted();
+        self.drop_graph();
+        self
+    }
+
+    /// Returns the storage and scoring configuration.
+    pub fn config(&self) -> DbConfig {
+        self.config
+    }
+
+    /// Sets the metric used to score and rank vectors.
     ///
-    /// * `Ok(String)` - Success message indicating insertion or update
-    /// * `Err(String)` - Error if dimension mismatch or normalization fails
+    /// The default, [`Metric::Cosine`], L2-normalizes vectors on insert. All
+    /// other metrics store vectors as given. Distance metrics rank results in
+    /// ascending order of score, similarity metrics in descending order. Set it
+    /// before inserting; the metric is saved with the database.
     ///
     /// # Examples
     ///
     /// ```
-    /// use kvdb::VecDB;
+    /// use kvdb::{Metric, VecDB};
     ///
-    /// let mut db = VecDB::new();
+    /// let mut db = VecDB::new().with_metric(Metric::Euclidean);
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
     ///
-    /// // Insert a new vector
-    /// let result = db.insert("vec1".to_string(), vec![3.0, 4.0]);
-    /// assert!(result.is_ok());
+    /// let results = db.search(vec![0.0, 0.0], 1).unwrap();
+    /// assert!((results[0].score - 5.0).abs() < 1e-5);
+    /// ```
+    pub fn with_metric(mut self, metric: Metric) -> Self {
+        self.config.metric = metric;
+        self.forget_persisted();
+        self.drop_graph();
+        self
+    }
+
+    /// Sets how vectors are held in memory and on disk.
     ///
-    /// // Update existing vector
-    /// let result = db.insert("vec1".to_string(), vec![1.0, 0.0]);
-    /// assert!(result.unwrap().contains("Updated"));
+    /// [`Quantization::Int8`] stores each component as an `i8` with a
+    /// per-vector scale, about 4× smaller than `f32`; vectors are dequantized
+    /// when searched. Expect top-k recall of roughly 90% or better against the
+    /// `f32` database on normalized embeddings, with the top-1 result almost
+    /// always unchanged. Vectors already stored are converted. The setting is
+    /// saved with the database.
+    ///
+    /// # Examples
     ///
-    /// // Dimension mismatch error
-    /// let result = db.insert("vec2".to_string(), vec![1.0, 2.0, 3.0]);
-    /// assert!(result.is_err());
     /// ```
-    pub fn insert(&mut self, id: String, vector: Vec<f32>) -> Result<String, String> {
-        let dim = vector.len();
-        match self.dimension {
-            None => {
-                self.dimension = Some(dim);
-            }
-            Some(d) => {
-                if dim != d {
-                    return Err("Different dimension".to_string());
-                }
-            }
-        }
-
-        let norm_vec = l2_norm(&vector);
-        match norm_vec {
-            Ok(res) => {
-                // Check if ID exists and update instead
-                if let Some(index) = self.ids.iter().position(|x| x == &id) {
-                    // Update existing vector
-                    let start = index * dim;
-                    self.vectors.splice(start..start + dim, res.iter().cloned());
-                    return Ok(format!("Updated vector with id: {}", id));
-                }
-                self.ids.push(id);
-                self.vectors.extend(res);
-            }
-            Err(msg) => return Err(msg),
-        }
-
-        Ok("Inserted to database with id".to_string())
+    /// use kvdb::{Quantization, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_quantization(Quantization::Int8);
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    /// assert_eq!(db.search(vec![0.9, 0.1], 1).unwrap()[0].id, "vec1");
+    /// ```
+    pub fn with_quantization(mut self, quantization: Quantization) -> Self {
+        let config = DbConfig {
+            quantization,
+            ..self.config
+        };
+        self.convert_storage(config);
+        self
     }
 
-    /// Searches for the k most similar vectors to the query vector.
+    /// Sets the precision of stored vectors (`f32` by default).
     ///
-    /// The query vector is normalized and compared against all stored vectors using
-    /// dot product similarity (equivalent to cosine similarity for normalized vectors).
-    /// Results are returned in descending order of similarity.
+    /// Under [`Precision::F64`], [`insert_f64`](VecDB::insert_f64),
+    /// [`get_f64`](VecDB::get_f64) and [`search_f64`](VecDB::search_f64) keep
+    /// and compare vectors in full `f64` precision. It only applies without
+    /// quantization. Vectors already stored are converted. The setting is saved
+    /// with the database.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `query` - Query vector (will be normalized)
-    /// * `top_k` - Number of results to return
+    /// ```
+    /// use kvdb::{Precision, VecDB};
     ///
-    /// # Returns
+    /// let mut db = VecDB::new().with_precision(Precision::F64);
+    /// db.insert_f64("vec1".to_string(), vec![1.0, 1e-10]).unwrap();
+    /// assert!(db.get_f64("vec1").unwrap()[1] > 0.0);
+    /// ```
+    pub fn with_precision(mut self, precision: Precision) -> Self {
+        let config = DbConfig {
+            precision,
+            ..self.config
+        };
+        self.convert_storage(config);
+        self
+    }
+
+    /// Turns automatic L2 normalization on or off (on by default).
     ///
-    /// * `Ok(Vec<(String, Vec<f32>, f32)>)` - Vector of tuples containing:
-    ///   - ID of the vector
-    ///   - The normalized vector
-    ///   - Similarity score (0.0 to 1.0)
-    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    /// With normalization off, [`insert`](VecDB::insert) stores vectors exactly
+    /// as given and queries are scored as given, so cosine search reduces to a
+    /// raw dot product. Only use this for embeddings that are already unit
+    /// length. Only affects [`Metric::Cosine`]; the setting is saved with the
+    /// database.
     ///
     /// # Examples
     ///
     /// ```
     /// use kvdb::VecDB;
     ///
-    /// let mut db = VecDB::new();
-    /// db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
-    /// db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
-    /// db.insert("vec3".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
-    ///
-    /// // Search for vectors similar to [1.0, 0.0, 0.0]
-    /// let results = db.search(vec![1.0, 0.0, 0.0], 2).unwrap();
-    /// assert_eq!(results.len(), 2);
-    /// assert_eq!(results[0].0, "vec1"); // Most similar
-    /// assert!((results[0].2 - 1.0).abs() < 0.01); // Similarity ~1.0
+    /// let mut db = VecDB::new().with_normalization(false);
+    /// db.insert("vec1".to_string(), vec![0.6, 0.8]).unwrap();
+    /// assert_eq!(db.get("vec1").unwrap(), vec![0.6, 0.8]);
     /// ```
-    pub fn search(
-        &self,
-        query: Vec<f32>,
-        top_k: usize,
-    ) -> Result<Vec<(String, Vec<f32>, f32)>, String> {
-        if self.dimension.is_none() {
-            return Err("Empty database".to_string());
-        } else if query.len() != self.dimension.unwrap() {
-            return Err("Wrong query dimension".to_string());
-        }
-
-        let norm_q = l2_norm(&query)?;
-
-        if top_k >= self.ids.len() {
-            let mut remain = Vec::new();
-            for i in 0..self.ids.len() {
-                remain.push(self.get_vector(i));
-            }
-
-            let result = self
-                .ids
-                .iter()
-                .zip(remain.iter())
-                .map(|(i, v)| (i.clone(), v.to_vec(), dot_product(v, &norm_q).unwrap()))
-                .collect();
-
-            return Ok(result);
-        }
-
-        let mut dps: Vec<(usize, f32)> = vec![(top_k - 1, f32::NEG_INFINITY); top_k];
-        for i in 0..self.ids.len() {
-            let sim = dot_product(self.get_vector(i), &norm_q).unwrap();
-            let insert_index = dps.partition_point(|&x| x.1 > sim);
-            dps.insert(insert_index, (i, sim));
-            dps.truncate(top_k);
-        }
-
-        let result = dps
-            .iter()
-            .map(|(i, dp)| (self.ids[*i].clone(), self.get_vector(*i).to_vec(), *dp))
-            .collect();
-
-        Ok(result)
+    pub fn with_normalization(mut self, normalize: bool) -> Self {
+        self.config.normalize = normalize;
+        self.forget_persisted();
+        self
     }
 
-    /// Retrieves a vector by its ID.
+    /// Sets the ID normalization policy.
     ///
-    /// Returns the normalized vector associated with the given ID, or `None`
-    /// if the ID doesn't exist or the database is empty.
+    /// The policy is applied in [`insert`](VecDB::insert), [`get`](VecDB::get),
+    /// [`delete`](VecDB::delete) and every other method taking an ID. Set it
+    /// before inserting: IDs already stored are not rewritten.
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `id` - The ID of the vector to retrieve
+    /// ```
+    /// use kvdb::{IdPolicy, VecDB};
     ///
-    /// # Returns
+    /// let mut db = VecDB::new().with_id_policy(IdPolicy {
+    ///     trim: true,
+    ///     lowercase: true,
+    /// });
+    /// db.insert(" Doc1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// assert!(db.get("doc1").is_some());
+    /// ```
+    pub fn with_id_policy(mut self, policy: IdPolicy) -> Self {
+        self.id_policy = policy;
+        self.forget_persisted();
+        self
+    }
+
+    /// Sets a custom ID normalizer, applied after the [`IdPolicy`].
     ///
-    /// * `Some(Vec<f32>)` - The normalized vector if found
-    /// * `None` - If the ID doesn't exist or database is empty
+    /// Unlike the policy, a custom normalizer is not saved with the database
+    /// and has to be set again after [`load`](VecDB::load).
     ///
     /// # Examples
     ///
     /// ```
     /// use kvdb::VecDB;
     ///
-    /// let mut db = VecDB::new();
-    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
-    ///
-    /// // Get existing vector
-    /// let vec = db.get("vec1");
-    /// assert!(vec.is_some());
-    /// assert_eq!(vec.unwrap().len(), 2);
-    ///
-    /// // Try to get non-existent vector
-    /// let vec = db.get("vec2");
-    /// assert!(vec.is_none());
+    /// let mut db = VecDB::new().with_id_normalizer(|id| id.replace('-', "_"));
+    /// db.insert("doc-1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// assert!(db.get("doc_1").is_some());
     /// ```
-    pub fn get(&self, id: &str) -> Option<Vec<f32>> {
-        self.dimension?;
-
-        for i in 0..self.ids.len() {
-            if self.ids[i] == id {
-                return Some(self.get_vector(i).to_vec());
-            }
-        }
-
-        None
+    pub fn with_id_normalizer(mut self, normalizer: fn(&str) -> String) -> Self {
+        self.id_normalizer = Some(normalizer);
+        self
     }
 
-    /// Deletes a vector from the database by its ID.
-    ///
-    /// Removes both the ID and the associated vector data from the flat array storage.
-    /// After deletion, the remaining vectors maintain their correct indices.
-    ///
-    /// # Arguments
+    /// Caps the database at `max` vectors, turning it into a FIFO cache.
     ///
-    /// * `id` - The ID of the vector to delete
+    /// Inserting a new ID into a full database first evicts the oldest
+    /// inserted vector; the insert message names the evicted ID. Updating an
+    /// existing ID doesn't count as a new insertion. The cap is not saved with
+    /// the database and has to be set again after [`load`](VecDB::load).
     ///
-    /// # Returns
+    /// # Panics
     ///
-    /// * `Ok(String)` - Success message if deletion succeeded
-    /// * `Err(String)` - Error if database is empty or ID not found
+    /// Panics if `max` is 0.
     ///
     /// # Examples
     ///
     /// ```
     /// use kvdb::VecDB;
     ///
-    /// let mut db = VecDB::new();
-    /// db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
-    /// db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
-    ///
-    /// // Delete existing vector
-    /// let result = db.delete("vec1");
-    /// assert!(result.is_ok());
-    /// assert!(db.get("vec1").is_none());
+    /// let mut db = VecDB::new().with_capacity(2);
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
     ///
-    /// // Try to delete non-existent vector
-    /// let result = db.delete("vec3");
-    /// assert!(result.is_err());
+    /// let message = db.insert("vec3".to_string(), vec![1.0, 1.0]).unwrap();
+    /// assert!(message.contains("evicted oldest id: vec1"));
+    /// assert_eq!(db.count(), 2);
     /// ```
-    pub fn delete(&mut self, id: &str) -> Result<String, String> {
-        if self.dimension.is_none() {
-            return Err("Cannot delete on empty database".to_string());
-        }
+    pub fn with_capacity(mut self, max: usize) -> Self {
+        assert!(max > 0, "capacity must be at least 1");
+        self.capacity = Some(max);
+        self
+    }
 
-        for i in 0..self.ids.len() {
-            if self.ids[i] == id {
-                self.vectors.splice(
-                    (i * self.dimension.unwrap())..((i + 1) * self.dimension.unwrap()),
-                    std::iter::empty(),
-                );
-                self.ids.remove(i);
-                return Ok("Success Delete".to_string());
-            }
-        }
+    /// Makes [`search`](VecDB::search) use an approximate index instead of
+    /// comparing the query with every vector.
+    ///
+    /// Results may miss some true neighbors; brute-force search stays the
+    /// default and the exact reference. Other search methods are not affected.
+    /// The index kind is saved with the database.
+    ///
+    /// An [`IndexKind::Hnsw`] graph is built over the vectors already stored
+    /// and extended on every insert of a new ID. Updates and deletes move or
+    /// change stored vectors, which the graph can't absorb: the next search
+    /// rebuilds it from scratch. The graph itself is not saved; the first
+    /// search after [`load`](VecDB::load) builds it.
+    ///
+    /// An [`IndexKind::Ivf`] index is trained by k-means on the vectors already
+    /// stored, if there are at least `nlist` of them, and new vectors are
+    /// assigned to the nearest centroid. Centroids don't move afterwards; call
+    /// [`train_index`](VecDB::train_index) once the data is in, or after it
+    /// has changed a lot. Until trained, search is brute force. Centroids and
+    /// assignments are saved with the database.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{IndexKind, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_index(IndexKind::default());
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search(vec![0.9, 0.1], 1).unwrap();
+    /// assert_eq!(results[0].id, "vec1");
+    /// ```
+    pub fn with_index(mut self, kind: IndexKind) -> Self {
+        self.index_kind = Some(kind);
+        self.forget_persisted();
+        let graph = self.build_graph(kind);
+        *self.graph.get_mut().unwrap() = graph;
 
-        Err("ID not found".to_string())
+        self.ivf = match kind {
+            IndexKind::Ivf { nlist, .. } => {
+                let mut ivf = Ivf::new(nlist);
+                if self.ids.len() >= ivf.nlist() {
+                    ivf.train(self.ids.len(), |i| self.get_vector(i));
+                }
+                Some(ivf)
+            }
+            IndexKind::Hnsw { .. } => None,
+        };
+        self
     }
 
-    /// Returns all vectors in the database with their IDs.
+    /// Trains the [`IndexKind::Ivf`] index on the vectors currently stored.
+    ///
+    /// Runs k-means from scratch and reassigns every vector. Needed when the
+    /// index was set on a database with fewer than `nlist` vectors, and
+    /// worthwhile after the data has changed a lot since the last training.
     ///
     /// # Returns
     ///
-    /// A vector of tuples containing (ID, normalized vector)
+    /// * `Ok(())` - The index is trained
+    /// * `Err(String)` - Error if the database has no IVF index or fewer than
+    ///   `nlist` vectors
     ///
     /// # Examples
     ///
     /// ```
-    /// use kvdb::VecDB;
+    /// use kvdb::{IndexKind, VecDB};
     ///
-    /// let mut db = VecDB::new();
+    /// let mut db = VecDB::new().with_index(IndexKind::Ivf { nlist: 2, nprobe: 1 });
     /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
     /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    /// db.train_index().unwrap();
     ///
-    /// let all_vectors = db.list();
-    /// assert_eq!(all_vectors.len(), 2);
+    /// assert_eq!(db.search(vec![0.9, 0.1], 1).unwrap()[0].id, "vec1");
     /// ```
-    pub fn list(&self) -> Vec<(String, Vec<f32>)> {
-        (0..self.ids.len())
-            .map(|i| (self.ids[i].clone(), self.get_vector(i).to_vec()))
-            .collect()
-    }
+    pub fn train_index(&mut self) -> Result<(), String> {
+        let Some(mut ivf) = self.ivf.take() else {
+            return Err("Database has no IVF index".to_string());
+        };
 
-    /// Returns the number of vectors in the database.
-    pub fn count(&self) -> usize {
-        self.ids.len()
+        let count = self.ids.len();
+        if count >= ivf.nlist() {
+            ivf.train(count, |i| self.get_vector(i));
+        }
+        let nlist = ivf.nlist();
+        self.ivf = Some(ivf);
+        if count < nlist {
+            return Err(format!(
+                "Training needs at least {} vectors, found {}",
+                nlist, count
+            ));
+        }
+
+        self.forget_persisted();
+        Ok(())
     }
 
-    /// Retrieves a vector slice from the flat array by index.
+    /// Inserts or updates a vector in the database.
     ///
-    /// This is a private helper function that efficiently slices the flat vector
-    /// array to return a reference to the vector at the given index. The vectors
-    /// are stored contiguously as: `[v1_d1, v1_d2, ..., v2_d1, v2_d2, ...]`
+    /// With the default cosine metric the vector is L2-normalized before storage;
+    /// other metrics store it as given (see [`with_metric`](VecDB::with_metric)). If the ID already
+    /// exists, the existing vector is updated. If the database is empty, the
+    /// dimension is set based on the first vector inserted.
     ///
     /// # Arguments
     ///
-    /// * `index` - The index of the vector (0-based)
+    /// * `id` - Unique identifier for the vector
+    /// * `vector` - Vector to insert (normalized under the cosine metric)
     ///
     /// # Returns
     ///
-    /// A slice reference to the vector at the specified index.
+    /// * `Ok(String)` - Success message indicating insertion or update
+    /// * `Err(String)` - Error if dimension mismatch or normalization fails
     ///
-    /// # Panics
+    /// # Examples
     ///
-    /// Panics if the dimension is `None` or if the index is out of bounds.
-    fn get_vector(&self, index: usize) -> &[f32] {
-        let start = index * self.dimension.unwrap();
-        &self.vectors[start..start + self.dimension.unwrap()]
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    ///
+    /// // Insert a new vector
+    /// let result = db.insert("vec1".to_string(), vec![3.0, 4.0]);
+    /// assert!(result.is_ok());
+    ///
+    /// // Update existing vector
+    /// let result = db.insert("vec1".to_string(), vec![1.0, 0.0]);
+    /// assert!(result.unwrap().contains("Updated"));
+    ///
+    /// // Dimension mismatch error
+    /// let result = db.insert("vec2".to_string(), vec![1.0, 2.0, 3.0]);
+    /// assert!(result.is_err());
+    /// ```
+    pub fn insert(&mut self, id: String, vector: Vec<f32>) -> Result<String, String> {
+        let id = self.normalize_id(&id);
+        let dim = vector.len();
+
+        let norm_vec = self.validate_vector(&vector);
+        match norm_vec {
+            Ok(res) => Ok(self.store(id, dim, &res)),
+            Err(msg) => Err(msg),
+        }
     }
 
-    /// Saves the database to a file using bincode serialization.
+    /// Inserts or updates an `f64` vector.
     ///
-    /// All vectors, IDs, and dimension metadata are serialized into a compact
-    /// binary format and written to disk using buffered I/O.
+    /// Works like [`insert`](VecDB::insert), with normalization done in `f64`.
+    /// Under [`Precision::F64`] the vector is stored at full precision; otherwise
+    /// it is rounded to `f32`.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - Success message indicating insertion or update
+    /// * `Err(String)` - Error if dimension mismatch or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{Precision, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_precision(Precision::F64);
+    /// db.insert_f64("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    /// assert_eq!(db.get_f64("vec1").unwrap(), vec![0.6, 0.8]);
+    /// ```
+    pub fn insert_f64(&mut self, id: String, vector: Vec<f64>) -> Result<String, String> {
+        let id = self.normalize_id(&id);
+        let dim = vector.len();
+        if let Some(d) = self.dimension
+            && dim != d
+        {
+            return Err("Different dimension".to_string());
+        }
+        let stored = self.to_stored_f64(&vector)?;
+
+        // `store` does the bookkeeping (updates, eviction, indexes) on the
+        // rounded vector; the exact values then replace it
+        let rounded: Vec<f32> = stored.iter().map(|&x| x as f32).collect();
+        let message = self.store(id.clone(), dim, &rounded);
+        if self.config.storage() == Storage::F64 {
+            self.set_wide(&id, &stored);
+        }
+
+        Ok(message)
+    }
+
+    /// Inserts or updates a vector that is already unit-length.
+    ///
+    /// Works like [`insert`](VecDB::insert), but skips L2 normalization for this
+    /// call only, so a precomputed unit vector can be mixed with raw ones. The
+    /// database stays in normalized mode. Debug builds assert that the vector
+    /// really has length 1. Under a metric that doesn't normalize, this is the
+    /// same as `insert`.
     ///
     /// # Arguments
     ///
-    /// * `path` - File path to save the database to
+    /// * `id` - Unique identifier for the vector
+    /// * `vector` - Unit-length vector to insert as is
     ///
     /// # Returns
     ///
-    /// * `Ok(())` - Database saved successfully
-    /// * `Err(String)` - Error if file creation or serialization fails
+    /// * `Ok(String)` - Success message indicating insertion or update
+    /// * `Err(String)` - Error if dimension mismatch, or the vector is empty or not finite
     ///
     /// # Examples
     ///
-    /// ```no_run
+    /// ```
     /// use kvdb::VecDB;
     ///
     /// let mut db = VecDB::new();
-    /// db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
-    /// db.save("my_database.db").unwrap();
+    /// db.insert_normalized("vec1".to_string(), vec![0.6, 0.8]).unwrap();
+    /// db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// assert_eq!(db.get("vec1"), db.get("vec2"));
     /// ```
-    pub fn save(&self, path: &str) -> Result<(), String> {
-        let file = File::create(path)
-            .map_err(|e| format!("Fail to create file for saving '{}': {}", path, e))?;
+    pub fn insert_normalized(&mut self, id: String, vector: Vec<f32>) -> Result<String, String> {
+        if !(self.config.metric.normalizes() && self.config.normalize) {
+            return self.insert(id, vector);
+        }
 
-        let writer = BufWriter::new(file);
-        bincode::serialize_into(writer, self)
-            .map_err(|e| format!("Serialization failed: {}", e))?;
+        let id = self.normalize_id(&id);
+        let dim = vector.len();
+        if let Some(d) = self.dimension
+            && dim != d
+        {
+            return Err("Different dimension".to_string());
+        }
+        if vector.is_empty() {
+            return Err("Cannot insert an empty vector".to_string());
+        }
+        check_finite(&vector)?;
+        debug_assert!(
+            (vector.iter().map(|x| x * x).sum::<f32>().sqrt() - 1.0).abs() < 1e-3,
+            "insert_normalized expects a unit-length vector"
+        );
 
-        Ok(())
+        Ok(self.store(id, dim, &vector))
     }
 
-    /// Loads a database from a file previously saved with [`save`](VecDB::save).
+    /// Replaces the vector stored under an existing ID.
     ///
-    /// Deserializes the binary file back into a fully functional `VecDB` instance
-    /// with all vectors, IDs, and dimension metadata restored.
+    /// Unlike [`insert`](VecDB::insert), this never creates a new entry, so a
+    /// mistyped ID is reported instead of silently added. The vector is checked
+    /// and normalized exactly as on insert.
     ///
     /// # Arguments
     ///
-    /// * `path` - File path to load the database from
+    /// * `id` - The ID of the vector to replace
+    /// * `vector` - The new vector
     ///
     /// # Returns
     ///
-    /// * `Ok(VecDB)` - The loaded database
-    /// * `Err(String)` - Error if file not found, cannot be opened, or deserialization fails
+    /// * `Ok(String)` - Success message
+    /// * `Err(String)` - Error if ID not found, dimension mismatch or normalization fails
     ///
     /// # Examples
     ///
-    /// ```no_run
+    /// ```
     /// use kvdb::VecDB;
     ///
-    /// let db = VecDB::load("my_database.db").unwrap();
-    /// println!("Loaded {} vectors", db.count());
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// db.update("vec1", vec![0.0, 2.0]).unwrap();
+    /// assert_eq!(db.get("vec1").unwrap(), vec![0.0, 1.0]);
+    ///
+    /// assert!(db.update("missing", vec![1.0, 0.0]).is_err());
     /// ```
-    pub fn load(path: &str) -> Result<Self, String> {
-        if !std::path::Path::new(path).exists() {
-            return Err("File not found!".to_string());
-        }
-
-        let file = File::open(path)
-            .map_err(|e| format!("Fail to create file for saving '{}': {}", path, e))?;
+    pub fn update(&mut self, id: &str, vector: Vec<f32>) -> Result<String, String> {
+        let id = self.normalize_id(id);
+        let index = self
+            .position(&id)
+            .ok_or_else(|| "ID not found".to_string())?;
 
-        let reader = BufReader::new(file);
+        let res = self.validate_vector(&vector)?;
+        self.set_vector(index, &res);
+        Ok(format!("Updated vector with id: {}", id))
+    }
 
-        let db: VecDB = bincode::deserialize_from(reader)
-            .map_err(|e| format!("Deserialization failed: {}", e))?;
+    /// Changes the ID of a stored vector, leaving its data untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `old_id` - The current ID of the vector
+    /// * `new_id` - The ID to store it under
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The vector was renamed
+    /// * `Err(String)` - Error if `old_id` is not found or `new_id` is already taken
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("draft".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// db.rename("draft", "final".to_string()).unwrap();
+    /// assert!(db.get("draft").is_none());
+    /// assert_eq!(db.get("final").unwrap(), vec![1.0, 0.0]);
+    /// ```
+    pub fn rename(&mut self, old_id: &str, new_id: String) -> Result<(), String> {
+        let index = self
+            .position(old_id)
+            .ok_or_else(|| "ID not found".to_string())?;
+        let new_id = self.normalize_id(&new_id);
+        if self.index.contains_key(&new_id) {
+            return Err(format!("ID already exists: {}", new_id));
+        }
 
-        Ok(db)
+        self.forget_persisted();
+        let old_id = std::mem::replace(&mut self.ids[index], new_id.clone());
+        self.index.remove(&old_id);
+        if let Some(meta) = self.metadata.remove(&old_id) {
+            self.metadata.insert(new_id.clone(), meta);
+        }
+        self.index.insert(new_id, index);
+        Ok(())
     }
-}
-
-#[cfg(test)]
-mod db_test {
-    use super::*;
 
-    #[test]
-    fn test_insert_single_vector() {
-        let mut db = VecDB::new();
-        let result = db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]);
+    /// Inserts or updates many vectors at once.
+    ///
+    /// Every entry goes through [`insert`](VecDB::insert), so each one is checked
+    /// against the database dimension, or against the first entry if the database
+    /// is empty. Storage is reserved up front for the whole batch. A failing entry
+    /// doesn't stop the others.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - `(id, vector)` pairs to insert
+    ///
+    /// # Returns
+    ///
+    /// One `Result` per entry, in the same order as `entries`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// let results = db.insert_many(vec![
+    ///     ("vec1".to_string(), vec![1.0, 0.0]),
+    ///     ("vec2".to_string(), vec![1.0, 0.0, 0.0]),
+    /// ]);
+    /// assert!(results[0].is_ok());
+    /// assert!(results[1].is_err());
+    /// ```
+    pub fn insert_many(&mut self, entries: Vec<(String, Vec<f32>)>) -> Vec<Result<String, String>> {
+        let dim = self
+            .dimension
+            .or_else(|| entries.first().map(|(_, vector)| vector.len()));
+        if let Some(d) = dim {
+            match self.config.storage() {
+                Storage::F32 => self.vectors.reserve(entries.len() * d),
+                Storage::F64 => self.wide.reserve(entries.len() * d),
+                Storage::Int8 => {
+                    self.codes.reserve(entries.len() * d);
+                    self.scales.reserve(entries.len());
+                }
+                Storage::Binary => self.bits.reserve(entries.len() * d.div_ceil(64)),
+            }
+        }
+        self.ids.reserve(entries.len());
+        self.index.reserve(entries.len());
 
-        assert!(result.is_ok());
-        assert_eq!(db.ids.len(), 1);
-        assert_eq!(db.ids[0], "vec1");
-        assert_eq!(db.dimension, Some(3));
-        // Vectors should be normalized, so length should be 3 floats
-        assert_eq!(db.vectors.len(), 3);
+        entries
+            .into_iter()
+            .map(|(id, vector)| self.insert(id, vector))
+            .collect()
     }
 
-    #[test]
-    fn test_insert_multiple_vectors() {
-        let mut db = VecDB::new();
+    /// Like [`insert_many`](VecDB::insert_many), but checks the whole batch first.
+    ///
+    /// The expected dimension is the database dimension, or the most common
+    /// dimension in the batch if the database is empty. If any entry differs,
+    /// nothing is inserted and the error names the expected dimension, how
+    /// many entries shared it, and every outlier. This catches off-by-one
+    /// embedding bugs on the client instead of reporting them one entry at a time.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - `(id, vector)` pairs to insert
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Result<String, String>>)` - One result per entry, as from `insert_many`
+    /// * `Err(String)` - Error listing the outliers if the batch is not uniform
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// let result = db.insert_many_strict(vec![
+    ///     ("vec1".to_string(), vec![1.0, 0.0]),
+    ///     ("vec2".to_string(), vec![0.0, 1.0]),
+    ///     ("vec3".to_string(), vec![1.0, 0.0, 0.0]),
+    /// ]);
+    /// assert!(result.unwrap_err().contains("vec3 (3)"));
+    /// assert_eq!(db.count(), 0);
+    /// ```
+    pub fn insert_many_strict(
+        &mut self,
+        entries: Vec<(String, Vec<f32>)>,
+    ) -> Result<Vec<Result<String, String>>, String> {
+        let expected = match self.dimension {
+            Some(d) => d,
+            None => {
+                let mut counts: HashMap<usize, usize> = HashMap::new();
+                for (_, vector) in &entries {
+                    *counts.entry(vector.len()).or_insert(0) += 1;
+                }
+                let top = counts.values().copied().max().unwrap_or(0);
+                // Ties go to the dimension seen first
+                entries
+                    .iter()
+                    .map(|(_, vector)| vector.len())
+                    .find(|len| counts[len] == top)
+                    .unwrap_or(0)
+            }
+        };
 
-        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
-        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
-        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+        let outliers: Vec<String> = entries
+            .iter()
+            .filter(|(_, vector)| vector.len() != expected)
+            .map(|(id, vector)| format!("{} ({})", id, vector.len()))
+            .collect();
+        if !outliers.is_empty() {
+            return Err(format!(
+                "Batch dimension mismatch: expected {}, shared by {} of {} entries; outliers: {}",
+                expected,
+                entries.len() - outliers.len(),
+                entries.len(),
+                outliers.join(", ")
+            ));
+        }
 
-        assert_eq!(db.ids.len(), 3);
-        assert_eq!(db.vectors.len(), 9); // 3 vectors × 3 dimensions
+        Ok(self.insert_many(entries))
     }
 
-    #[test]
-    fn test_insert_dimension_mismatch() {
-        let mut db = VecDB::new();
+    /// Copies every vector of `other` into this database.
+    ///
+    /// On an ID collision `other` wins: the existing vector (and its metadata,
+    /// if `other` has some) is updated, as with [`insert`](VecDB::insert).
+    /// Both databases must have the same [`DbConfig`], otherwise vectors stored
+    /// under one metric would be scored under another.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - Database to copy vectors from
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - Number of vectors copied, i.e. added or updated
+    /// * `Err(String)` - Error if the configurations or dimensions differ; nothing
+    ///   is copied in that case
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// let mut other = VecDB::new();
+    /// other.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// assert_eq!(db.merge(&other).unwrap(), 1);
+    /// assert_eq!(db.count(), 2);
+    /// ```
+    pub fn merge(&mut self, other: &VecDB) -> Result<usize, String> {
+        if self.config != other.config {
+            return Err("Cannot merge databases with different configurations".to_string());
+        }
+        if let (Some(d), Some(other_d)) = (self.dimension, other.dimension)
+            && d != other_d
+        {
+            return Err("Different dimension".to_string());
+        }
 
-        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
-        let result = db.insert("vec2".to_string(), vec![1.0, 2.0]); // Wrong dimension
+        for (i, id) in other.ids.iter().enumerate() {
+            let vector = other.get_vector(i).into_owned();
+            match other.metadata.get(id).cloned() {
+                Some(meta) => self.insert_with_meta(id.clone(), vector, meta)?,
+                None => self.insert(id.clone(), vector)?,
+            };
+            if self.config.storage() == Storage::F64 {
+                self.set_wide(id, &other.get_wide(i));
+            }
+        }
 
-        assert!(result.is_err());
-        assert_eq!(db.ids.len(), 1); // Only first vector inserted
+        Ok(other.count())
     }
 
-    #[test]
-    fn test_get_vector() {
-        let mut db = VecDB::new();
-
-        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
-        db.insert("vec2".to_string(), vec![5.0, 12.0]).unwrap();
-
-        // Get first vector (normalized [3,4] = [0.6, 0.8])
-        let v1 = db.get_vector(0);
+    /// Searches for the k most similar vectors to the query vector.
+    ///
+    /// The query vector is normalized and compared against all stored vectors using
+    /// dot product similarity (equivalent to cosine similarity for normalized vectors).
+    /// Results are returned in descending order of similarity.
+    ///
+    /// With a distance metric set through [`with_metric`](VecDB::with_metric), the
+    /// query is used as given and results are in ascending order of distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return; `0` returns no results
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The matches, best first, each with:
+    ///   - `id`: ID of the vector
+    ///   - `vector`: The normalized vector
+    ///   - `score`: Similarity score; under cosine, clamped to `[-1.0, 1.0]`
+    ///     against float rounding
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// Since 0.4 this and the other search methods return [`SearchResult`]s
+    /// instead of `(id, vector, score)` tuples.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+    /// db.insert("vec3".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
+    ///
+    /// // Search for vectors similar to [1.0, 0.0, 0.0]
+    /// let results = db.search(vec![1.0, 0.0, 0.0], 2).unwrap();
+    /// assert_eq!(results.len(), 2);
+    /// assert_eq!(results[0].id, "vec1"); // Most similar
+    /// assert!((results[0].score - 1.0).abs() < 0.01); // Similarity ~1.0
+    /// ```
+    pub fn search(&self, query: Vec<f32>, top_k: usize) -> Result<Vec<SearchResult>, String> {
+        self.search_with_metrics(query, top_k, &mut SearchMetrics::default())
+    }
+
+    /// Searches in `f64` precision for the k vectors most similar to the query.
+    ///
+    /// Works like [`search`](VecDB::search), but the query is normalized and
+    /// scored in `f64`, and every vector is compared (any index is ignored).
+    /// Under [`Precision::F64`] the stored vectors are used at full precision;
+    /// otherwise they are widened from their `f32` form.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult<f64>>)` - The matches, best first
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{Precision, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_precision(Precision::F64);
+    /// db.insert_f64("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert_f64("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_f64(vec![0.9, 0.1], 1).unwrap();
+    /// assert_eq!(results[0].id, "vec1");
+    /// ```
+    pub fn search_f64(
+        &self,
+        query: Vec<f64>,
+        top_k: usize,
+    ) -> Result<Vec<SearchResult<f64>>, String> {
+        let query = match self.dimension {
+            None => return Err("Empty database".to_string()),
+            Some(d) if query.len() != d => {
+                return Err(format!(
+                    "Wrong query dimension: expected {}, got {}",
+                    d,
+                    query.len()
+                ));
+            }
+            Some(_) => self.to_stored_f64(&query)?,
+        };
+
+        let metric = self.config.metric;
+        let clamp = metric.normalizes() && self.config.normalize;
+        let mut scored: Vec<(usize, f64)> = (0..self.count())
+            .map(|i| {
+                let score = metric.score_f64(&self.get_wide(i), &query);
+                (i, if clamp { score.clamp(-1.0, 1.0) } else { score })
+            })
+            .collect();
+        let key = |score: f64| if metric.is_distance() { -score } else { score };
+        scored.sort_by(|a, b| key(b.1).total_cmp(&key(a.1)).then(a.0.cmp(&b.0)));
+        scored.truncate(top_k);
+
+        Ok(scored
+            .into_iter()
+            .map(|(i, score)| SearchResult {
+                id: self.ids[i].clone(),
+                vector: self.get_wide(i).into_owned(),
+                score,
+            })
+            .collect())
+    }
+
+    /// Same as [`search`](VecDB::search), but always compares the query with
+    /// every vector, ignoring any index set with [`with_index`](VecDB::with_index).
+    ///
+    /// The exact ground truth for measuring an index's recall.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{IndexKind, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_index(IndexKind::default());
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// assert_eq!(db.search_exact(vec![0.9, 0.1], 1).unwrap()[0].id, "vec1");
+    /// ```
+    pub fn search_exact(&self, query: Vec<f32>, top_k: usize) -> Result<SearchResults, String> {
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+        let ranked = self.rank(
+            &norm_q,
+            0..self.ids.len(),
+            top_k,
+            &mut SearchMetrics::default(),
+        );
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Same as [`search`](VecDB::search), but also returns performance counters.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((results, metrics))` - The search results and the counters collected
+    ///   while computing them
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let (results, metrics) = db.search_instrumented(vec![1.0, 0.0], 1).unwrap();
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(metrics.comparisons, 2);
+    /// ```
+    pub fn search_instrumented(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+    ) -> Result<(SearchResults, SearchMetrics), String> {
+        let mut metrics = SearchMetrics::default();
+        let results = self.search_with_metrics(query, top_k, &mut metrics)?;
+        Ok((results, metrics))
+    }
+
+    /// Searches only among the vectors whose ID is in `allowed`.
+    ///
+    /// Useful when the caller already knows which documents may be returned,
+    /// e.g. the ones a user is allowed to see. IDs in `allowed` that aren't
+    /// stored are ignored. An empty `allowed` set returns no results.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return
+    /// * `allowed` - IDs that may appear in the results
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The best allowed matches, best first
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let allowed = HashSet::from(["vec2".to_string()]);
+    /// let results = db.search_within(vec![1.0, 0.0], 5, &allowed).unwrap();
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(results[0].id, "vec2");
+    /// ```
+    pub fn search_within(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        allowed: &HashSet<String>,
+    ) -> Result<SearchResults, String> {
+        if top_k == 0 || allowed.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+        let mut indices: Vec<usize> = allowed.iter().filter_map(|id| self.position(id)).collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        let ranked = self.rank(
+            &norm_q,
+            indices.into_iter(),
+            top_k,
+            &mut SearchMetrics::default(),
+        );
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Searches while skipping every vector whose ID is in `exclude`.
+    ///
+    /// Excluded vectors are filtered out before ranking, so up to `top_k`
+    /// results still come from the remaining vectors, e.g. to page past results
+    /// already shown. IDs in `exclude` that aren't stored are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return
+    /// * `exclude` - IDs that must not appear in the results
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The best remaining matches, best first
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    /// use std::collections::HashSet;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let shown = HashSet::from(["vec1".to_string()]);
+    /// let results = db.search_excluding(vec![1.0, 0.0], 1, &shown).unwrap();
+    /// assert_eq!(results[0].id, "vec2");
+    /// ```
+    pub fn search_excluding(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        exclude: &HashSet<String>,
+    ) -> Result<SearchResults, String> {
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+        let skipped: HashSet<usize> = exclude.iter().filter_map(|id| self.position(id)).collect();
+
+        let ranked = self.rank(
+            &norm_q,
+            (0..self.ids.len()).filter(|i| !skipped.contains(i)),
+            top_k,
+            &mut SearchMetrics::default(),
+        );
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Recommends vectors like the `positives` and unlike the `negatives`.
+    ///
+    /// The query is the mean of the stored positive vectors minus the mean of
+    /// the stored negative ones, L2-normalized. The input IDs themselves never
+    /// appear in the results.
+    ///
+    /// # Arguments
+    ///
+    /// * `positives` - IDs of examples to move towards; at least one
+    /// * `negatives` - IDs of examples to move away from; may be empty
+    /// * `top_k` - Number of results to return
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The best matches, best first
+    /// * `Err(String)` - Error if there are no positives, an ID isn't stored,
+    ///   or the examples cancel out to a zero vector
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("cat".to_string(), vec![1.0, 0.1, 0.0]).unwrap();
+    /// db.insert("lion".to_string(), vec![0.9, 0.2, 0.0]).unwrap();
+    /// db.insert("car".to_string(), vec![0.0, 0.1, 1.0]).unwrap();
+    ///
+    /// let results = db.recommend(&["cat".to_string()], &[], 1).unwrap();
+    /// assert_eq!(results[0].id, "lion");
+    /// ```
+    pub fn recommend(
+        &self,
+        positives: &[String],
+        negatives: &[String],
+        top_k: usize,
+    ) -> Result<SearchResults, String> {
+        if positives.is_empty() {
+            return Err("At least one positive ID is required".to_string());
+        }
+
+        let mean = |ids: &[String]| -> Result<Vec<f32>, String> {
+            let mut sum = vec![0.0f32; self.dimension.unwrap_or(0)];
+            for id in ids {
+                let index = self
+                    .position(id)
+                    .ok_or_else(|| format!("ID not found: {}", id))?;
+                for (total, x) in sum.iter_mut().zip(self.get_vector(index).iter()) {
+                    *total += x;
+                }
+            }
+            let count = ids.len().max(1) as f32;
+            Ok(sum.into_iter().map(|total| total / count).collect())
+        };
+
+        let positive = mean(positives)?;
+        let negative = mean(negatives)?;
+        let query: Vec<f32> = positive.iter().zip(&negative).map(|(p, n)| p - n).collect();
+        let query = l2_norm(&query)?;
+
+        let exclude: HashSet<String> = positives
+            .iter()
+            .chain(negatives)
+            .map(|id| self.normalize_id(id))
+            .collect();
+        self.search_excluding(query, top_k, &exclude)
+    }
+
+    /// Same as [`search`](VecDB::search), but returns only IDs and scores.
+    ///
+    /// No vector is copied, which saves a lot when only the ranking is needed.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(String, f32)>)` - `(id, score)` pairs, best first
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_ids(vec![1.0, 0.1], 1).unwrap();
+    /// assert_eq!(results[0].0, "vec1");
+    /// ```
+    pub fn search_ids(&self, query: Vec<f32>, top_k: usize) -> Result<Vec<(String, f32)>, String> {
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+        let ranked = self.rank(
+            &norm_q,
+            0..self.ids.len(),
+            top_k,
+            &mut SearchMetrics::default(),
+        );
+
+        Ok(ranked
+            .into_iter()
+            .map(|(i, score)| (self.ids[i].clone(), score))
+            .collect())
+    }
+
+    /// Same as [`search`](VecDB::search), but each match also carries its squared
+    /// Euclidean distance to the query.
+    ///
+    /// Handy when migrating between metrics. For normalized vectors the distance
+    /// follows from the cosine score as `2 - 2 * score`; otherwise it is computed
+    /// from the stored vector.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(SearchResult, f32)>)` - Like [`search`](VecDB::search), each
+    ///   result paired with its squared L2 distance
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_with_l2(vec![1.0, 0.0], 2).unwrap();
+    /// assert!(results[0].1.abs() < 1e-6);
+    /// assert!((results[1].1 - 2.0).abs() < 1e-6);
+    /// ```
+    pub fn search_with_l2(&self, query: Vec<f32>, top_k: usize) -> Result<L2Results, String> {
+        let results =
+            self.search_with_metrics(query.clone(), top_k, &mut SearchMetrics::default())?;
+        if results.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let unit = self.config.metric.normalizes() && self.config.normalize;
+        let norm_q = self.prepare_query(&query)?;
+        results
+            .into_iter()
+            .map(|result| {
+                let l2_sq = if unit {
+                    (2.0 - 2.0 * result.score).max(0.0)
+                } else {
+                    euclidean_distance(&norm_q, &result.vector)?.powi(2)
+                };
+                Ok((result, l2_sq))
+            })
+            .collect()
+    }
+
+    /// Same as [`search`](VecDB::search), but also reports an exact match.
+    ///
+    /// Meant for deduplication on ingest: the second element is the ID of the
+    /// best match if its similarity is at least `1 - eps`, i.e. it points the same
+    /// way as the query. Under a distance metric, an exact match is one within
+    /// `eps` of the query. The check doesn't depend on `top_k`.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return
+    /// * `eps` - Tolerance for an exact match
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((results, exact))` - The search results and the exact match's ID, if any
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let (_, exact) = db.search_with_exact_flag(vec![2.0, 0.0], 2, 1e-6).unwrap();
+    /// assert_eq!(exact.as_deref(), Some("vec1"));
+    ///
+    /// let (_, exact) = db.search_with_exact_flag(vec![1.0, 1.0], 2, 1e-6).unwrap();
+    /// assert_eq!(exact, None);
+    /// ```
+    pub fn search_with_exact_flag(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        eps: f32,
+    ) -> Result<(SearchResults, Option<String>), String> {
+        let mut results =
+            self.search_with_metrics(query, top_k.max(1), &mut SearchMetrics::default())?;
+
+        let metric = self.config.metric;
+        let exact = results
+            .first()
+            .filter(|result| {
+                if metric.is_distance() {
+                    result.score <= eps
+                } else {
+                    result.score >= 1.0 - eps
+                }
+            })
+            .map(|result| result.id.clone());
+
+        results.truncate(top_k);
+        Ok((results, exact))
+    }
+
+    /// Search implementation shared by [`search`](VecDB::search) and
+    /// [`search_instrumented`](VecDB::search_instrumented).
+    fn search_with_metrics(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        metrics: &mut SearchMetrics,
+    ) -> Result<SearchResults, String> {
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+
+        let ranked = match self.index_kind {
+            Some(kind @ IndexKind::Hnsw { .. }) => self.search_graph(kind, &norm_q, top_k, metrics),
+            Some(IndexKind::Ivf { nprobe, .. }) => self.search_ivf(&norm_q, top_k, nprobe, metrics),
+            // With top_k >= count the heap simply keeps everything, still ranked
+            None => self.rank(&norm_q, 0..self.ids.len(), top_k, metrics),
+        };
+
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Same as [`search`](VecDB::search) on an [`IndexKind::Ivf`] index, but
+    /// scans the `nprobe` closest groups instead of the configured number.
+    ///
+    /// Raising `nprobe` trades speed for recall; `nprobe = nlist` scans every
+    /// group and matches brute force.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The best matches found, best first
+    /// * `Err(String)` - Error if the database has no IVF index, is empty, or
+    ///   the query has the wrong dimension
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{IndexKind, VecDB};
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    /// let db = db.with_index(IndexKind::Ivf { nlist: 2, nprobe: 1 });
+    ///
+    /// let results = db.search_nprobe(vec![0.9, 0.1], 2, 2).unwrap();
+    /// assert_eq!(results.len(), 2);
+    /// ```
+    pub fn search_nprobe(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        nprobe: usize,
+    ) -> Result<SearchResults, String> {
+        if !matches!(self.index_kind, Some(IndexKind::Ivf { .. })) {
+            return Err("Database has no IVF index".to_string());
+        }
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+        let ranked = self.search_ivf(&norm_q, top_k, nprobe, &mut SearchMetrics::default());
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Returns every vector whose similarity to the query is at least `min_score`.
+    ///
+    /// Unlike [`search`](VecDB::search), the number of results isn't fixed, which
+    /// suits deduplication ("everything with similarity ≥ 0.95"). The threshold is
+    /// inclusive and results are sorted best first. Under a distance metric,
+    /// `min_score` is the maximum distance instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `min_score` - Minimum similarity (inclusive) a vector must reach
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - All matches, possibly none, in
+    ///   descending order of similarity
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_threshold(vec![1.0, 0.1], 0.9).unwrap();
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(results[0].id, "vec1");
+    /// ```
+    pub fn search_threshold(
+        &self,
+        query: Vec<f32>,
+        min_score: f32,
+    ) -> Result<SearchResults, String> {
+        let norm_q = self.prepare_query(&query)?;
+        let metric = self.config.metric;
+        let min_key = metric.rank_key(min_score);
+
+        let query_bits = self.query_bits(&norm_q);
+        let mut matches: Vec<(usize, f32)> = (0..self.ids.len())
+            .map(|i| (i, self.score_at(i, &norm_q, query_bits.as_deref())))
+            .filter(|&(_, score)| metric.rank_key(score) >= min_key)
+            .collect();
+        matches.sort_by(|a, b| metric.rank_key(b.1).total_cmp(&metric.rank_key(a.1)));
+        for (_, score) in &mut matches {
+            *score = self.clamp_score(*score);
+        }
+
+        Ok(self.to_results(&matches))
+    }
+
+    /// Searches for results that are relevant but not redundant, using Maximal
+    /// Marginal Relevance (MMR).
+    ///
+    /// The `top_k * 4` best matches form a candidate pool. Results are then
+    /// picked one at a time, each time taking the candidate with the highest
+    /// `lambda * sim(query, c) - (1 - lambda) * max(sim(c, picked))`.
+    /// `lambda = 1.0` gives plain search order; lower values favor candidates
+    /// unlike those already picked. Under a distance metric, similarity is the
+    /// negated distance.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return
+    /// * `lambda` - Relevance weight between 0.0 (diversity only) and 1.0
+    ///   (relevance only)
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - Results in the order they were
+    ///   picked, each with its score against the query
+    /// * `Err(String)` - Error if `lambda` is out of range, database is empty,
+    ///   dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec1_copy".to_string(), vec![1.0, 0.01]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.6, 0.8]).unwrap();
+    ///
+    /// let results = db.search_mmr(vec![1.0, 0.0], 2, 0.3).unwrap();
+    /// assert_eq!(results[0].id, "vec1");
+    /// assert_eq!(results[1].id, "vec2");
+    /// ```
+    pub fn search_mmr(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        lambda: f32,
+    ) -> Result<SearchResults, String> {
+        if !(0.0..=1.0).contains(&lambda) {
+            return Err(format!("lambda must be between 0 and 1, got {}", lambda));
+        }
+        if top_k == 0 {
+            return Ok(Vec::new());
+        }
+
+        let norm_q = self.prepare_query(&query)?;
+        let mut pool = self.rank(
+            &norm_q,
+            0..self.ids.len(),
+            top_k.saturating_mul(4),
+            &mut SearchMetrics::default(),
+        );
+
+        let metric = self.config.metric;
+        let similarity = |a: usize, b: usize| {
+            metric.rank_key(
+                metric
+                    .score(&self.get_vector(a), &self.get_vector(b))
+                    .unwrap(),
+            )
+        };
+
+        let mut picked: Vec<(usize, f32)> = Vec::with_capacity(top_k.min(pool.len()));
+        while picked.len() < top_k && !pool.is_empty() {
+            let mut best = (0, f32::NEG_INFINITY);
+            for (slot, &(index, score)) in pool.iter().enumerate() {
+                let redundancy = picked
+                    .iter()
+                    .map(|&(other, _)| similarity(index, other))
+                    .reduce(f32::max)
+                    .unwrap_or(0.0);
+                let value = lambda * metric.rank_key(score) - (1.0 - lambda) * redundancy;
+                // Ties keep the better ranked candidate
+                if value > best.1 {
+                    best = (slot, value);
+                }
+            }
+            picked.push(pool.remove(best.0));
+        }
+
+        Ok(self.to_results(&picked))
+    }
+
+    /// Searches, then expands each result to its own nearest neighbors.
+    ///
+    /// Useful for exploratory search: the top-k matches for the query plus, for
+    /// each match, the `neighbors_per` stored vectors closest to it (never the
+    /// match itself), which together form a local similarity graph.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of direct matches to return
+    /// * `neighbors_per` - Number of neighbors to fetch for each match
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(match, neighbors)>)` - Each match as returned by
+    ///   [`search`](VecDB::search), with its neighbors best first
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.9, 0.1]).unwrap();
+    /// db.insert("vec3".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let expanded = db.search_expand(vec![1.0, 0.0], 1, 1).unwrap();
+    /// let (top, neighbors) = &expanded[0];
+    /// assert_eq!(top.id, "vec1");
+    /// assert_eq!(neighbors[0].id, "vec2");
+    /// ```
+    pub fn search_expand(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        neighbors_per: usize,
+    ) -> Result<ExpandedResults, String> {
+        let norm_q = self.prepare_query(&query)?;
+        let ranked = self.rank(
+            &norm_q,
+            0..self.ids.len(),
+            top_k,
+            &mut SearchMetrics::default(),
+        );
+
+        Ok(ranked
+            .iter()
+            .map(|&(index, score)| {
+                let neighbors = self.neighbors_of(index, neighbors_per);
+                (
+                    SearchResult {
+                        id: self.ids[index].clone(),
+                        vector: self.get_vector(index).to_vec(),
+                        score,
+                    },
+                    self.to_results(&neighbors),
+                )
+            })
+            .collect())
+    }
+
+    /// Finds the `top_k` nearest neighbors of a stored vector.
+    ///
+    /// The stored vector is used as the query as is, without normalizing it
+    /// again, and the vector itself is left out of the results instead of
+    /// matching itself at the top.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The closest other vectors, best first
+    /// * `Err(String)` - "ID not found" if the ID isn't stored
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.9, 0.1]).unwrap();
+    /// db.insert("vec3".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_by_id("vec1", 1).unwrap();
+    /// assert_eq!(results[0].id, "vec2");
+    /// ```
+    pub fn search_by_id(&self, id: &str, top_k: usize) -> Result<SearchResults, String> {
+        let index = self
+            .position(id)
+            .ok_or_else(|| "ID not found".to_string())?;
+        Ok(self.to_results(&self.neighbors_of(index, top_k)))
+    }
+
+    /// Runs [`search`](VecDB::search) for each query in a batch.
+    ///
+    /// Each query gets its own result, so one bad query (e.g. of the wrong
+    /// length) doesn't fail the whole batch. A dimension mismatch error names
+    /// the expected and actual length of that query.
+    ///
+    /// # Arguments
+    ///
+    /// * `queries` - Query vectors (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return per query
+    ///
+    /// # Returns
+    ///
+    /// One `Result` per query, in the same order as `queries`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// let results = db.search_many(&[vec![1.0, 0.0], vec![1.0]], 1);
+    /// assert_eq!(results[0].as_ref().unwrap()[0].id, "vec1");
+    /// assert!(results[1].is_err());
+    /// ```
+    pub fn search_many(
+        &self,
+        queries: &[Vec<f32>],
+        top_k: usize,
+    ) -> Vec<Result<SearchResults, String>> {
+        queries
+            .iter()
+            .map(|query| {
+                self.search_with_metrics(query.clone(), top_k, &mut SearchMetrics::default())
+            })
+            .collect()
+    }
+
+    /// Searches only the `window` most recently inserted vectors.
+    ///
+    /// Vectors are kept in insertion order, so the window is the tail of storage.
+    /// Updating an existing ID keeps its original position, and deletes preserve
+    /// the relative order of the remaining vectors. Useful for recency-biased
+    /// retrieval over a sliding window.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return
+    /// * `window` - Number of most recent vectors to consider
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - Matches within the window, in descending
+    ///   order of similarity
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("old".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("new".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_recent(vec![1.0, 0.0], 5, 1).unwrap();
+    /// assert_eq!(results.len(), 1);
+    /// assert_eq!(results[0].id, "new");
+    /// ```
+    pub fn search_recent(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        window: usize,
+    ) -> Result<SearchResults, String> {
+        let norm_q = self.prepare_query(&query)?;
+
+        let start = self.ids.len().saturating_sub(window);
+        let ranked = self.rank(
+            &norm_q,
+            start..self.ids.len(),
+            top_k,
+            &mut SearchMetrics::default(),
+        );
+
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Searches for the k least similar vectors to the query vector.
+    ///
+    /// The mirror image of [`search`](VecDB::search): results are returned in
+    /// ascending order of similarity, least similar first. Only a bounded heap of
+    /// `top_k` candidates is kept while scanning, so no sorted copy of the whole
+    /// database is made.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `top_k` - Number of results to return
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<SearchResult>)` - The least similar vectors, in ascending
+    ///   order of similarity
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![-1.0, 0.0]).unwrap();
+    /// db.insert("vec3".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.search_farthest(vec![1.0, 0.0], 1).unwrap();
+    /// assert_eq!(results[0].id, "vec2");
+    /// ```
+    pub fn search_farthest(&self, query: Vec<f32>, top_k: usize) -> Result<SearchResults, String> {
+        self.search_farthest_with_metrics(query, top_k, &mut SearchMetrics::default())
+    }
+
+    /// Implementation of [`search_farthest`](VecDB::search_farthest) that also
+    /// collects performance counters.
+    fn search_farthest_with_metrics(
+        &self,
+        query: Vec<f32>,
+        top_k: usize,
+        metrics: &mut SearchMetrics,
+    ) -> Result<SearchResults, String> {
+        let norm_q = self.prepare_query(&query)?;
+
+        // Lowest rank keys are the least similar, so distances work the same way
+        let metric = self.config.metric;
+        let ranked: Vec<(usize, f32)> = self
+            .lowest_keys(&norm_q, 0..self.ids.len(), top_k, metrics, |score| {
+                metric.rank_key(score)
+            })
+            .into_iter()
+            .map(|(index, key)| (index, self.clamp_score(metric.rank_key(key))))
+            .collect();
+
+        Ok(self.to_results(&ranked))
+    }
+
+    /// Scores explicit (query, ID) pairs without running a full search.
+    ///
+    /// Useful for reranking pipelines that already know their candidates. Each
+    /// query is normalized and compared only against the vector stored under
+    /// its paired ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `pairs` - `(query, id)` pairs to score
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<Option<f32>>)` - One similarity score per pair, in order, or
+    ///   `None` if the pair's ID doesn't exist
+    /// * `Err(String)` - Error if database is empty, or any query has the wrong
+    ///   dimension or cannot be normalized
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// let scores = db
+    ///     .score_pairs(&[
+    ///         (vec![1.0, 0.0], "vec1".to_string()),
+    ///         (vec![1.0, 0.0], "missing".to_string()),
+    ///     ])
+    ///     .unwrap();
+    /// assert!((scores[0].unwrap() - 1.0).abs() < 1e-5);
+    /// assert!(scores[1].is_none());
+    /// ```
+    pub fn score_pairs(&self, pairs: &[(Vec<f32>, String)]) -> Result<Vec<Option<f32>>, String> {
+        pairs
+            .iter()
+            .map(|(query, id)| {
+                let norm_q = self.prepare_query(query)?;
+                let query_bits = self.query_bits(&norm_q);
+                Ok(self
+                    .position(id)
+                    .map(|i| self.clamp_score(self.score_at(i, &norm_q, query_bits.as_deref()))))
+            })
+            .collect()
+    }
+
+    /// Returns the first vector whose similarity to the query reaches `min_score`.
+    ///
+    /// Vectors are scanned in storage order and the scan stops at the first match,
+    /// so the returned vector is not necessarily the best one in the database. This
+    /// trades exactness for speed when any "good enough" match will do.
+    ///
+    /// # Arguments
+    ///
+    /// * `query` - Query vector (normalized under the cosine metric)
+    /// * `min_score` - Minimum similarity (inclusive) a vector must reach, or the
+    ///   maximum distance (inclusive) under a distance metric
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Some(SearchResult))` - The first vector meeting the threshold
+    /// * `Ok(None)` - No vector meets the threshold
+    /// * `Err(String)` - Error if database is empty, dimension mismatch, or normalization fails
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let result = db.nearest_early_stop(vec![0.0, 1.0], 0.99).unwrap().unwrap();
+    /// assert_eq!(result.id, "vec2");
+    /// assert!(result.score >= 0.99);
+    ///
+    /// assert!(db.nearest_early_stop(vec![1.0, 1.0], 0.99).unwrap().is_none());
+    /// ```
+    pub fn nearest_early_stop(
+        &self,
+        query: Vec<f32>,
+        min_score: f32,
+    ) -> Result<Option<SearchResult>, String> {
+        self.nearest_early_stop_with_metrics(query, min_score, &mut SearchMetrics::default())
+    }
+
+    /// Implementation of [`nearest_early_stop`](VecDB::nearest_early_stop) that
+    /// also counts how many vectors were scored before the scan stopped.
+    fn nearest_early_stop_with_metrics(
+        &self,
+        query: Vec<f32>,
+        min_score: f32,
+        metrics: &mut SearchMetrics,
+    ) -> Result<Option<SearchResult>, String> {
+        let norm_q = self.prepare_query(&query)?;
+        let query_bits = self.query_bits(&norm_q);
+
+        for i in 0..self.ids.len() {
+            metrics.comparisons += 1;
+            let sim = self.score_at(i, &norm_q, query_bits.as_deref());
+            if self.config.metric.rank_key(sim) >= self.config.metric.rank_key(min_score) {
+                return Ok(Some(SearchResult {
+                    id: self.ids[i].clone(),
+                    vector: self.get_vector(i).to_vec(),
+                    score: self.clamp_score(sim),
+                }));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Retrieves a vector by its ID.
+    ///
+    /// Returns the normalized vector associated with the given ID, or `None`
+    /// if the ID doesn't exist or the database is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the vector to retrieve
+    ///
+    /// # Returns
+    ///
+    /// * `Some(Vec<f32>)` - The normalized vector if found
+    /// * `None` - If the ID doesn't exist or database is empty
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// // Get existing vector
+    /// let vec = db.get("vec1");
+    /// assert!(vec.is_some());
+    /// assert_eq!(vec.unwrap().len(), 2);
+    ///
+    /// // Try to get non-existent vector
+    /// let vec = db.get("vec2");
+    /// assert!(vec.is_none());
+    /// ```
+    pub fn get(&self, id: &str) -> Option<Vec<f32>> {
+        self.dimension?;
+
+        self.position(id).map(|i| self.get_vector(i).to_vec())
+    }
+
+    /// Retrieves a vector by its ID as `f64`.
+    ///
+    /// Under [`Precision::F64`] this returns the stored values at full
+    /// precision; otherwise the `f32` values are widened.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{Precision, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_precision(Precision::F64);
+    /// db.insert_f64("vec1".to_string(), vec![1.0, 1e-10]).unwrap();
+    ///
+    /// let vector = db.get_f64("vec1").unwrap();
+    /// assert!((vector[1] - 1e-10).abs() < 1e-20);
+    /// ```
+    pub fn get_f64(&self, id: &str) -> Option<Vec<f64>> {
+        self.dimension?;
+
+        self.position(id).map(|i| self.get_wide(i).into_owned())
+    }
+
+    /// Retrieves many vectors by ID at once.
+    ///
+    /// Returns one entry per requested ID, in the same order, with `None` for
+    /// IDs that aren't stored. Each lookup goes through the ID index, so this
+    /// is O(m) for m IDs.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// let vectors = db.get_many(&["missing".to_string(), "vec1".to_string()]);
+    /// assert!(vectors[0].is_none());
+    /// assert_eq!(vectors[1].as_ref().unwrap().len(), 2);
+    /// ```
+    pub fn get_many(&self, ids: &[String]) -> Vec<Option<Vec<f32>>> {
+        ids.iter().map(|id| self.get(id)).collect()
+    }
+
+    /// Inserts or updates a vector together with its metadata payload.
+    ///
+    /// Works like [`insert`](VecDB::insert) and then replaces the ID's payload
+    /// with `meta`. Nothing is stored if the vector is rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{Metadata, VecDB};
+    /// use serde_json::json;
+    ///
+    /// let mut db = VecDB::new();
+    /// let meta = Metadata::from([("lang".to_string(), json!("en"))]);
+    /// db.insert_with_meta("vec1".to_string(), vec![1.0, 0.0], meta).unwrap();
+    ///
+    /// assert_eq!(db.get_meta("vec1").unwrap()["lang"], "en");
+    /// ```
+    pub fn insert_with_meta(
+        &mut self,
+        id: String,
+        vector: Vec<f32>,
+        meta: Metadata,
+    ) -> Result<String, String> {
+        let message = self.insert(id.clone(), vector)?;
+        self.forget_persisted();
+        self.metadata.insert(self.normalize_id(&id), meta);
+        Ok(message)
+    }
+
+    /// Returns the metadata payload of an ID, or `None` if the ID doesn't exist
+    /// or has no payload. Plain [`insert`](VecDB::insert) stores no payload.
+    pub fn get_meta(&self, id: &str) -> Option<&Metadata> {
+        self.metadata.get(&self.normalize_id(id))
+    }
+
+    /// Replaces the metadata payload of an existing ID, leaving its vector untouched.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The payload was replaced
+    /// * `Err(String)` - "ID not found" if the ID doesn't exist
+    pub fn set_meta(&mut self, id: &str, meta: Metadata) -> Result<(), String> {
+        let index = self
+            .position(id)
+            .ok_or_else(|| "ID not found".to_string())?;
+        self.forget_persisted();
+        self.metadata.insert(self.ids[index].clone(), meta);
+        Ok(())
+    }
+
+    /// Deletes a vector from the database by its ID.
+    ///
+    /// Removes both the ID and the associated vector data from the flat array storage.
+    /// After deletion, the remaining vectors maintain their correct indices. Deleting
+    /// the last vector clears the dimension, so the next insert may use any dimension.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the vector to delete
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(String)` - Success message if deletion succeeded
+    /// * `Err(String)` - Error if database is empty or ID not found
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// // Delete existing vector
+    /// let result = db.delete("vec1");
+    /// assert!(result.is_ok());
+    /// assert!(db.get("vec1").is_none());
+    ///
+    /// // Try to delete non-existent vector
+    /// let result = db.delete("vec3");
+    /// assert!(result.is_err());
+    /// ```
+    pub fn delete(&mut self, id: &str) -> Result<String, String> {
+        self.remove(id)?;
+        Ok("Success Delete".to_string())
+    }
+
+    /// Deletes a vector from the database and returns it.
+    ///
+    /// Works like [`delete`](VecDB::delete), but hands back the stored (normalized)
+    /// vector, analogous to `HashMap::remove`. Handy for moving a vector between
+    /// databases.
+    ///
+    /// # Arguments
+    ///
+    /// * `id` - The ID of the vector to remove
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<f32>)` - The removed normalized vector
+    /// * `Err(String)` - Error if database is empty or ID not found
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// let vec = db.remove("vec1").unwrap();
+    /// assert!((vec[0] - 0.6).abs() < 1e-5);
+    /// assert!(db.get("vec1").is_none());
+    /// ```
+    pub fn remove(&mut self, id: &str) -> Result<Vec<f32>, String> {
+        if self.dimension.is_none() {
+            return Err("Cannot delete on empty database".to_string());
+        }
+
+        let index = self
+            .position(id)
+            .ok_or_else(|| "ID not found".to_string())?;
+
+        Ok(self.remove_at(index))
+    }
+
+    /// Removes the vector at storage `index` and returns it, keeping the
+    /// insertion order of the others.
+    fn remove_at(&mut self, index: usize) -> Vec<f32> {
+        let removed = self.take_vector(index);
+        let removed_id = self.ids.remove(index);
+
+        // Keep insertion order (search_recent relies on it) and shift the
+        // indices of every vector stored after the removed one
+        self.index.remove(&removed_id);
+        self.metadata.remove(&removed_id);
+        for i in self.index.values_mut() {
+            if *i > index {
+                *i -= 1;
+            }
+        }
+
+        // An empty database accepts any dimension again
+        if self.ids.is_empty() {
+            self.dimension = None;
+        }
+
+        removed
+    }
+
+    /// Deletes a vector in O(dimension) by moving the last vector into its slot.
+    ///
+    /// Unlike [`delete`](VecDB::delete), which shifts every vector stored after
+    /// the removed one, this does not preserve insertion order: the last
+    /// vector takes the deleted one's position, which changes the results of
+    /// [`search_recent`](VecDB::search_recent) and the order of
+    /// [`list`](VecDB::list).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    /// db.insert("vec3".to_string(), vec![1.0, 1.0]).unwrap();
+    ///
+    /// db.delete_fast("vec1").unwrap();
+    /// assert!(db.get("vec1").is_none());
+    /// assert_eq!(db.count(), 2);
+    /// ```
+    pub fn delete_fast(&mut self, id: &str) -> Result<String, String> {
+        if self.dimension.is_none() {
+            return Err("Cannot delete on empty database".to_string());
+        }
+
+        let index = self
+            .position(id)
+            .ok_or_else(|| "ID not found".to_string())?;
+
+        self.swap_remove_vector(index);
+        let removed_id = self.ids.swap_remove(index);
+        self.index.remove(&removed_id);
+        self.metadata.remove(&removed_id);
+        if let Some(moved_id) = self.ids.get(index) {
+            self.index.insert(moved_id.clone(), index);
+        }
+
+        if self.ids.is_empty() {
+            self.dimension = None;
+        }
+
+        Ok("Success Delete".to_string())
+    }
+
+    /// Deletes many vectors at once, reporting each ID separately.
+    ///
+    /// Every ID is looked up first, then the surviving vectors are compacted
+    /// in a single pass, which is O(n) overall instead of the O(n) shift that
+    /// each [`delete`](VecDB::delete) pays. Insertion order is preserved. An ID
+    /// listed twice is only deleted once; its second entry reports "ID not found".
+    ///
+    /// # Returns
+    ///
+    /// One result per requested ID, in order, with the same messages as
+    /// [`delete`](VecDB::delete).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let results = db.delete_many(&["vec1".to_string(), "vec3".to_string()]);
+    /// assert!(results[0].is_ok());
+    /// assert!(results[1].is_err());
+    /// assert_eq!(db.count(), 1);
+    /// ```
+    pub fn delete_many(&mut self, ids: &[String]) -> Vec<Result<String, String>> {
+        if self.dimension.is_none() {
+            return ids
+                .iter()
+                .map(|_| Err("Cannot delete on empty database".to_string()))
+                .collect();
+        }
+
+        let mut keep = vec![true; self.ids.len()];
+        let results: Vec<Result<String, String>> = ids
+            .iter()
+            .map(|id| match self.position(id) {
+                Some(index) if keep[index] => {
+                    keep[index] = false;
+                    Ok("Success Delete".to_string())
+                }
+                _ => Err("ID not found".to_string()),
+            })
+            .collect();
+        if keep.iter().all(|&keep| keep) {
+            return results;
+        }
+
+        self.retain_vectors(&keep);
+        let mut flags = keep.iter();
+        let removed: Vec<String> = self
+            .ids
+            .extract_if(.., |_| !*flags.next().unwrap())
+            .collect();
+        for id in &removed {
+            self.metadata.remove(id);
+        }
+        self.rebuild_index();
+
+        if self.ids.is_empty() {
+            self.dimension = None;
+        }
+
+        results
+    }
+
+    /// Returns all vectors in the database with their IDs.
+    ///
+    /// # Returns
+    ///
+    /// A vector of tuples containing (ID, normalized vector)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let all_vectors = db.list();
+    /// assert_eq!(all_vectors.len(), 2);
+    /// ```
+    pub fn list(&self) -> Vec<(String, Vec<f32>)> {
+        self.iter()
+            .map(|(id, vector)| (id.to_string(), vector.into_owned()))
+            .collect()
+    }
+
+    /// Iterates over all `(id, vector)` pairs in insertion order.
+    ///
+    /// Unlike [`list`](VecDB::list), nothing is cloned: IDs and vectors are
+    /// borrowed from the database. Vectors of an int8-quantized database have to
+    /// be dequantized, so those are yielded as owned copies.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// let total: f32 = db.iter().map(|(_, vector)| vector.iter().sum::<f32>()).sum();
+    /// assert_eq!(total, 2.0);
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter { db: self, next: 0 }
+    }
+
+    /// Returns true if a vector with this ID is stored.
+    ///
+    /// Only looks up the ID index, so unlike `get(id).is_some()` no vector is copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// assert!(db.contains("vec1"));
+    /// assert!(!db.contains("vec2"));
+    /// ```
+    pub fn contains(&self, id: &str) -> bool {
+        self.position(id).is_some()
+    }
+
+    /// Returns all stored IDs in insertion order, without copying anything.
+    ///
+    /// Cheaper than [`list`](VecDB::list) when the vectors aren't needed, e.g.
+    /// to diff two databases.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// assert_eq!(db.ids(), ["vec1", "vec2"]);
+    /// ```
+    pub fn ids(&self) -> &[String] {
+        &self.ids
+    }
+
+    /// Returns roughly how many bytes of heap memory the database holds.
+    ///
+    /// Counts the stored vectors (in whatever form they are held), the IDs and
+    /// the ID index. Metadata payloads and any index graph are not counted.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// assert!(db.memory_bytes() >= 2 * 4);
+    /// ```
+    pub fn memory_bytes(&self) -> usize {
+        let storage = self.vectors.len() * size_of::<f32>()
+            + self.codes.len()
+            + self.scales.len() * size_of::<f32>()
+            + self.bits.len() * size_of::<u64>()
+            + self.wide.len() * size_of::<f64>();
+        // Each ID is held twice, in `ids` and as an index key
+        let ids = self
+            .ids
+            .iter()
+            .map(|id| 2 * (size_of::<String>() + id.len()) + size_of::<usize>())
+            .sum::<usize>();
+        storage + ids
+    }
+
+    /// Copies the whole database into memory, to be brought back later with
+    /// [`restore`](VecDB::restore).
+    ///
+    /// The copy is a full clone, so it costs as much memory as the database itself.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// let snapshot = db.snapshot_memory();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// db.restore(snapshot);
+    /// assert_eq!(db.count(), 1);
+    /// ```
+    pub fn snapshot_memory(&self) -> DbSnapshot {
+        DbSnapshot(VecDB {
+            ids: self.ids.clone(),
+            vectors: self.vectors.clone(),
+            codes: self.codes.clone(),
+            scales: self.scales.clone(),
+            bits: self.bits.clone(),
+            wide: self.wide.clone(),
+            dimension: self.dimension,
+            config: self.config,
+            id_policy: self.id_policy,
+            metadata: self.metadata.clone(),
+            id_normalizer: self.id_normalizer,
+            capacity: self.capacity,
+            index_kind: self.index_kind,
+            ivf: self.ivf.clone(),
+            graph: RwLock::new(self.graph.read().unwrap().clone()),
+            index: self.index.clone(),
+            persisted: Mutex::new(None),
+        })
+    }
+
+    /// Replaces the database with a snapshot taken by
+    /// [`snapshot_memory`](VecDB::snapshot_memory), undoing every change made since.
+    pub fn restore(&mut self, snapshot: DbSnapshot) {
+        *self = snapshot.0;
+    }
+
+    /// Removes every vector, keeping the configuration.
+    ///
+    /// The dimension is reset, so the next insert may use a different one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// db.clear();
+    /// assert_eq!(db.count(), 0);
+    /// assert_eq!(db.dimension(), None);
+    /// ```
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.index.clear();
+        self.metadata.clear();
+        self.truncate_storage(0);
+        self.dimension = None;
+    }
+
+    /// Returns the number of vectors in the database.
+    pub fn count(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns the dimension vectors must have, or `None` if the database is empty.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// assert_eq!(db.dimension(), None);
+    ///
+    /// db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    /// assert_eq!(db.dimension(), Some(3));
+    /// ```
+    pub fn dimension(&self) -> Option<usize> {
+        self.dimension
+    }
+
+    /// Returns the count, dimension and L2 norm range of the stored vectors.
+    ///
+    /// Norms are computed on the stored vectors (decoded if quantized), one
+    /// dimension-sized chunk at a time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// let stats = db.stats();
+    /// assert_eq!(stats.count, 1);
+    /// assert_eq!(stats.dimension, Some(2));
+    /// assert!((stats.mean_norm - 1.0).abs() < 1e-6);
+    /// ```
+    pub fn stats(&self) -> DbStats {
+        let count = self.count();
+        let mut stats = DbStats {
+            count,
+            dimension: self.dimension,
+            ..DbStats::default()
+        };
+        if count == 0 {
+            return stats;
+        }
+
+        let mut sum = 0.0;
+        stats.min_norm = f32::INFINITY;
+        for index in 0..count {
+            let norm = self
+                .get_vector(index)
+                .iter()
+                .map(|x| x * x)
+                .sum::<f32>()
+                .sqrt();
+            sum += norm;
+            stats.min_norm = stats.min_norm.min(norm);
+            stats.max_norm = stats.max_norm.max(norm);
+        }
+        stats.mean_norm = sum / count as f32;
+        stats
+    }
+
+    /// Returns the indices of dimensions whose variance across the database is
+    /// below `tol`.
+    ///
+    /// Constant dimensions carry no information for similarity search, so
+    /// they are candidates for removal. Variance is computed over the stored
+    /// (normalized) vectors. Returns an empty list if the database is empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `tol` - Variance threshold below which a dimension counts as constant
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0, 1.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0, 1.0]).unwrap();
+    ///
+    /// assert_eq!(db.constant_dimensions(1e-6), vec![2]);
+    /// ```
+    pub fn constant_dimensions(&self, tol: f32) -> Vec<usize> {
+        let Some(dim) = self.dimension else {
+            return Vec::new();
+        };
+        let count = self.count();
+        if count == 0 {
+            return Vec::new();
+        }
+
+        (0..dim)
+            .filter(|&d| {
+                let values = (0..count).map(|i| self.get_vector(i)[d]);
+                let mean = values.clone().sum::<f32>() / count as f32;
+                let variance = values.map(|x| (x - mean).powi(2)).sum::<f32>() / count as f32;
+                variance < tol
+            })
+            .collect()
+    }
+
+    /// Drops every dimension not listed in `keep`, in place.
+    ///
+    /// Each stored vector is rewritten to the kept dimensions (in the order
+    /// given), re-normalized under the cosine metric, and the database
+    /// dimension is updated. The
+    /// database is left unchanged if any step fails.
+    ///
+    /// # Arguments
+    ///
+    /// * `keep` - Indices of the dimensions to keep
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - All vectors projected successfully
+    /// * `Err(String)` - Error if database is empty, `keep` is empty, an index
+    ///   is out of range or repeated, or a projected vector becomes zero
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0, 1.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0, 1.0]).unwrap();
+    ///
+    /// let constant = db.constant_dimensions(1e-6);
+    /// let keep: Vec<usize> = (0..3).filter(|d| !constant.contains(d)).collect();
+    /// db.project_dimensions(&keep).unwrap();
+    ///
+    /// assert_eq!(db.get("vec1").unwrap(), vec![1.0, 0.0]);
+    /// ```
+    pub fn project_dimensions(&mut self, keep: &[usize]) -> Result<(), String> {
+        let Some(dim) = self.dimension else {
+            return Err("Empty database".to_string());
+        };
+        if keep.is_empty() {
+            return Err("No dimensions to keep".to_string());
+        }
+        for (i, &d) in keep.iter().enumerate() {
+            if d >= dim {
+                return Err(format!("Dimension {} out of range", d));
+            }
+            if keep[..i].contains(&d) {
+                return Err(format!("Dimension {} listed more than once", d));
+            }
+        }
+
+        let mut vectors = Vec::with_capacity(self.count() * keep.len());
+        for i in 0..self.count() {
+            let vector = self.get_vector(i);
+            let projected: Vec<f32> = keep.iter().map(|&d| vector[d]).collect();
+            let normed = self
+                .to_stored(&projected)
+                .map_err(|e| format!("Cannot project '{}': {}", self.ids[i], e))?;
+            vectors.extend(normed);
+        }
+
+        self.truncate_storage(0);
+        self.dimension = Some(keep.len());
+        for vector in vectors.chunks(keep.len()) {
+            self.push_vector(vector);
+        }
+
+        Ok(())
+    }
+
+    /// Reweights every stored vector by inverse document frequency, in place.
+    ///
+    /// Meant for bag-of-words style vectors where a zero component means the
+    /// term is absent. A dimension's document frequency is the number of
+    /// vectors with a non-zero value there; its weight is the smoothed IDF
+    /// `ln((1 + n) / (1 + df)) + 1`, so dimensions present everywhere count the
+    /// least. Vectors are re-normalized under the cosine metric. The weights are
+    /// returned so queries can be reweighted the same way before searching.
+    /// The database is left unchanged if any step fails.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<f32>)` - The IDF weight of each dimension
+    /// * `Err(String)` - Error if database is empty or a reweighted vector becomes zero
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::{Metric, VecDB};
+    ///
+    /// let mut db = VecDB::new().with_metric(Metric::DotProduct);
+    /// db.insert("doc1".to_string(), vec![1.0, 1.0]).unwrap();
+    /// db.insert("doc2".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// let idf = db.apply_idf().unwrap();
+    /// assert!(idf[0] < idf[1]);
+    ///
+    /// let query: Vec<f32> = [1.0, 1.0].iter().zip(&idf).map(|(q, w)| q * w).collect();
+    /// assert_eq!(db.search(query, 1).unwrap()[0].id, "doc1");
+    /// ```
+    pub fn apply_idf(&mut self) -> Result<Vec<f32>, String> {
+        let Some(dim) = self.dimension else {
+            return Err("Empty database".to_string());
+        };
+
+        let count = self.count();
+        let mut df = vec![0usize; dim];
+        for i in 0..count {
+            for (d, &x) in self.get_vector(i).iter().enumerate() {
+                if x != 0.0 {
+                    df[d] += 1;
+                }
+            }
+        }
+        let idf: Vec<f32> = df
+            .iter()
+            .map(|&df| ((1 + count) as f32 / (1 + df) as f32).ln() + 1.0)
+            .collect();
+
+        let mut vectors = Vec::with_capacity(count * dim);
+        for i in 0..count {
+            let weighted: Vec<f32> = self
+                .get_vector(i)
+                .iter()
+                .zip(&idf)
+                .map(|(x, w)| x * w)
+                .collect();
+            let stored = self
+                .to_stored(&weighted)
+                .map_err(|e| format!("Cannot reweight '{}': {}", self.ids[i], e))?;
+            vectors.extend(stored);
+        }
+
+        self.truncate_storage(0);
+        for vector in vectors.chunks(dim) {
+            self.push_vector(vector);
+        }
+
+        Ok(idf)
+    }
+
+    /// Converts the stored vectors to int8 in place, without re-inserting.
+    ///
+    /// Each vector is scaled by its largest component so it fits `-127..=127`
+    /// (see [`quantize_int8`](crate::vector::quantize_int8)), cutting vector
+    /// memory about 4×. Vectors are dequantized when read or searched, and new
+    /// inserts are quantized as well. Scores shift slightly: on normalized
+    /// embeddings expect top-k recall of roughly 90% or better against the
+    /// `f32` database, with the top-1 result almost always unchanged.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Storage converted
+    /// * `Err(String)` - Error if the database is already quantized or its storage
+    ///   is inconsistent (see [`verify`](VecDB::verify))
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+    ///
+    /// db.quantize_int8().unwrap();
+    /// assert_eq!(db.search(vec![0.9, 0.1], 1).unwrap()[0].id, "vec1");
+    /// ```
+    pub fn quantize_int8(&mut self) -> Result<(), String> {
+        if self.config.quantization != Quantization::None {
+            return Err("Database is already quantized".to_string());
+        }
+        self.verify()?;
+
+        let vectors = match self.config.storage() {
+            Storage::F64 => self.wide.iter().map(|&x| x as f32).collect(),
+            _ => std::mem::take(&mut self.vectors),
+        };
+        self.truncate_storage(0);
+        self.config.quantization = Quantization::Int8;
+        if let Some(dim) = self.dimension.filter(|&d| d > 0) {
+            for vector in vectors.chunks(dim) {
+                self.push_vector(vector);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks that the stored IDs and vector data are consistent.
+    ///
+    /// The flat storage must hold exactly `count() * dimension` values.
+    /// Searching or reading a database that fails this check can panic; use
+    /// [`repair`](VecDB::repair) to fix it.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Storage is consistent
+    /// * `Err(String)` - Description of the mismatch
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    /// assert!(db.verify().is_ok());
+    /// ```
+    pub fn verify(&self) -> Result<(), String> {
+        let expected = self.ids.len() * self.dimension.unwrap_or(0);
+        if self.stored_values() != expected {
+            return Err(format!(
+                "Storage mismatch: {} ids of dimension {:?} need {} values, found {}",
+                self.ids.len(),
+                self.dimension,
+                expected,
+                self.stored_values()
+            ));
+        }
+        if self.config.quantization == Quantization::Int8 && self.scales.len() != self.ids.len() {
+            return Err(format!(
+                "Storage mismatch: {} ids need {} scales, found {}",
+                self.ids.len(),
+                self.ids.len(),
+                self.scales.len()
+            ));
+        }
+        if let Some(ivf) = &self.ivf
+            && !ivf.covers(self.ids.len())
+        {
+            return Err(format!(
+                "Index mismatch: IVF assignments don't cover the {} ids",
+                self.ids.len()
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Restores the invariant checked by [`verify`](VecDB::verify).
+    ///
+    /// Trailing IDs without a complete vector are dropped, as are trailing
+    /// values that don't belong to any ID (including an incomplete last
+    /// vector). Vectors that are still paired with an ID are kept unchanged.
+    ///
+    /// # Returns
+    ///
+    /// A [`RepairReport`] listing what was removed; it is empty if the database
+    /// was already consistent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+    ///
+    /// let report = db.repair();
+    /// assert!(report.dropped_ids.is_empty());
+    /// assert_eq!(report.truncated_values, 0);
+    /// ```
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport::default();
+
+        // Without a dimension no value can be attributed to an ID
+        let mut complete = match self.dimension {
+            Some(d) if d > 0 => self.stored_values() / d,
+            _ => 0,
+        };
+        if self.config.quantization == Quantization::Int8 {
+            complete = complete.min(self.scales.len());
+        }
+        let kept = complete.min(self.ids.len());
+
+        report.dropped_ids = self.ids.split_off(kept);
+        for id in &report.dropped_ids {
+            self.metadata.remove(id);
+        }
+        let kept_values = kept * self.dimension.unwrap_or(0);
+        report.truncated_values = self.stored_values() - kept_values;
+        self.truncate_storage(kept);
+
+        if self.ids.is_empty() {
+            self.dimension = None;
+        }
+        self.rebuild_index();
+        if let Some(mut ivf) = self.ivf.take() {
+            ivf.reassign(self.ids.len(), |i| self.get_vector(i));
+            self.ivf = Some(ivf);
+        }
+
+        report
+    }
+
+    /// Applies the ID policy and custom normalizer to an ID.
+    fn normalize_id(&self, id: &str) -> String {
+        let mut id = if self.id_policy.trim { id.trim() } else { id }.to_string();
+        if self.id_policy.lowercase {
+            id = id.to_lowercase();
+        }
+        match self.id_normalizer {
+            Some(normalizer) => normalizer(&id),
+            None => id,
+        }
+    }
+
+    /// Finds the storage index of an ID, after normalizing it.
+    fn position(&self, id: &str) -> Option<usize> {
+        self.index.get(&self.normalize_id(id)).copied()
+    }
+
+    /// Rebuilds the ID to index map from `ids`.
+    fn rebuild_index(&mut self) {
+        self.index = self
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(i, id)| (id.clone(), i))
+            .collect();
+    }
+
+    /// Retrieves a vector slice from the flat array by index.
+    ///
+    /// This is a private helper function that efficiently slices the flat vector
+    /// array to return a reference to the vector at the given index. The vectors
+    /// are stored contiguously as: `[v1_d1, v1_d2, ..., v2_d1, v2_d2, ...]`
+    /// Under [`Quantization::Int8`] the vector is dequantized into a new buffer.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - The index of the vector (0-based)
+    ///
+    /// # Returns
+    ///
+    /// The vector at the specified index, borrowed when stored as `f32`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dimension is `None` or if the index is out of bounds.
+    fn get_vector(&self, index: usize) -> Cow<'_, [f32]> {
+        let start = index * self.dimension.unwrap();
+        let end = start + self.dimension.unwrap();
+        match self.config.storage() {
+            Storage::F32 => Cow::Borrowed(&self.vectors[start..end]),
+            Storage::F64 => Cow::Owned(self.wide[start..end].iter().map(|&x| x as f32).collect()),
+            Storage::Int8 => {
+                Cow::Owned(dequantize_int8(&self.codes[start..end], self.scales[index]))
+            }
+            Storage::Binary => Cow::Owned(dequantize_binary(
+                self.bits_at(index),
+                self.dimension.unwrap(),
+            )),
+        }
+    }
+
+    /// Appends a vector (already prepared by `to_stored`) to the storage.
+    fn push_vector(&mut self, vector: &[f32]) {
+        match self.config.storage() {
+            Storage::F32 => self.vectors.extend_from_slice(vector),
+            Storage::F64 => self.wide.extend(vector.iter().map(|&x| x as f64)),
+            Storage::Int8 => {
+                let (codes, scale) = quantize_int8(vector);
+                self.codes.extend(codes);
+                self.scales.push(scale);
+            }
+            Storage::Binary => self.bits.extend(quantize_binary(vector)),
+        }
+        if let Some(ivf) = &mut self.ivf {
+            ivf.push(vector);
+        }
+        self.extend_graph();
+    }
+
+    /// Overwrites the stored vector at `index`.
+    fn set_vector(&mut self, index: usize, vector: &[f32]) {
+        self.forget_persisted();
+        self.drop_graph();
+        if let Some(ivf) = &mut self.ivf {
+            ivf.set(index, vector);
+        }
+        let start = index * vector.len();
+        let end = start + vector.len();
+        match self.config.storage() {
+            Storage::F32 => self.vectors[start..end].copy_from_slice(vector),
+            Storage::F64 => {
+                for (stored, &x) in self.wide[start..end].iter_mut().zip(vector) {
+                    *stored = x as f64;
+                }
+            }
+            Storage::Int8 => {
+                let (codes, scale) = quantize_int8(vector);
+                self.codes[start..end].copy_from_slice(&codes);
+                self.scales[index] = scale;
+            }
+            Storage::Binary => {
+                let words = vector.len().div_ceil(64);
+                self.bits[index * words..(index + 1) * words]
+                    .copy_from_slice(&quantize_binary(vector));
+            }
+        }
+    }
+
+    /// Removes the vector at `index` from the storage and returns it.
+    fn take_vector(&mut self, index: usize) -> Vec<f32> {
+        self.forget_persisted();
+        self.drop_graph();
+        if let Some(ivf) = &mut self.ivf {
+            ivf.remove(index);
+        }
+        let dim = self.dimension.unwrap();
+        let range = index * dim..(index + 1) * dim;
+        match self.config.storage() {
+            Storage::F32 => self.vectors.drain(range).collect(),
+            Storage::F64 => self.wide.drain(range).map(|x| x as f32).collect(),
+            Storage::Int8 => {
+                let codes: Vec<i8> = self.codes.drain(range).collect();
+                dequantize_int8(&codes, self.scales.remove(index))
+            }
+            Storage::Binary => {
+                let words = dim.div_ceil(64);
+                let bits: Vec<u64> = self
+                    .bits
+                    .drain(index * words..(index + 1) * words)
+                    .collect();
+                dequantize_binary(&bits, dim)
+            }
+        }
+    }
+
+    /// Overwrites the vector at `index` with the last one, then drops the last.
+    fn swap_remove_vector(&mut self, index: usize) {
+        self.drop_graph();
+        if let Some(ivf) = &mut self.ivf {
+            ivf.swap_remove(index);
+        }
+        let last = self.ids.len() - 1;
+        if index != last {
+            self.move_vector(last, index);
+        }
+        self.truncate_storage(last);
+    }
+
+    /// Keeps only the vectors whose `keep` flag is set, in order.
+    ///
+    /// Survivors are moved down in one pass and the tail is truncated once,
+    /// instead of shifting the storage for every removed vector.
+    fn retain_vectors(&mut self, keep: &[bool]) {
+        self.drop_graph();
+        if let Some(ivf) = &mut self.ivf {
+            ivf.retain(keep);
+        }
+        let mut kept = 0;
+        for (index, _) in keep.iter().enumerate().filter(|(_, keep)| **keep) {
+            if index != kept {
+                self.move_vector(index, kept);
+            }
+            kept += 1;
+        }
+        self.truncate_storage(kept);
+    }
+
+    /// Copies the stored vector at `from` over the one at `to`.
+    /// Quantized data is moved as is, without requantizing.
+    fn move_vector(&mut self, from: usize, to: usize) {
+        let dim = self.dimension.unwrap();
+        match self.config.storage() {
+            Storage::F32 => self
+                .vectors
+                .copy_within(from * dim..(from + 1) * dim, to * dim),
+            Storage::F64 => self
+                .wide
+                .copy_within(from * dim..(from + 1) * dim, to * dim),
+            Storage::Int8 => {
+                self.codes
+                    .copy_within(from * dim..(from + 1) * dim, to * dim);
+                self.scales[to] = self.scales[from];
+            }
+            Storage::Binary => {
+                let words = dim.div_ceil(64);
+                self.bits
+                    .copy_within(from * words..(from + 1) * words, to * words);
+            }
+        }
+    }
+
+    /// Keeps only the first `count` vectors in the storage.
+    fn truncate_storage(&mut self, count: usize) {
+        self.forget_persisted();
+        self.drop_graph();
+        if let Some(ivf) = &mut self.ivf {
+            ivf.truncate(count);
+        }
+        let dim = self.dimension.unwrap_or(0);
+        self.vectors.truncate(count * dim);
+        self.codes.truncate(count * dim);
+        self.scales.truncate(count);
+        self.bits.truncate(count * dim.div_ceil(64));
+        self.wide.truncate(count * dim);
+    }
+
+    /// Returns the number of stored components, whatever the quantization.
+    fn stored_values(&self) -> usize {
+        match self.config.storage() {
+            Storage::F32 => self.vectors.len(),
+            Storage::F64 => self.wide.len(),
+            Storage::Int8 => self.codes.len(),
+            // Only whole vectors count; their padding bits are not components
+            Storage::Binary => match self.dimension {
+                Some(d) if d > 0 => self.bits.len() / d.div_ceil(64) * d,
+                _ => 0,
+            },
+        }
+    }
+
+    /// Returns the vector at `index` as `f64`, borrowed under [`Precision::F64`].
+    fn get_wide(&self, index: usize) -> Cow<'_, [f64]> {
+        let dim = self.dimension.unwrap();
+        match self.config.storage() {
+            Storage::F64 => Cow::Borrowed(&self.wide[index * dim..(index + 1) * dim]),
+            _ => Cow::Owned(self.get_vector(index).iter().map(|&x| x as f64).collect()),
+        }
+    }
+
+    /// Replaces the rounded values just stored for `id` with the exact ones,
+    /// under [`Precision::F64`]. `id` must already be normalized and stored.
+    fn set_wide(&mut self, id: &str, stored: &[f64]) {
+        let index = self.index[id];
+        self.wide[index * stored.len()..(index + 1) * stored.len()].copy_from_slice(stored);
+    }
+
+    /// Switches to `config`, converting the stored vectors if their layout changes.
+    fn convert_storage(&mut self, config: DbConfig) {
+        if config.storage() != self.config.storage() {
+            let vectors: Vec<Vec<f32>> = (0..self.count())
+                .map(|i| self.get_vector(i).into_owned())
+                .collect();
+            self.truncate_storage(0);
+            self.config = config;
+            for vector in &vectors {
+                self.push_vector(vector);
+            }
+        }
+        self.config = config;
+        self.forget_persisted();
+    }
+
+    /// Links the vector just stored for the newest ID into the index graph.
+    ///
+    /// Does nothing without a graph; a graph that doesn't end right before the
+    /// new vector is dropped, to be rebuilt by the next search.
+    fn extend_graph(&mut self) {
+        let Some(mut graph) = self.graph.get_mut().unwrap().take() else {
+            return;
+        };
+        if graph.len() + 1 == self.ids.len() {
+            graph.insert(|a, b| self.graph_distance(a, b));
+            *self.graph.get_mut().unwrap() = Some(graph);
+        }
+    }
+
+    /// Drops the index graph after stored vectors moved or changed.
+    fn drop_graph(&mut self) {
+        *self.graph.get_mut().unwrap() = None;
+    }
+
+    /// Builds an index graph over every stored vector, for an
+    /// [`IndexKind::Hnsw`] index.
+    fn build_graph(&self, kind: IndexKind) -> Option<Hnsw> {
+        let IndexKind::Hnsw {
+            m,
+            ef_construction,
+            ef_search,
+        } = kind
+        else {
+            return None;
+        };
+
+        let mut graph = Hnsw::new(m, ef_construction, ef_search);
+        for _ in 0..self.ids.len() {
+            graph.insert(|a, b| self.graph_distance(a, b));
+        }
+        Some(graph)
+    }
+
+    /// Distance between the stored vectors at `a` and `b` for the index graph,
+    /// lower is closer whatever the metric.
+    fn graph_distance(&self, a: usize, b: usize) -> f32 {
+        let target = self.get_vector(b);
+        let target_bits = self.query_bits(&target);
+        -self
+            .config
+            .metric
+            .rank_key(self.score_at(a, &target, target_bits.as_deref()))
+    }
+
+    /// Searches the index graph, rebuilding it first if it is missing.
+    ///
+    /// Returns the `top_k` best `(index, score)` pairs found, best first.
+    fn search_graph(
+        &self,
+        kind: IndexKind,
+        norm_q: &[f32],
+        top_k: usize,
+        metrics: &mut SearchMetrics,
+    ) -> Vec<(usize, f32)> {
+        let stale = self
+            .graph
+            .read()
+            .unwrap()
+            .as_ref()
+            .is_none_or(|graph| graph.len() != self.ids.len());
+        if stale {
+            let graph = self.build_graph(kind);
+            *self.graph.write().unwrap() = graph;
+        }
+
+        let metric = self.config.metric;
+        let query_bits = self.query_bits(norm_q);
+        let graph = self.graph.read().unwrap();
+        graph
+            .as_ref()
+            .unwrap()
+            .search(top_k, |i| {
+                metrics.comparisons += 1;
+                -metric.rank_key(self.score_at(i, norm_q, query_bits.as_deref()))
+            })
+            .into_iter()
+            .map(|(index, key)| (index, self.clamp_score(metric.rank_key(-key))))
+            .collect()
+    }
+
+    /// Scans the vectors of the `nprobe` IVF groups closest to the query, or
+    /// every vector while the index is untrained.
+    ///
+    /// Returns the `top_k` best `(index, score)` pairs found, best first.
+    fn search_ivf(
+        &self,
+        norm_q: &[f32],
+        top_k: usize,
+        nprobe: usize,
+        metrics: &mut SearchMetrics,
+    ) -> Vec<(usize, f32)> {
+        match &self.ivf {
+            Some(ivf) if ivf.is_trained() => {
+                self.rank(norm_q, ivf.probe(norm_q, nprobe), top_k, metrics)
+            }
+            _ => self.rank(norm_q, 0..self.ids.len(), top_k, metrics),
+        }
+    }
+
+    /// Returns the packed sign bits of the vector at `index`.
+    fn bits_at(&self, index: usize) -> &[u64] {
+        let words = self.dimension.unwrap().div_ceil(64);
+        &self.bits[index * words..(index + 1) * words]
+    }
+
+    /// Packs the query's sign bits under [`Quantization::Binary`], once per
+    /// search, for [`score_at`](VecDB::score_at).
+    fn query_bits(&self, norm_q: &[f32]) -> Option<Vec<u64>> {
+        (self.config.quantization == Quantization::Binary).then(|| quantize_binary(norm_q))
+    }
+
+    /// Scores the vector at `index` against a prepared query.
+    ///
+    /// With `query_bits` (binary quantization) only the Hamming distance `h`
+    /// is computed. It is converted to the configured metric's score between
+    /// the two unit sign vectors, which is monotonic in `h`: cosine and dot
+    /// product give `1 - 2h/d`, Euclidean `2·sqrt(h/d)`, Manhattan `2h/sqrt(d)`.
+    fn score_at(&self, index: usize, norm_q: &[f32], query_bits: Option<&[u64]>) -> f32 {
+        let Some(query_bits) = query_bits else {
+            return self
+                .config
+                .metric
+                .score(&self.get_vector(index), norm_q)
+                .unwrap();
+        };
+
+        let h = hamming_distance(query_bits, self.bits_at(index)) as f32;
+        let d = self.dimension.unwrap() as f32;
+        match self.config.metric {
+            Metric::Cosine | Metric::DotProduct => 1.0 - 2.0 * h / d,
+            Metric::Euclidean => 2.0 * (h / d).sqrt(),
+            Metric::Manhattan => 2.0 * h / d.sqrt(),
+        }
+    }
+
+    /// Scores the vectors at `indices` against a normalized query.
+    ///
+    /// Returns the `top_k` best `(index, score)` pairs, best first.
+    fn rank(
+        &self,
+        norm_q: &[f32],
+        indices: impl Iterator<Item = usize>,
+        top_k: usize,
+        metrics: &mut SearchMetrics,
+    ) -> Vec<(usize, f32)> {
+        // Negated rank keys, so the lowest keys are the best matches
+        let metric = self.config.metric;
+        self.lowest_keys(norm_q, indices, top_k, metrics, |score| {
+            -metric.rank_key(score)
+        })
+        .into_iter()
+        .map(|(index, key)| (index, self.clamp_score(metric.rank_key(-key))))
+        .collect()
+    }
+
+    /// Clamps a cosine similarity to `[-1.0, 1.0]` in normalized mode.
+    ///
+    /// Float rounding can push a self-match slightly above 1.0. Only reported
+    /// scores are clamped, after ranking, so the order of results is unchanged.
+    fn clamp_score(&self, score: f32) -> f32 {
+        if self.config.metric.normalizes() && self.config.normalize {
+            score.clamp(-1.0, 1.0)
+        } else {
+            score
+        }
+    }
+
+    /// Keeps the `top_k` vectors at `indices` with the lowest `key(score)`.
+    ///
+    /// Uses a bounded max-heap: the root is the highest key kept so far, i.e. the
+    /// one to evict when a lower key shows up. This is O(n log k) with no shifting,
+    /// and sorts only the `top_k` survivors at the end. Returns `(index, key)`
+    /// pairs in ascending order of key, ties broken by index.
+    fn lowest_keys(
+        &self,
+        norm_q: &[f32],
+        indices: impl Iterator<Item = usize>,
+        top_k: usize,
+        metrics: &mut SearchMetrics,
+        key: impl Fn(f32) -> f32,
+    ) -> Vec<(usize, f32)> {
+        if top_k == 0 {
+            return Vec::new();
+        }
+
+        let query_bits = self.query_bits(norm_q);
+        let mut heap: BinaryHeap<Candidate> = BinaryHeap::with_capacity(top_k);
+        for index in indices {
+            let score = key(self.score_at(index, norm_q, query_bits.as_deref()));
+            metrics.comparisons += 1;
+
+            if heap.len() < top_k {
+                heap.push(Candidate { score, index });
+                metrics.heap_updates += 1;
+            } else if let Some(mut worst) = heap.peek_mut()
+                && score < worst.score
+            {
+                *worst = Candidate { score, index };
+                metrics.heap_updates += 1;
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|c| (c.index, c.score))
+            .collect()
+    }
+
+    /// Ranks the `top_k` stored vectors closest to the one at `index`, excluding it.
+    fn neighbors_of(&self, index: usize, top_k: usize) -> Vec<(usize, f32)> {
+        let vector = self.get_vector(index);
+        self.rank(
+            &vector,
+            (0..self.ids.len()).filter(|&i| i != index),
+            top_k,
+            &mut SearchMetrics::default(),
+        )
+    }
+
+    /// Turns ranked `(index, score)` pairs into search results.
+    fn to_results(&self, ranked: &[(usize, f32)]) -> SearchResults {
+        ranked
+            .iter()
+            .map(|&(i, score)| SearchResult {
+                id: self.ids[i].clone(),
+                vector: self.get_vector(i).to_vec(),
+                score,
+            })
+            .collect()
+    }
+
+    /// Stores an already validated vector under `id`, updating it if the ID
+    /// exists. Returns the success message for insert.
+    fn store(&mut self, id: String, dim: usize, stored: &[f32]) -> String {
+        // Check if ID exists and update instead
+        if let Some(index) = self.position(&id) {
+            // Update existing vector
+            self.set_vector(index, stored);
+            return format!("Updated vector with id: {}", id);
+        }
+
+        // A full database drops its oldest vector first
+        let evicted = match self.capacity {
+            Some(max) if self.ids.len() >= max => {
+                // The stored ID is already normalized, so evict by position
+                let oldest = self.ids[0].clone();
+                self.remove_at(0);
+                Some(oldest)
+            }
+            _ => None,
+        };
+
+        // Only a valid first vector fixes the dimension
+        self.dimension = Some(dim);
+        self.index.insert(id.clone(), self.ids.len());
+        self.ids.push(id);
+        self.push_vector(stored);
+
+        match evicted {
+            Some(oldest) => format!(
+                "Inserted to database with id; evicted oldest id: {}",
+                oldest
+            ),
+            None => "Inserted to database with id".to_string(),
+        }
+    }
+
+    /// Checks a vector against the database dimension and converts it to its
+    /// stored form. Shared by insert and update.
+    fn validate_vector(&self, vector: &[f32]) -> Result<Vec<f32>, String> {
+        if let Some(d) = self.dimension
+            && vector.len() != d
+        {
+            return Err("Different dimension".to_string());
+        }
+
+        self.to_stored(vector)
+    }
+
+    /// Validates a query against the database dimension and normalizes it.
+    ///
+    /// Shared by all search methods so they report the same errors.
+    fn prepare_query(&self, query: &[f32]) -> Result<Vec<f32>, String> {
+        match self.dimension {
+            None => Err("Empty database".to_string()),
+            Some(d) if query.len() != d => Err(format!(
+                "Wrong query dimension: expected {}, got {}",
+                d,
+                query.len()
+            )),
+            Some(_) => self.to_stored(query),
+        }
+    }
+
+    /// Prepares a vector for storage or scoring under the configured metric.
+    ///
+    /// Only [`Metric::Cosine`] normalizes, unless normalization is turned off;
+    /// otherwise the raw values are kept.
+    fn to_stored(&self, vector: &[f32]) -> Result<Vec<f32>, String> {
+        if self.config.metric.normalizes() && self.config.normalize {
+            l2_norm(vector)
+        } else if vector.is_empty() {
+            Err("Cannot insert an empty vector".to_string())
+        } else {
+            check_finite(vector)?;
+            Ok(vector.to_vec())
+        }
+    }
+
+    /// Same as [`to_stored`](VecDB::to_stored), in `f64`.
+    fn to_stored_f64(&self, vector: &[f64]) -> Result<Vec<f64>, String> {
+        if self.config.metric.normalizes() && self.config.normalize {
+            l2_norm_f64(vector)
+        } else if vector.is_empty() {
+            Err("Cannot insert an empty vector".to_string())
+        } else {
+            check_finite_f64(vector)?;
+            Ok(vector.to_vec())
+        }
+    }
+
+    /// Saves the database to a file using bincode serialization.
+    ///
+    /// All vectors, IDs, and dimension metadata are serialized into a compact
+    /// binary format and written to disk using buffered I/O. The file is
+    /// synced to disk before returning, so a successful save survives a crash.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the database to
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Database saved successfully
+    /// * `Err(String)` - Error if file creation or serialization fails
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    /// db.save("my_database.db").unwrap();
+    /// ```
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        self.save_as(path, Format::Bincode)
+    }
+
+    /// Saves the database as gzip-compressed bincode.
+    ///
+    /// Shorthand for [`save_as`](VecDB::save_as) with [`Format::BincodeGz`].
+    /// [`load`](VecDB::load) recognizes the gzip magic bytes, so compressed
+    /// files load without saying which variant was used.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    /// db.save_compressed("my_database.db.gz").unwrap();
+    ///
+    /// let loaded = VecDB::load("my_database.db.gz").unwrap();
+    /// assert_eq!(loaded.count(), 1);
+    /// ```
+    pub fn save_compressed(&self, path: &str) -> Result<(), String> {
+        self.save_as(path, Format::BincodeGz)
+    }
+
+    /// Saves the database to a file in the given [`Format`].
+    ///
+    /// Files in any format can be read back with [`load`](VecDB::load), which
+    /// detects the format, or with [`load_as`](VecDB::load_as).
+    ///
+    /// The file is fully rewritten from this database, so its WAL is emptied too:
+    /// pending records are either already held by the database or belong to the
+    /// data being replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to save the database to
+    /// * `format` - On-disk format to write
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Database saved successfully
+    /// * `Err(String)` - Error if file creation or serialization fails
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::{Format, VecDB};
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    /// db.save_as("my_database.db.gz", Format::BincodeGz).unwrap();
+    ///
+    /// let loaded = VecDB::load("my_database.db.gz").unwrap();
+    /// assert_eq!(loaded.count(), 1);
+    /// ```
+    pub fn save_as(&self, path: &str, format: Format) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Fail to create file for saving '{}': {}", path, e))?;
+
+        self.write_as(&file, format)?;
+        // Don't report success while the data may still sit in the OS cache
+        file.sync_all()
+            .map_err(|e| format!("Fail to sync '{}': {}", path, e))?;
+
+        // A stale WAL would otherwise be replayed on top of the new contents
+        wal::truncate(path)?;
+        self.mark_persisted(path);
+
+        Ok(())
+    }
+
+    /// Dumps the raw vector data and a JSON metadata file for external tools.
+    ///
+    /// `vectors_path` receives the flat vector array as raw little-endian `f32`
+    /// bytes (`count * dimension` values, row-major, no header). `meta_path`
+    /// receives a JSON object `{"count": .., "dimension": .., "ids": [..]}` where
+    /// `ids[i]` labels row `i`. With NumPy the data can be read back with
+    /// `np.fromfile(vectors_path, dtype="<f4").reshape(count, dimension)`.
+    ///
+    /// # Arguments
+    ///
+    /// * `vectors_path` - File path for the raw vector bytes
+    /// * `meta_path` - File path for the JSON metadata
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Both files written successfully
+    /// * `Err(String)` - Error if file creation or writing fails
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    /// db.dump_flat("vectors.f32", "meta.json").unwrap();
+    /// ```
+    pub fn dump_flat(&self, vectors_path: &str, meta_path: &str) -> Result<(), String> {
+        let file = File::create(vectors_path)
+            .map_err(|e| format!("Fail to create file for saving '{}': {}", vectors_path, e))?;
+        let mut writer = BufWriter::new(file);
+        for i in 0..self.count() {
+            for value in self.get_vector(i).iter() {
+                writer
+                    .write_all(&value.to_le_bytes())
+                    .map_err(|e| format!("Fail to write '{}': {}", vectors_path, e))?;
+            }
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Fail to write '{}': {}", vectors_path, e))?;
+
+        let meta = serde_json::json!({
+            "count": self.count(),
+            "dimension": self.dimension,
+            "ids": self.ids,
+        });
+        let file = File::create(meta_path)
+            .map_err(|e| format!("Fail to create file for saving '{}': {}", meta_path, e))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &meta)
+            .map_err(|e| format!("Serialization failed: {}", e))?;
+        writer
+            .flush()
+            .map_err(|e| format!("Fail to write '{}': {}", meta_path, e))?;
+
+        Ok(())
+    }
+
+    /// Loads a database from a file previously saved with [`save`](VecDB::save).
+    ///
+    /// Deserializes the binary file back into a fully functional `VecDB` instance
+    /// with all vectors, IDs, and dimension metadata restored. Files written by
+    /// [`save_as`](VecDB::save_as) in any [`Format`] are detected and loaded too.
+    ///
+    /// If a write-ahead log exists next to the file (`<path>.wal`, see [`wal`](crate::wal)),
+    /// its records are replayed on top of the loaded data, the merged database is
+    /// saved back to `path`, and the log is truncated. This lets a crashed writer
+    /// recover transparently on the next load. Use
+    /// [`load_with_options`](VecDB::load_with_options) to skip the replay.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to load the database from
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(VecDB)` - The loaded database
+    /// * `Err(String)` - Error if file not found, cannot be opened, or deserialization fails
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let db = VecDB::load("my_database.db").unwrap();
+    /// println!("Loaded {} vectors", db.count());
+    /// ```
+    pub fn load(path: &str) -> Result<Self, String> {
+        Self::load_with_options(path, true)
+    }
+
+    /// Loads a database from a file, optionally replaying its write-ahead log.
+    ///
+    /// With `replay_wal` set to `false` only the base file is read and any
+    /// `<path>.wal` sidecar is left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to load the database from
+    /// * `replay_wal` - Whether to replay and compact the sidecar WAL
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(VecDB)` - The loaded database
+    /// * `Err(String)` - Error if the file cannot be read, deserialization fails,
+    ///   or a WAL record cannot be applied
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// // Inspect the base file without applying pending WAL records
+    /// let db = VecDB::load_with_options("my_database.db", false).unwrap();
+    /// ```
+    pub fn load_with_options(path: &str, replay_wal: bool) -> Result<Self, String> {
+        Self::read_file(path, None, replay_wal)
+    }
+
+    /// Loads a database saved in a known [`Format`], skipping detection.
+    ///
+    /// The write-ahead log is replayed as in [`load`](VecDB::load), and the
+    /// merged database is saved back in the same format.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - File path to load the database from
+    /// * `format` - On-disk format of the file
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(VecDB)` - The loaded database
+    /// * `Err(String)` - Error if the file cannot be read, is not in `format`, or
+    ///   a WAL record cannot be applied
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::{Format, VecDB};
+    ///
+    /// let db = VecDB::load_as("my_database.json", Format::Json).unwrap();
+    /// ```
+    pub fn load_as(path: &str, format: Format) -> Result<Self, String> {
+        Self::read_file(path, Some(format), true)
+    }
+
+    /// Load implementation shared by [`load_with_options`](VecDB::load_with_options)
+    /// and [`load_as`](VecDB::load_as); detects the format when `format` is `None`.
+    fn read_file(path: &str, format: Option<Format>, replay_wal: bool) -> Result<Self, String> {
+        if !std::path::Path::new(path).exists() {
+            return Err("File not found!".to_string());
+        }
+
+        let file = File::open(path)
+            .map_err(|e| format!("Fail to create file for saving '{}': {}", path, e))?;
+
+        let (mut db, format) = Self::read_as(file, format)?;
+
+        if replay_wal {
+            let records = wal::read_all(path)?;
+            if !records.is_empty() {
+                for record in records {
+                    db.apply_wal_record(record)?;
+                }
+                db.save_as(path, format)?;
+                wal::truncate(path)?;
+            }
+        }
+        db.mark_persisted(path);
+
+        Ok(db)
+    }
+
+    /// Writes the database as bincode to any writer, e.g. an in-memory buffer
+    /// or a network stream.
+    ///
+    /// Produces the same bytes as [`save`](VecDB::save); read them back with
+    /// [`load_from`](VecDB::load_from). The writer is buffered internally.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    ///
+    /// let mut buffer = Vec::new();
+    /// db.save_to(&mut buffer).unwrap();
+    ///
+    /// let loaded = VecDB::load_from(buffer.as_slice()).unwrap();
+    /// assert_eq!(loaded.get("vec1"), db.get("vec1"));
+    /// ```
+    pub fn save_to<W: Write>(&self, writer: W) -> Result<(), String> {
+        self.write_as(writer, Format::Bincode)
+    }
+
+    /// Reads a database from any reader, detecting the [`Format`] as
+    /// [`load`](VecDB::load) does.
+    ///
+    /// There is no WAL to replay without a file path.
+    pub fn load_from<R: Read>(reader: R) -> Result<Self, String> {
+        Self::read_as(reader, None).map(|(db, _)| db)
+    }
+
+    /// Serializes the database to `writer` in the given format.
+    fn write_as<W: Write>(&self, writer: W, format: Format) -> Result<(), String> {
+        let mut writer = BufWriter::new(writer);
+        let write_error = |e: std::io::Error| format!("Fail to write: {}", e);
+        match format {
+            Format::Bincode => {
+                writer.write_all(&self.header()).map_err(write_error)?;
+                bincode_options()
+                    .serialize_into(&mut writer, self)
+                    .map_err(|e| format!("Serialization failed: {}", e))?;
+            }
+            Format::Json => serde_json::to_writer(&mut writer, self)
+                .map_err(|e| format!("Serialization failed: {}", e))?,
+            Format::BincodeGz => {
+                let mut encoder = GzEncoder::new(&mut writer, Compression::default());
+                encoder.write_all(&self.header()).map_err(write_error)?;
+                bincode_options()
+                    .serialize_into(&mut encoder, self)
+                    .map_err(|e| format!("Serialization failed: {}", e))?;
+                encoder.finish().map_err(write_error)?;
+            }
+        }
+        writer.flush().map_err(write_error)
+    }
+
+    /// Deserializes a database from `reader`, detecting the format unless
+    /// one is given. Returns the format that was read.
+    fn read_as<R: Read>(reader: R, format: Option<Format>) -> Result<(Self, Format), String> {
+        let mut bytes = Vec::new();
+        BufReader::new(reader)
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("Fail to read: {}", e))?;
+        let detected = format.is_none();
+        let format = format.unwrap_or_else(|| Format::detect(&bytes));
+
+        // A detected format that fails to parse may be a headerless 0.3 file
+        // whose leading ID count happens to look like gzip or JSON
+        let legacy = |error: String| match Self::from_legacy(&bytes) {
+            Ok(db) if detected => Ok((db, Format::Bincode)),
+            _ => Err(error),
+        };
+        let (mut db, format): (VecDB, Format) = match format {
+            Format::Bincode => (Self::from_bincode(&bytes)?, format),
+            Format::BincodeGz => {
+                let mut decompressed = Vec::new();
+                match GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed) {
+                    Ok(_) => (Self::from_bincode(&decompressed)?, format),
+                    Err(e) => legacy(format!("Fail to decompress: {}", e))?,
+                }
+            }
+            Format::Json => match serde_json::from_slice(&bytes) {
+                Ok(db) => (db, format),
+                Err(e) => legacy(format!("Deserialization failed: {}", e))?,
+            },
+        };
+        db.rebuild_index();
+
+        Ok((db, format))
+    }
+
+    /// Saves only the vectors inserted since the last save or load of `path`.
+    ///
+    /// New vectors are appended to the `<path>.wal` sidecar as length-prefixed
+    /// records, which [`load`](VecDB::load) replays. This avoids rewriting a
+    /// large file after a few inserts. If `path` wasn't the last file saved or
+    /// loaded, or anything else changed since (an update, delete, rename,
+    /// metadata change, ...), the whole database is written as by
+    /// [`save`](VecDB::save) instead. So is a database using [`Precision::F64`],
+    /// since WAL records hold `f32` vectors.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::load("my_database.db").unwrap();
+    /// db.insert("new".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    /// db.save_append("my_database.db").unwrap();
+    /// ```
+    pub fn save_append(&self, path: &str) -> Result<(), String> {
+        let persisted = self
+            .persisted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone();
+        let start = match persisted {
+            Some(Persisted {
+                path: p,
+                count: Some(count),
+            }) if p == path && count <= self.count() && self.config.storage() != Storage::F64 => {
+                count
+            }
+            _ => return self.save(path),
+        };
+
+        let records: Vec<WalRecord> = (start..self.count())
+            .map(|i| WalRecord::Insert {
+                id: self.ids[i].clone(),
+                vector: self.get_vector(i).to_vec(),
+            })
+            .collect();
+        wal::append_all(path, &records)?;
+        self.mark_persisted(path);
+
+        Ok(())
+    }
+
+    /// Returns the file this database was last loaded from or saved to, if any.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let db = VecDB::load("my_database.db").unwrap();
+    /// assert_eq!(db.persisted_path().as_deref(), Some("my_database.db"));
+    /// ```
+    pub fn persisted_path(&self) -> Option<String> {
+        self.persisted
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+            .map(|persisted| persisted.path.clone())
+    }
+
+    /// Records that the file at `path` holds every current vector.
+    fn mark_persisted(&self, path: &str) {
+        *self.persisted.lock().unwrap_or_else(|e| e.into_inner()) = Some(Persisted {
+            path: path.to_string(),
+            count: Some(self.count()),
+        });
+    }
+
+    /// Forces the next `save_append` to rewrite the whole file.
+    fn forget_persisted(&mut self) {
+        if let Some(persisted) = self.persisted.get_mut().unwrap_or_else(|e| e.into_inner()) {
+            persisted.count = None;
+        }
+    }
+
+    /// Loads a database saved with [`save_compressed`](VecDB::save_compressed).
+    ///
+    /// Shorthand for [`load_as`](VecDB::load_as) with [`Format::BincodeGz`];
+    /// [`load`](VecDB::load) reads these files too.
+    pub fn load_compressed(path: &str) -> Result<Self, String> {
+        Self::load_as(path, Format::BincodeGz)
+    }
+
+    /// Writes every vector to a human-readable JSON file, for inspection,
+    /// hand-editing or other tools.
+    ///
+    /// The file is an array of `{"id": ..., "values": [...]}` objects in
+    /// insertion order, holding the stored (normalized) values. Unlike
+    /// [`save_as`](VecDB::save_as) with [`Format::Json`], it keeps nothing
+    /// else: configuration and metadata are not exported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    /// db.export_json("vectors.json").unwrap();
+    /// // [{"id": "vec1", "values": [0.6, 0.8]}]
+    ///
+    /// let imported = VecDB::import_json("vectors.json").unwrap();
+    /// assert_eq!(imported.count(), 1);
+    /// ```
+    pub fn export_json(&self, path: &str) -> Result<(), String> {
+        let entries: Vec<ExportEntry> = self
+            .iter()
+            .map(|(id, values)| ExportEntry {
+                id: id.to_string(),
+                values: values.into_owned(),
+            })
+            .collect();
+
+        let file = File::create(path)
+            .map_err(|e| format!("Fail to create file for saving '{}': {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(&mut writer, &entries)
+            .map_err(|e| format!("Serialization failed: {}", e))?;
+        writer
+            .flush()
+            .map_err(|e| format!("Fail to write '{}': {}", path, e))
+    }
+
+    /// Builds a new database from a file written by [`export_json`](VecDB::export_json).
+    ///
+    /// Every entry is inserted as with [`insert`](VecDB::insert), so values are
+    /// normalized again and the dimension is taken from the first entry.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(VecDB)` - The imported database
+    /// * `Err(String)` - Error if the file can't be read or parsed, or an entry
+    ///   has a different length than the first one
+    pub fn import_json(path: &str) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Fail to open '{}': {}", path, e))?;
+        let entries: Vec<ExportEntry> = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| format!("Deserialization failed: {}", e))?;
+
+        let mut db = VecDB::new();
+        for (i, entry) in entries.into_iter().enumerate() {
+            if let Some(d) = db.dimension
+                && entry.values.len() != d
+            {
+                return Err(format!(
+                    "Entry {} ('{}') has {} values, expected {}",
+                    i,
+                    entry.id,
+                    entry.values.len(),
+                    d
+                ));
+            }
+            db.insert(entry.id.clone(), entry.values)
+                .map_err(|e| format!("Entry {} ('{}'): {}", i, entry.id, e))?;
+        }
+
+        Ok(db)
+    }
+
+    /// Builds a new database from a CSV file of vectors.
+    ///
+    /// Each row is an ID followed by its components, e.g. `doc1,0.1,0.2,0.3`.
+    /// Rows are inserted as with [`insert`](VecDB::insert), so the first row
+    /// sets the dimension. Blank lines are skipped. Fields are not quoted.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - CSV file to read
+    /// * `has_header` - Skip the first line as a header row
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(VecDB)` - The imported database
+    /// * `Err(String)` - Error naming the 1-based line number of a row that
+    ///   can't be parsed or has a different dimension
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let db = VecDB::import_csv("embeddings.csv", true).unwrap();
+    /// println!("Imported {} vectors", db.count());
+    /// ```
+    pub fn import_csv(path: &str, has_header: bool) -> Result<Self, String> {
+        let file = File::open(path).map_err(|e| format!("Fail to open '{}': {}", path, e))?;
+
+        let mut db = VecDB::new();
+        for (i, line) in BufReader::new(file).lines().enumerate() {
+            let line_no = i + 1;
+            let line = line.map_err(|e| format!("Fail to read '{}': {}", path, e))?;
+            if (has_header && i == 0) || line.trim().is_empty() {
+                continue;
+            }
+
+            let mut fields = line.split(',').map(str::trim);
+            let id = fields.next().unwrap_or_default();
+            if id.is_empty() {
+                return Err(format!("Line {}: missing ID", line_no));
+            }
+            let vector = fields
+                .map(|field| {
+                    field
+                        .parse::<f32>()
+                        .map_err(|_| format!("Line {}: invalid component '{}'", line_no, field))
+                })
+                .collect::<Result<Vec<f32>, String>>()?;
+            if let Some(d) = db.dimension
+                && vector.len() != d
+            {
+                return Err(format!(
+                    "Line {}: expected {} components, got {}",
+                    line_no,
+                    d,
+                    vector.len()
+                ));
+            }
+
+            db.insert(id.to_string(), vector)
+                .map_err(|e| format!("Line {}: {}", line_no, e))?;
+        }
+
+        Ok(db)
+    }
+
+    /// Writes every vector to a CSV file that [`import_csv`](VecDB::import_csv)
+    /// reads back (without a header).
+    ///
+    /// Each row is an ID followed by its stored (normalized) components, in
+    /// insertion order. There is no header row, and configuration and metadata
+    /// are not exported.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - The file was written
+    /// * `Err(String)` - Error if the file can't be written, or an ID contains a
+    ///   comma or line break and could not be read back
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let mut db = VecDB::new();
+    /// db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    /// db.export_csv("vectors.csv").unwrap();
+    /// // vec1,0.6,0.8
+    /// ```
+    pub fn export_csv(&self, path: &str) -> Result<(), String> {
+        if let Some(id) = self.ids.iter().find(|id| id.contains([',', '\n', '\r'])) {
+            return Err(format!("ID '{}' can't be written to CSV", id));
+        }
+
+        let file = File::create(path)
+            .map_err(|e| format!("Fail to create file for saving '{}': {}", path, e))?;
+        let mut writer = BufWriter::new(file);
+        for (id, values) in self.iter() {
+            write!(writer, "{}", id).map_err(|e| format!("Fail to write '{}': {}", path, e))?;
+            for value in values.iter() {
+                write!(writer, ",{}", value)
+                    .map_err(|e| format!("Fail to write '{}': {}", path, e))?;
+            }
+            writeln!(writer).map_err(|e| format!("Fail to write '{}': {}", path, e))?;
+        }
+        writer
+            .flush()
+            .map_err(|e| format!("Fail to write '{}': {}", path, e))
+    }
+
+    /// Builds a new database from an `.fvecs` file, the format of ANN
+    /// benchmark datasets such as SIFT and GIST.
+    ///
+    /// Each vector is stored as its dimension (little-endian `i32`) followed by
+    /// that many little-endian `f32` components. Vectors get the IDs
+    /// `"{id_prefix}_0"`, `"{id_prefix}_1"`, ... in file order and are inserted
+    /// as with [`insert`](VecDB::insert).
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(VecDB)` - The imported database
+    /// * `Err(String)` - Error if the file can't be read, is truncated, or
+    ///   holds vectors of different dimensions
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kvdb::VecDB;
+    ///
+    /// let db = VecDB::from_fvecs("sift_base.fvecs", "sift").unwrap();
+    /// assert!(db.contains("sift_0"));
+    /// ```
+    pub fn from_fvecs(path: &str, id_prefix: &str) -> Result<Self, String> {
+        let mut bytes = Vec::new();
+        File::open(path)
+            .and_then(|file| BufReader::new(file).read_to_end(&mut bytes))
+            .map_err(|e| format!("Fail to read '{}': {}", path, e))?;
+
+        let mut db = VecDB::new();
+        let mut rest = bytes.as_slice();
+        while !rest.is_empty() {
+            let index = db.count();
+            let Some((dim_bytes, tail)) = rest.split_first_chunk::<4>() else {
+                return Err(format!("Truncated fvecs file at vector {}", index));
+            };
+            let dim = i32::from_le_bytes(*dim_bytes);
+            if dim <= 0 {
+                return Err(format!("Invalid dimension {} at vector {}", dim, index));
+            }
+            let len = dim as usize * 4;
+            if tail.len() < len {
+                return Err(format!("Truncated fvecs file at vector {}", index));
+            }
+
+            let vector = tail[..len]
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes(c.try_into().unwrap()))
+                .collect();
+            db.insert(format!("{}_{}", id_prefix, index), vector)
+                .map_err(|e| format!("Vector {}: {}", index, e))?;
+            rest = &tail[len..];
+        }
+
+        Ok(db)
+    }
+
+    /// Builds the header written before the bincode data.
+    fn header(&self) -> [u8; HEADER_LEN] {
+        let mut header = [0u8; HEADER_LEN];
+        header[..4].copy_from_slice(MAGIC);
+        header[4..8].copy_from_slice(&FORMAT_VERSION.to_le_bytes());
+        header[8..].copy_from_slice(&(self.dimension.unwrap_or(0) as u64).to_le_bytes());
+        header
+    }
+
+    /// Validates the header of (decompressed) bincode file contents and
+    /// deserializes the database that follows it.
+    fn from_bincode(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Self::from_legacy(bytes);
+        }
+        let Some(header) = bytes.get(..HEADER_LEN) else {
+            return Err("Truncated kvdb header".to_string());
+        };
+        let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(format!(
+                "Unsupported db version {}, this build supports {}",
+                version, FORMAT_VERSION
+            ));
+        }
+        let dimension = u64::from_le_bytes(header[8..].try_into().unwrap());
+
+        let db: VecDB = bincode_options()
+            .deserialize(&bytes[HEADER_LEN..])
+            .map_err(|e| format!("Deserialization failed: {}", e))?;
+        if db.dimension.unwrap_or(0) as u64 != dimension {
+            return Err(format!(
+                "Corrupt db file: header dimension {} doesn't match stored dimension {}",
+                dimension,
+                db.dimension.unwrap_or(0)
+            ));
+        }
+
+        Ok(db)
+    }
+
+    /// Reads a headerless file written by kvdb 0.3 (version 0), which held
+    /// only IDs and normalized vectors under the default configuration.
+    fn from_legacy(bytes: &[u8]) -> Result<Self, String> {
+        let not_kvdb = || "Not a kvdb file: missing KVDB header".to_string();
+        let legacy: LegacyDb = bincode_options()
+            .deserialize(bytes)
+            .map_err(|_| not_kvdb())?;
+        let expected = legacy.ids.len() * legacy.dimension.unwrap_or(0);
+        if legacy.vectors.len() != expected || legacy.ids.is_empty() != legacy.dimension.is_none() {
+            return Err(not_kvdb());
+        }
+
+        let mut db = VecDB::new();
+        db.ids = legacy.ids;
+        db.vectors = legacy.vectors;
+        db.dimension = legacy.dimension;
+        Ok(db)
+    }
+
+    /// Applies a single WAL record during replay.
+    ///
+    /// Replay must be idempotent, so deleting an ID that is already gone is not an error.
+    fn apply_wal_record(&mut self, record: WalRecord) -> Result<(), String> {
+        match record {
+            WalRecord::Insert { id, vector } => {
+                self.insert(id.clone(), vector)
+                    .map_err(|e| format!("WAL replay failed for '{}': {}", id, e))?;
+            }
+            WalRecord::Delete { id } => {
+                self.delete(&id).ok();
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> IntoIterator for &'a VecDB {
+    type Item = (&'a str, Cow<'a, [f32]>);
+    type IntoIter = Iter<'a>;
+
+    /// Same as [`VecDB::iter`].
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+/// Builds a database by [`insert`](VecDB::insert)ing each `(id, vector)` pair.
+///
+/// The first valid vector sets the dimension. Collecting can't fail, so
+/// entries that `insert` would reject (wrong dimension, empty or non-finite
+/// vector) are skipped silently; use
+/// [`insert_many_strict`](VecDB::insert_many_strict) to have them reported.
+/// A repeated ID keeps the last vector, as with `insert`.
+///
+/// # Examples
+///
+/// ```
+/// use kvdb::VecDB;
+///
+/// let db: VecDB = vec![
+///     ("vec1".to_string(), vec![1.0, 0.0]),
+///     ("vec2".to_string(), vec![0.0, 1.0]),
+///     ("bad".to_string(), vec![1.0, 0.0, 0.0]),
+/// ]
+/// .into_iter()
+/// .collect();
+/// assert_eq!(db.count(), 2);
+/// ```
+impl FromIterator<(String, Vec<f32>)> for VecDB {
+    fn from_iter<I: IntoIterator<Item = (String, Vec<f32>)>>(entries: I) -> Self {
+        let mut db = VecDB::new();
+        for (id, vector) in entries {
+            db.insert(id, vector).ok();
+        }
+        db
+    }
+}
+
+#[cfg(test)]
+mod db_test {
+    use super::*;
+
+    #[test]
+    fn test_insert_single_vector() {
+        let mut db = VecDB::new();
+        let result = db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]);
+
+        assert!(result.is_ok());
+        assert_eq!(db.ids.len(), 1);
+        assert_eq!(db.ids[0], "vec1");
+        assert_eq!(db.dimension, Some(3));
+        // Vectors should be normalized, so length should be 3 floats
+        assert_eq!(db.vectors.len(), 3);
+    }
+
+    #[test]
+    fn test_insert_multiple_vectors() {
+        let mut db = VecDB::new();
+
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+
+        assert_eq!(db.ids.len(), 3);
+        assert_eq!(db.vectors.len(), 9); // 3 vectors × 3 dimensions
+    }
+
+    #[test]
+    fn test_insert_dimension_mismatch() {
+        let mut db = VecDB::new();
+
+        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+        let result = db.insert("vec2".to_string(), vec![1.0, 2.0]); // Wrong dimension
+
+        assert!(result.is_err());
+        assert_eq!(db.ids.len(), 1); // Only first vector inserted
+    }
+
+    #[test]
+    fn test_insert_many_reports_per_entry() {
+        let mut db = VecDB::new();
+        let results = db.insert_many(vec![
+            ("vec1".to_string(), vec![1.0, 0.0, 0.0]),
+            ("bad".to_string(), vec![1.0, 0.0]),
+            ("vec2".to_string(), vec![0.0, 1.0, 0.0]),
+            ("vec1".to_string(), vec![0.0, 0.0, 1.0]),
+        ]);
+
+        assert_eq!(results.len(), 4);
+        assert!(results[0].is_ok());
+        assert_eq!(results[1].as_ref().unwrap_err(), "Different dimension");
+        assert!(results[2].is_ok());
+        assert!(results[3].as_ref().unwrap().contains("Updated"));
+
+        assert_eq!(db.count(), 2);
+        assert!(db.get("bad").is_none());
+        assert_eq!(db.get("vec1").unwrap(), vec![0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_insert_rejects_non_finite() {
+        for db in [VecDB::new(), VecDB::new().with_metric(Metric::Euclidean)] {
+            let mut db = db;
+            db.insert("ok".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+
+            let result = db.insert("nan".to_string(), vec![1.0, f32::NAN, 0.0]);
+            assert_eq!(
+                result.unwrap_err(),
+                "Vector contains non-finite value at index 1"
+            );
+            let result = db.insert("inf".to_string(), vec![0.0, 0.0, f32::INFINITY]);
+            assert_eq!(
+                result.unwrap_err(),
+                "Vector contains non-finite value at index 2"
+            );
+            assert_eq!(db.count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_rejected_first_insert_leaves_dimension_unset() {
+        let mut db = VecDB::new();
+        assert!(db.insert("nan".to_string(), vec![f32::NAN, 0.0]).is_err());
+        assert_eq!(db.dimension(), None);
+
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(db.dimension(), Some(3));
+    }
+
+    #[test]
+    fn test_get_vector() {
+        let mut db = VecDB::new();
+
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![5.0, 12.0]).unwrap();
+
+        // Get first vector (normalized [3,4] = [0.6, 0.8])
+        let v1 = db.get_vector(0);
         assert_eq!(v1.len(), 2);
         assert!((v1[0] - 0.6).abs() < 1e-5);
         assert!((v1[1] - 0.8).abs() < 1e-5);
 
-        // Get second vector (normalized [5,12] = [~0.38, ~0.92])
-        let v2 = db.get_vector(1);
-        assert_eq!(v2.len(), 2);
-        assert!((v2[0] - 0.384615).abs() < 1e-5);
-        assert!((v2[1] - 0.923077).abs() < 1e-5);
+        // Get second vector (normalized [5,12] = [~0.38, ~0.92])
+        let v2 = db.get_vector(1);
+        assert_eq!(v2.len(), 2);
+        assert!((v2[0] - 0.384615).abs() < 1e-5);
+        assert!((v2[1] - 0.923077).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_search_basic() {
+        let mut db = VecDB::new();
+
+        // Insert three vectors
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
+
+        // Search for vector close to vec1
+        let results = db.search(vec![1.0, 0.0, 0.0], 2).unwrap();
+
+        assert_eq!(results.len(), 2);
+        // First result should be vec1 (exact match, similarity = 1.0)
+        assert_eq!(results[0].id, "vec1");
+        assert!((results[0].score - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_search_returns_top_k() {
+        let mut db = VecDB::new();
+
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.5, 0.5]).unwrap();
+
+        // Request top 2 out of 3
+        let results = db.search(vec![1.0, 1.0], 2).unwrap();
+
+        assert_eq!(results.len(), 2);
+    }
+
+    #[test]
+    fn test_search_top_k_larger_than_db_is_sorted() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec2".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.7, 0.7]).unwrap();
+
+        let results = db.search(vec![1.0, 0.0], 5).unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].id, "vec2");
+        for pair in results.windows(2) {
+            assert!(pair[0].score >= pair[1].score);
+        }
+    }
+
+    #[test]
+    fn test_search_rejects_non_finite_query() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+
+        let result = db.search(vec![f32::NAN, 1.0], 1);
+        assert_eq!(
+            result.unwrap_err(),
+            "Vector contains non-finite value at index 0"
+        );
+        let result = db.search(vec![1.0, f32::NEG_INFINITY], 1);
+        assert_eq!(
+            result.unwrap_err(),
+            "Vector contains non-finite value at index 1"
+        );
+    }
+
+    #[test]
+    fn test_search_within_allow_list() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.9, 0.1, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec4".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+        db.insert("vec5".to_string(), vec![0.5, 0.5, 0.0]).unwrap();
+
+        let allowed: HashSet<String> = ["vec3", "vec4", "vec5", "unknown"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let results = db.search_within(vec![1.0, 0.0, 0.0], 5, &allowed).unwrap();
+
+        assert_eq!(results.len(), 3);
+        assert!(results.iter().all(|result| allowed.contains(&result.id)));
+        assert_eq!(results[0].id, "vec5");
+
+        let results = db.search_within(vec![1.0, 0.0, 0.0], 1, &allowed).unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = db
+            .search_within(vec![1.0, 0.0, 0.0], 5, &HashSet::new())
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_apply_idf_downweights_common_dimension() {
+        let mut db = VecDB::new();
+        // Dimension 0 appears in every document, dimension 2 in only one
+        db.insert("doc1".to_string(), vec![1.0, 0.0, 1.0]).unwrap();
+        db.insert("doc2".to_string(), vec![1.0, 1.0, 0.0]).unwrap();
+        db.insert("doc3".to_string(), vec![1.0, 1.0, 0.0]).unwrap();
+        db.insert("doc4".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+
+        let idf = db.apply_idf().unwrap();
+        assert_eq!(idf.len(), 3);
+        assert!(idf[0] < idf[1]);
+        assert!(idf[1] < idf[2]);
+        assert!((idf[0] - 1.0).abs() < 1e-6);
+
+        // doc1 had equal weight on both terms; the rare one now dominates
+        let doc1 = db.get("doc1").unwrap();
+        assert!(doc1[0] < doc1[2]);
+        let norm: f32 = doc1.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-6);
+        assert_eq!(db.count(), 4);
+
+        assert!(VecDB::new().apply_idf().is_err());
+    }
+
+    #[test]
+    fn test_search_excluding_promotes_next_best() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.9, 0.1, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.5, 0.5, 0.0]).unwrap();
+        db.insert("vec4".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+
+        let query = vec![1.0, 0.0, 0.0];
+        let full = db.search(query.clone(), 3).unwrap();
+        assert_eq!(full[0].id, "vec1");
+
+        let exclude = HashSet::from(["vec1".to_string()]);
+        let results = db.search_excluding(query.clone(), 3, &exclude).unwrap();
+
+        // Still top_k results, with the second best now first
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].id, "vec2");
+        assert_eq!(results[1].id, "vec3");
+        assert_eq!(results[2].id, "vec4");
+
+        let exclude: HashSet<String> = ["vec1", "vec2", "vec3", "vec4"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        assert!(db.search_excluding(query, 3, &exclude).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_ids_matches_search() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
+        db.insert("vec4".to_string(), vec![0.5, 0.1, 0.9]).unwrap();
+
+        let query = vec![1.0, 0.3, 0.1];
+        let ids = db.search_ids(query.clone(), 3).unwrap();
+        let full = db.search(query, 3).unwrap();
+
+        assert_eq!(ids.len(), 3);
+        for ((id, score), full) in ids.iter().zip(&full) {
+            assert_eq!(id, &full.id);
+            assert_eq!(score, &full.score);
+        }
+        assert!(db.search_ids(vec![1.0, 0.0], 3).is_err());
+        assert!(db.search_ids(vec![1.0, 0.0, 0.0], 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_search_with_l2() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
+
+        let results = db.search_with_l2(vec![1.0, 0.2, 0.0], 3).unwrap();
+        assert_eq!(results.len(), 3);
+        let plain = db.search(vec![1.0, 0.2, 0.0], 3).unwrap();
+        for ((result, l2_sq), plain) in results.iter().zip(&plain) {
+            assert_eq!(result, plain);
+            assert!((l2_sq - (2.0 - 2.0 * result.score)).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_search_with_l2_raw_metric() {
+        let mut db = VecDB::new().with_metric(Metric::DotProduct);
+        db.insert("vec1".to_string(), vec![2.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 3.0]).unwrap();
+
+        let results = db.search_with_l2(vec![1.0, 0.0], 2).unwrap();
+        assert_eq!(results[0].0.id, "vec1");
+        assert!((results[0].1 - 1.0).abs() < 1e-6);
+        assert!((results[1].1 - 10.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_search_with_exact_flag() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("dup".to_string(), vec![0.0, 3.0, 4.0]).unwrap();
+
+        // Same direction, different length
+        let (results, exact) = db
+            .search_with_exact_flag(vec![0.0, 0.6, 0.8], 2, 1e-5)
+            .unwrap();
+        assert_eq!(exact.as_deref(), Some("dup"));
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "dup");
+
+        let (results, exact) = db
+            .search_with_exact_flag(vec![1.0, 1.0, 0.0], 2, 1e-5)
+            .unwrap();
+        assert_eq!(exact, None);
+        assert_eq!(results.len(), 2);
+
+        // Still reported with top_k == 0
+        let (results, exact) = db
+            .search_with_exact_flag(vec![1.0, 0.0, 0.0], 0, 1e-5)
+            .unwrap();
+        assert!(results.is_empty());
+        assert_eq!(exact.as_deref(), Some("vec1"));
+    }
+
+    #[test]
+    fn test_search_top_k_zero() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let results = db.search(vec![1.0, 0.0], 0).unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    fn test_search_empty_database() {
+        let db = VecDB::new();
+
+        let result = db.search(vec![1.0, 2.0], 5);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_search_dimension_mismatch() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+
+        let result = db.search(vec![1.0, 2.0], 1); // Wrong dimension
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_metric_changes_top_result() {
+        let vectors = [
+            ("small", vec![1.0, 0.0]),
+            ("large", vec![10.0, 1.0]),
+            ("diag", vec![0.5, 0.5]),
+        ];
+        let mut cosine = VecDB::new();
+        let mut euclidean = VecDB::new().with_metric(Metric::Euclidean);
+        for (id, v) in &vectors {
+            cosine.insert(id.to_string(), v.clone()).unwrap();
+            euclidean.insert(id.to_string(), v.clone()).unwrap();
+        }
+
+        // Same direction as "small", but closest in space to "large"
+        let query = vec![10.0, 0.0];
+        let cos_results = cosine.search(query.clone(), 1).unwrap();
+        let euc_results = euclidean.search(query, 2).unwrap();
+
+        assert_eq!(cos_results[0].id, "small");
+        assert_eq!(euc_results[0].id, "large");
+        assert!((euc_results[0].score - 1.0).abs() < 1e-5);
+        // Distances come back in ascending order
+        assert!(euc_results[0].score <= euc_results[1].score);
+    }
+
+    #[test]
+    fn test_raw_metrics_store_vectors_as_given() {
+        let mut db = VecDB::new().with_metric(Metric::Manhattan);
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![1.0, 1.0]).unwrap();
+        assert_eq!(db.get("vec1").unwrap(), vec![3.0, 4.0]);
+
+        let results = db.search_recent(vec![0.0, 0.0], 2, 2).unwrap();
+        assert_eq!(results[0].id, "vec2");
+        assert!((results[0].score - 2.0).abs() < 1e-5);
+
+        let farthest = db.search_farthest(vec![0.0, 0.0], 1).unwrap();
+        assert_eq!(farthest[0].id, "vec1");
+        assert!((farthest[0].score - 7.0).abs() < 1e-5);
+
+        let mut dot = VecDB::new().with_metric(Metric::DotProduct);
+        dot.insert("short".to_string(), vec![1.0, 0.0]).unwrap();
+        dot.insert("long".to_string(), vec![5.0, 1.0]).unwrap();
+        assert_eq!(dot.search(vec![1.0, 0.0], 1).unwrap()[0].id, "long");
+    }
+
+    #[test]
+    fn test_normalization_off_stores_raw_vector() {
+        let mut db = VecDB::new().with_normalization(false);
+        db.insert("vec1".to_string(), vec![0.6, 0.8]).unwrap();
+        db.insert("vec2".to_string(), vec![1.0, 0.0]).unwrap();
+
+        assert_eq!(db.get("vec1").unwrap(), vec![0.6, 0.8]);
+
+        // The query is not normalized either, so scores are raw dot products
+        let results = db.search(vec![0.0, 2.0], 1).unwrap();
+        assert_eq!(results[0].id, "vec1");
+        assert!((results[0].score - 1.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_search_multi_matches_combined_db() {
+        let vectors = [
+            ("vec1", vec![1.0, 0.0, 0.0]),
+            ("vec2", vec![0.0, 1.0, 0.0]),
+            ("vec3", vec![0.7, 0.7, 0.0]),
+            ("vec4", vec![0.9, 0.1, 0.2]),
+            ("vec5", vec![0.0, 0.2, 1.0]),
+        ];
+        let mut combined = VecDB::new();
+        let mut shard1 = VecDB::new();
+        let mut shard2 = VecDB::new();
+        for (i, (id, v)) in vectors.iter().enumerate() {
+            combined.insert(id.to_string(), v.clone()).unwrap();
+            let shard = if i % 2 == 0 { &mut shard1 } else { &mut shard2 };
+            shard.insert(id.to_string(), v.clone()).unwrap();
+        }
+
+        let query = [1.0, 0.3, 0.0];
+        let merged = search_multi(&[&shard1, &shard2], &query, 3).unwrap();
+        let expected = combined.search(query.to_vec(), 3).unwrap();
+
+        let merged_ids: Vec<&str> = merged.iter().map(|r| r.id.as_str()).collect();
+        let expected_ids: Vec<&str> = expected.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(merged_ids, expected_ids);
+    }
+
+    #[test]
+    fn test_search_multi_rejects_mismatched_dbs() {
+        let mut db2 = VecDB::new();
+        db2.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        let mut db3 = VecDB::new();
+        db3.insert("vec2".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        let mut euclidean = VecDB::new().with_metric(Metric::Euclidean);
+        euclidean
+            .insert("vec3".to_string(), vec![1.0, 0.0])
+            .unwrap();
+
+        assert!(search_multi(&[&db2, &db3], &[1.0, 0.0], 1).is_err());
+        assert!(search_multi(&[&db2, &euclidean], &[1.0, 0.0], 1).is_err());
+        assert!(search_multi(&[&VecDB::new()], &[1.0, 0.0], 1).is_err());
+    }
+
+    #[test]
+    fn test_search_threshold_returns_all_above() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.95, 0.1, 0.0]).unwrap();
+
+        let results = db.search_threshold(vec![1.0, 0.05, 0.0], 0.9).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "vec1");
+        assert_eq!(results[1].id, "vec3");
+        assert!(results[0].score >= results[1].score);
+
+        // Inclusive: an exact match clears a threshold of 1.0
+        let results = db.search_threshold(vec![0.0, 1.0, 0.0], 1.0).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "vec2");
+
+        // Nothing clears the threshold: empty, not an error
+        let results = db.search_threshold(vec![0.0, 0.0, 1.0], 0.5).unwrap();
+        assert!(results.is_empty());
+
+        assert!(db.search_threshold(vec![1.0, 0.0], 0.5).is_err());
+    }
+
+    #[test]
+    fn test_search_expand_returns_distinct_neighbors() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.9, 0.1, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec4".to_string(), vec![0.0, 0.9, 0.1]).unwrap();
+        db.insert("vec5".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+
+        let expanded = db.search_expand(vec![1.0, 0.0, 0.0], 2, 3).unwrap();
+        assert_eq!(expanded.len(), 2);
+        assert_eq!(expanded[0].0.id, "vec1");
+        assert_eq!(expanded[1].0.id, "vec2");
+
+        for (top, neighbors) in &expanded {
+            assert_eq!(neighbors.len(), 3);
+            // Never the match itself, and no duplicates
+            assert!(neighbors.iter().all(|n| n.id != top.id));
+            let mut ids: Vec<&str> = neighbors.iter().map(|n| n.id.as_str()).collect();
+            ids.sort();
+            ids.dedup();
+            assert_eq!(ids.len(), 3);
+        }
+        assert_eq!(expanded[0].1[0].id, "vec2");
+        assert_eq!(expanded[1].1[0].id, "vec1");
+    }
+
+    #[test]
+    fn test_search_many_reports_failing_query() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+
+        let results = db.search_many(
+            &[vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0], vec![1.0, 0.0]],
+            1,
+        );
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap()[0].id, "vec1");
+        assert_eq!(results[1].as_ref().unwrap()[0].id, "vec2");
+        assert_eq!(
+            results[2].as_ref().unwrap_err(),
+            "Wrong query dimension: expected 3, got 2"
+        );
+    }
+
+    #[test]
+    fn test_search_heap_matches_brute_force() {
+        // Fixed-seed pseudo-random vectors, as in examples/gen_demo.rs
+        let mut state: u64 = 42;
+        let mut next = move || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((state >> 33) as f32) / (u32::MAX as f32) * 2.0 - 1.0
+        };
+
+        let dim = 16;
+        let mut db = VecDB::new();
+        for i in 0..500 {
+            let vector: Vec<f32> = (0..dim).map(|_| next()).collect();
+            db.insert(format!("vec{}", i), vector).unwrap();
+        }
+        let query: Vec<f32> = (0..dim).map(|_| next()).collect();
+
+        // Brute force: score everything, sort descending, take top k
+        let norm_q = l2_norm(&query).unwrap();
+        let mut expected: Vec<(String, f32)> = db
+            .list()
+            .into_iter()
+            .map(|(id, v)| {
+                let score = v.iter().zip(&norm_q).map(|(a, b)| a * b).sum();
+                (id, score)
+            })
+            .collect();
+        expected.sort_by(|a, b| b.1.total_cmp(&a.1));
+        expected.truncate(10);
+
+        let results = db.search(query, 10).unwrap();
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        let expected_ids: Vec<&str> = expected.iter().map(|r| r.0.as_str()).collect();
+        assert_eq!(ids, expected_ids);
+        for (result, (_, score)) in results.iter().zip(&expected) {
+            assert!((result.score - score).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_search_instrumented_counts_comparisons() {
+        let mut db = VecDB::new();
+        for i in 0..10 {
+            db.insert(format!("vec{}", i), vec![i as f32 + 1.0, 1.0, 0.5])
+                .unwrap();
+        }
+
+        let (results, metrics) = db.search_instrumented(vec![1.0, 0.0, 0.0], 3).unwrap();
+        assert_eq!(results.len(), 3);
+        // Brute-force search scores every stored vector exactly once
+        assert_eq!(metrics.comparisons, db.count());
+        assert!(metrics.heap_updates >= 3);
+        assert!(metrics.heap_updates <= db.count());
+    }
+
+    #[test]
+    fn test_search_recent_only_scores_window() {
+        let mut db = VecDB::new();
+        for i in 0..10 {
+            db.insert(format!("vec{}", i), vec![1.0, i as f32]).unwrap();
+        }
+
+        // vec0 is the best match overall, but falls outside the window
+        let results = db.search_recent(vec![1.0, 0.0], 10, 3).unwrap();
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["vec7", "vec8", "vec9"]);
+
+        let results = db.search_recent(vec![1.0, 0.0], 1, 3).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].id, "vec7");
+    }
+
+    #[test]
+    fn test_search_recent_window_larger_than_db() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let results = db.search_recent(vec![1.0, 0.0], 5, 100).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "vec1");
+    }
+
+    #[test]
+    fn test_search_farthest_returns_lowest_scores() {
+        let mut db = VecDB::new();
+        db.insert("same".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("opposite".to_string(), vec![-1.0, 0.0]).unwrap();
+        db.insert("orthogonal".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("close".to_string(), vec![0.9, 0.1]).unwrap();
+        db.insert("mostly_opposite".to_string(), vec![-0.9, 0.2])
+            .unwrap();
+
+        let mut metrics = SearchMetrics::default();
+        let results = db
+            .search_farthest_with_metrics(vec![1.0, 0.0], 2, &mut metrics)
+            .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "opposite");
+        assert_eq!(results[1].id, "mostly_opposite");
+        assert!(results[0].score <= results[1].score);
+
+        // Single pass over the db, only the bounded heap gets updated
+        assert_eq!(metrics.comparisons, db.count());
+        assert!(metrics.heap_updates <= db.count());
+    }
+
+    #[test]
+    fn test_search_farthest_edge_cases() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        assert!(db.search_farthest(vec![1.0, 0.0], 0).unwrap().is_empty());
+
+        let results = db.search_farthest(vec![1.0, 0.0], 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "vec2");
+    }
+
+    #[test]
+    fn test_score_pairs() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let scores = db
+            .score_pairs(&[
+                (vec![1.0, 1.0], "vec1".to_string()),
+                (vec![0.0, 2.0], "vec2".to_string()),
+                (vec![1.0, 0.0], "missing".to_string()),
+            ])
+            .unwrap();
+
+        assert_eq!(scores.len(), 3);
+        assert!((scores[0].unwrap() - 0.70710677).abs() < 1e-5);
+        assert!((scores[1].unwrap() - 1.0).abs() < 1e-5);
+        assert!(scores[2].is_none());
+
+        let result = db.score_pairs(&[(vec![1.0, 0.0, 0.0], "vec1".to_string())]);
+        assert_eq!(
+            result.unwrap_err(),
+            "Wrong query dimension: expected 2, got 3"
+        );
+    }
+
+    #[test]
+    fn test_nearest_early_stop_stops_at_first_match() {
+        let mut db = VecDB::new();
+        db.insert("exact".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+        db.insert("vec4".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
+        db.insert("vec5".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+
+        let mut metrics = SearchMetrics::default();
+        let result = db
+            .nearest_early_stop_with_metrics(vec![1.0, 0.0, 0.0], 0.99, &mut metrics)
+            .unwrap();
+
+        let result = result.unwrap();
+        assert_eq!(result.id, "exact");
+        assert!((result.score - 1.0).abs() < 1e-5);
+        // Perfect match is stored first, so nothing after it is scored
+        assert_eq!(metrics.comparisons, 1);
+    }
+
+    #[test]
+    fn test_nearest_early_stop_no_match_scans_all() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let mut metrics = SearchMetrics::default();
+        let result = db
+            .nearest_early_stop_with_metrics(vec![1.0, 1.0], 0.99, &mut metrics)
+            .unwrap();
+
+        assert!(result.is_none());
+        assert_eq!(metrics.comparisons, 2);
+    }
+
+    #[test]
+    fn test_nearest_early_stop_empty_database() {
+        let db = VecDB::new();
+
+        assert!(db.nearest_early_stop(vec![1.0, 0.0], 0.5).is_err());
+    }
+
+    #[test]
+    fn test_insert_many_strict_flags_outlier() {
+        let mut db = VecDB::new();
+        let entries = vec![
+            ("vec1".to_string(), vec![1.0, 0.0, 0.0]),
+            ("vec2".to_string(), vec![0.0, 1.0, 0.0]),
+            ("odd".to_string(), vec![0.0, 0.0, 1.0, 0.0]),
+            ("vec3".to_string(), vec![0.0, 0.0, 1.0]),
+            ("vec4".to_string(), vec![1.0, 1.0, 0.0]),
+        ];
+
+        let err = db.insert_many_strict(entries).unwrap_err();
+        assert!(err.contains("expected 3"));
+        assert!(err.contains("4 of 5"));
+        assert!(err.contains("odd (4)"));
+        assert!(!err.contains("vec1"));
+        assert_eq!(db.count(), 0);
+        assert_eq!(db.dimension(), None);
+    }
+
+    #[test]
+    fn test_insert_many_strict_uses_db_dimension() {
+        let mut db = VecDB::new();
+        db.insert("vec0".to_string(), vec![1.0, 0.0]).unwrap();
+
+        // The batch agrees with itself but not with the database
+        let entries = vec![
+            ("vec1".to_string(), vec![1.0, 0.0, 0.0]),
+            ("vec2".to_string(), vec![0.0, 1.0, 0.0]),
+        ];
+        assert!(
+            db.insert_many_strict(entries)
+                .unwrap_err()
+                .contains("0 of 2")
+        );
+
+        let entries = vec![
+            ("vec1".to_string(), vec![1.0, 1.0]),
+            ("vec2".to_string(), vec![0.0, 1.0]),
+        ];
+        let results = db.insert_many_strict(entries).unwrap();
+        assert!(results.iter().all(|r| r.is_ok()));
+        assert_eq!(db.count(), 3);
+    }
+
+    #[test]
+    fn test_insert_normalized_mixed_with_insert() {
+        let mut db = VecDB::new();
+        let unit = vec![0.0, 0.6, 0.8];
+        db.insert_normalized("unit".to_string(), unit.clone())
+            .unwrap();
+        db.insert("raw".to_string(), vec![2.0, 0.0, 0.0]).unwrap();
+
+        for id in ["unit", "raw"] {
+            let v = db.get(id).unwrap();
+            let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+            assert!((norm - 1.0).abs() < 1e-6);
+        }
+        // Stored exactly as given
+        assert_eq!(db.get("unit").unwrap(), unit);
+        assert!(
+            db.insert_normalized("bad".to_string(), vec![1.0, 0.0])
+                .is_err()
+        );
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "unit-length")]
+    fn test_insert_normalized_rejects_raw_vector_in_debug() {
+        let mut db = VecDB::new();
+        db.insert_normalized("vec1".to_string(), vec![3.0, 4.0])
+            .ok();
+    }
+
+    #[test]
+    fn test_cosine_scores_are_clamped() {
+        let mut db = VecDB::new();
+        // Slightly longer than unit length, so its self-match overshoots 1.0
+        let near = vec![0.6006, 0.8];
+        db.insert_normalized("near".to_string(), near.clone())
+            .unwrap();
+        db.insert("other".to_string(), vec![1.0, 0.0]).unwrap();
+        let query = l2_norm(&near).unwrap();
+        assert!(crate::vector::dot_product(&near, &query).unwrap() > 1.0);
+
+        let results = db.search(near.clone(), 2).unwrap();
+        assert_eq!(results[0].id, "near");
+        assert_eq!(results[0].score, 1.0);
+        assert!(results[1].score < 1.0);
+
+        let threshold = db.search_threshold(near.clone(), 0.99).unwrap();
+        assert_eq!(threshold[0].score, 1.0);
+        let scores = db.score_pairs(&[(near, "near".to_string())]).unwrap();
+        assert_eq!(scores, vec![Some(1.0)]);
+    }
+
+    #[test]
+    fn test_search_result_fields() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let results = db.search(vec![3.0, 4.0], 2).unwrap();
+        let top = &results[0];
+        assert_eq!(top.id, "vec1");
+        assert!((top.vector[0] - 0.6).abs() < 1e-6);
+        assert!((top.vector[1] - 0.8).abs() < 1e-6);
+        assert!((top.score - 1.0).abs() < 1e-6);
+        assert_eq!(results[1].id, "vec2");
+        assert!((results[1].score - 0.8).abs() < 1e-6);
+
+        // Same matches as the other search methods; tuples still convert
+        assert_eq!(db.search_exact(vec![3.0, 4.0], 2).unwrap(), results);
+        let tuple = ("vec1".to_string(), top.vector.clone(), top.score);
+        assert_eq!(&SearchResult::from(tuple), top);
+    }
+
+    #[test]
+    fn test_capacity_evicts_oldest() {
+        let mut db = VecDB::new().with_capacity(3);
+        for i in 0..3 {
+            let message = db.insert(format!("vec{}", i), vec![1.0, i as f32]).unwrap();
+            assert!(!message.contains("evicted"));
+        }
+
+        // Updating an existing ID neither evicts nor refreshes its age
+        db.insert("vec0".to_string(), vec![0.0, 1.0]).unwrap();
+        assert_eq!(db.count(), 3);
+
+        for i in 3..10 {
+            let message = db.insert(format!("vec{}", i), vec![1.0, i as f32]).unwrap();
+            assert!(message.ends_with(&format!("evicted oldest id: vec{}", i - 3)));
+            assert_eq!(db.count(), 3);
+        }
+        assert_eq!(db.ids, vec!["vec7", "vec8", "vec9"]);
+        assert_eq!(db.search(vec![1.0, 9.0], 1).unwrap()[0].id, "vec9");
+    }
+
+    #[test]
+    fn test_capacity_one() {
+        let mut db = VecDB::new().with_capacity(1);
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        assert_eq!(db.count(), 1);
+        assert_eq!(db.dimension(), Some(2));
+        assert_eq!(db.get("vec2").unwrap(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_capacity_with_id_normalizer() {
+        let mut db = VecDB::new()
+            .with_id_normalizer(|id| format!("ns:{}", id))
+            .with_capacity(2);
+        db.insert("a".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("b".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let message = db.insert("c".to_string(), vec![1.0, 1.0]).unwrap();
+        assert!(message.ends_with("evicted oldest id: ns:a"));
+        let message = db.insert("d".to_string(), vec![1.0, 2.0]).unwrap();
+        assert!(message.ends_with("evicted oldest id: ns:b"));
+
+        assert_eq!(db.count(), 2);
+        assert_eq!(db.ids(), ["ns:c".to_string(), "ns:d".to_string()]);
+        assert!(db.get("a").is_none());
+        assert!(db.get("c").is_some());
+        assert!(db.verify().is_ok());
+    }
+
+    // ========== ID Hash Tests ==========
+
+    #[test]
+    fn test_id_hash_is_stable() {
+        // Fixed values: the hash must not change between runs or releases
+        assert_eq!(id_hash(""), 0xcbf29ce484222325);
+        assert_eq!(id_hash("vec1"), 7425188943681526492);
+        assert_ne!(id_hash("vec1"), id_hash("vec2"));
+    }
+
+    // ========== Get Tests ==========
+
+    #[test]
+    fn test_get_existing_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+
+        let result = db.get("vec1");
+        assert!(result.is_some());
+
+        let vec = result.unwrap();
+        assert_eq!(vec.len(), 2);
+        // Should be normalized [3,4] -> [0.6, 0.8]
+        assert!((vec[0] - 0.6).abs() < 1e-5);
+        assert!((vec[1] - 0.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_get_nonexistent_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+
+        let result = db.get("vec2");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_get_from_empty_database() {
+        let db = VecDB::new();
+
+        let result = db.get("vec1");
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_id_policy_trim_lowercase() {
+        let mut db = VecDB::new().with_id_policy(IdPolicy {
+            trim: true,
+            lowercase: true,
+        });
+        db.insert("VecA ".to_string(), vec![1.0, 0.0]).unwrap();
+
+        assert!(db.get("veca").is_some());
+        assert!(db.get("  VECA").is_some());
+        assert_eq!(db.ids[0], "veca");
+
+        // Same ID after normalization updates instead of duplicating
+        let result = db.insert("vecA".to_string(), vec![0.0, 1.0]).unwrap();
+        assert!(result.contains("Updated"));
+        assert_eq!(db.count(), 1);
+
+        db.delete(" VecA").unwrap();
+        assert_eq!(db.count(), 0);
+    }
+
+    #[test]
+    fn test_id_policy_default_is_exact() {
+        let mut db = VecDB::new();
+        db.insert("VecA ".to_string(), vec![1.0, 0.0]).unwrap();
+
+        assert!(db.get("veca").is_none());
+        assert!(db.get("VecA ").is_some());
+    }
+
+    #[test]
+    fn test_id_normalizer_custom() {
+        let mut db = VecDB::new()
+            .with_id_policy(IdPolicy {
+                trim: true,
+                lowercase: false,
+            })
+            .with_id_normalizer(|id| id.trim_start_matches("doc:").to_string());
+        db.insert(" doc:42".to_string(), vec![1.0, 0.0]).unwrap();
+
+        assert_eq!(db.ids[0], "42");
+        assert!(db.get("42").is_some());
+        assert!(db.get("doc:42 ").is_some());
+    }
+
+    #[test]
+    fn test_get_many_keeps_request_order() {
+        let mut db = VecDB::new();
+        db.insert("a".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("b".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let ids: Vec<String> = ["b", "x", "a", "b", "y"]
+            .iter()
+            .map(|id| id.to_string())
+            .collect();
+        let vectors = db.get_many(&ids);
+        assert_eq!(
+            vectors,
+            vec![
+                Some(vec![0.0, 1.0]),
+                None,
+                Some(vec![1.0, 0.0]),
+                Some(vec![0.0, 1.0]),
+                None,
+            ]
+        );
+        assert!(db.get_many(&[]).is_empty());
+        assert_eq!(VecDB::new().get_many(&ids[..1]), vec![None]);
+    }
+
+    #[test]
+    fn test_iter() {
+        let mut db = VecDB::new().with_metric(Metric::DotProduct);
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+        db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec3".to_string(), vec![5.0, 6.0]).unwrap();
+        db.delete("vec2").unwrap();
+
+        let mut count = 0;
+        let mut sum = 0.0;
+        for (_, vector) in db.iter() {
+            count += 1;
+            sum += vector.iter().sum::<f32>();
+        }
+        assert_eq!(count, db.count());
+        assert_eq!(sum, 14.0);
+
+        let ids: Vec<&str> = db.iter().map(|(id, _)| id).collect();
+        assert_eq!(ids, vec!["vec1", "vec3"]);
+        assert!(
+            db.iter()
+                .all(|(_, vector)| matches!(vector, Cow::Borrowed(_)))
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let mut db = VecDB::new();
+        assert!(!db.contains("vec1"));
+
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        assert!(db.contains("vec1"));
+        assert!(!db.contains("vec2"));
+
+        db.delete("vec1").unwrap();
+        assert!(!db.contains("vec1"));
+    }
+
+    #[test]
+    fn test_ids_in_insertion_order() {
+        let mut db = VecDB::new();
+        assert!(db.ids().is_empty());
+        for id in ["c", "a", "b"] {
+            db.insert(id.to_string(), vec![1.0, 0.0]).unwrap();
+        }
+        // Updating keeps the original position
+        db.insert("a".to_string(), vec![0.0, 1.0]).unwrap();
+        assert_eq!(db.ids(), ["c", "a", "b"]);
+
+        db.delete("c").unwrap();
+        db.insert("d".to_string(), vec![1.0, 1.0]).unwrap();
+        assert_eq!(db.ids(), ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn test_collect_and_iterate_round_trip() {
+        let entries = vec![
+            ("vec1".to_string(), vec![3.0, 4.0]),
+            ("vec2".to_string(), vec![0.0, 2.0]),
+            ("empty".to_string(), vec![]),
+            ("vec3".to_string(), vec![1.0, 0.0]),
+            ("wrong".to_string(), vec![1.0, 0.0, 0.0]),
+        ];
+        let db: VecDB = entries.into_iter().collect();
+        assert_eq!(db.ids(), ["vec1", "vec2", "vec3"]);
+        assert_eq!(db.iter().len(), 3);
+
+        let mut seen = Vec::new();
+        for (id, vector) in &db {
+            seen.push((id.to_string(), vector.into_owned()));
+        }
+        assert_eq!(seen, db.list());
+
+        // Vectors are already normalized, so collecting them again is lossless
+        let copy: VecDB = db
+            .iter()
+            .map(|(id, vector)| (id.to_string(), vector.into_owned()))
+            .collect();
+        let mut original = db.list();
+        let mut copied = copy.list();
+        original.sort_by(|a, b| a.0.cmp(&b.0));
+        copied.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(copied, original);
+    }
+
+    // ========== Update Tests ==========
+
+    #[test]
+    fn test_update_existing_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let result = db.update("vec1", vec![0.0, 3.0]);
+        assert!(result.unwrap().contains("Updated"));
+        assert_eq!(db.count(), 2);
+        assert_eq!(db.get("vec1").unwrap(), vec![0.0, 1.0]);
+        assert_eq!(db.get("vec2").unwrap(), vec![0.0, 1.0]);
+
+        // Dimension is still enforced
+        assert!(db.update("vec1", vec![1.0, 0.0, 0.0]).is_err());
+    }
+
+    #[test]
+    fn test_update_missing_id() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+
+        assert_eq!(
+            db.update("vec9", vec![1.0, 0.0]).unwrap_err(),
+            "ID not found"
+        );
+        assert_eq!(db.count(), 1);
+        assert!(db.get("vec9").is_none());
+    }
+
+    #[test]
+    fn test_rename() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        let vectors = db.vectors.clone();
+
+        db.rename("vec1", "canonical".to_string()).unwrap();
+
+        assert_eq!(db.vectors, vectors);
+        assert_eq!(db.count(), 2);
+        assert!(db.get("vec1").is_none());
+        assert_eq!(db.get("canonical").unwrap(), vec![1.0, 0.0]);
+        assert_eq!(db.list()[0].0, "canonical");
+
+        // The old ID is free again
+        db.insert("vec1".to_string(), vec![1.0, 1.0]).unwrap();
+        assert_eq!(db.count(), 3);
+    }
+
+    #[test]
+    fn test_rename_missing_id() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+
+        assert_eq!(
+            db.rename("vec9", "new".to_string()).unwrap_err(),
+            "ID not found"
+        );
+        assert!(db.get("new").is_none());
+    }
+
+    #[test]
+    fn test_rename_to_existing_id() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        let result = db.rename("vec1", "vec2".to_string());
+        assert!(result.unwrap_err().contains("already exists"));
+        assert_eq!(db.get("vec1").unwrap(), vec![1.0, 0.0]);
+        assert_eq!(db.get("vec2").unwrap(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        let snapshot = db.snapshot_memory();
+
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec1".to_string(), vec![1.0, 1.0]).unwrap();
+        db.restore(snapshot);
+
+        assert_eq!(db.count(), 1);
+        assert!(!db.contains("vec2"));
+        assert_eq!(db.get("vec1").unwrap(), vec![1.0, 0.0]);
+
+        // The restored index is usable for further changes
+        db.delete("vec1").unwrap();
+        assert_eq!(db.count(), 0);
+    }
+
+    // ========== Metadata Tests ==========
+
+    #[test]
+    fn test_metadata_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut db = VecDB::new();
+        let meta = Metadata::from([
+            ("source".to_string(), serde_json::json!("wiki")),
+            ("tags".to_string(), serde_json::json!(["a", {"n": 1.5}])),
+        ]);
+        db.insert_with_meta("vec1".to_string(), vec![1.0, 0.0], meta.clone())
+            .unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        for format in [Format::Bincode, Format::BincodeGz, Format::Json] {
+            let path = dir.path().join(format!("{:?}.db", format));
+            let path_str = path.to_str().unwrap();
+            db.save_as(path_str, format).unwrap();
+
+            let loaded = VecDB::load(path_str).unwrap();
+            assert_eq!(loaded.get_meta("vec1"), Some(&meta));
+            assert_eq!(loaded.get_meta("vec2"), None);
+            assert_eq!(loaded.get("vec1").unwrap(), vec![1.0, 0.0]);
+        }
+    }
+
+    #[test]
+    fn test_set_meta_keeps_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        let vectors = db.vectors.clone();
+
+        let meta = Metadata::from([("lang".to_string(), serde_json::json!("en"))]);
+        db.set_meta("vec1", meta.clone()).unwrap();
+        assert_eq!(db.get_meta("vec1"), Some(&meta));
+        assert_eq!(db.vectors, vectors);
+
+        // Updating the vector keeps the payload
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        assert_eq!(db.get_meta("vec1"), Some(&meta));
+
+        assert_eq!(
+            db.set_meta("vec9", Metadata::new()).unwrap_err(),
+            "ID not found"
+        );
+        assert!(db.get_meta("vec9").is_none());
+    }
+
+    #[test]
+    fn test_metadata_follows_id() {
+        let mut db = VecDB::new();
+        let meta = Metadata::from([("lang".to_string(), serde_json::json!("en"))]);
+        db.insert_with_meta("vec1".to_string(), vec![1.0, 0.0], meta.clone())
+            .unwrap();
+
+        db.rename("vec1", "doc".to_string()).unwrap();
+        assert!(db.get_meta("vec1").is_none());
+        assert_eq!(db.get_meta("doc"), Some(&meta));
+
+        // A deleted ID doesn't hand its payload to a later insert
+        db.delete("doc").unwrap();
+        db.insert("doc".to_string(), vec![0.0, 1.0]).unwrap();
+        assert!(db.get_meta("doc").is_none());
+    }
+
+    // ========== Delete Tests ==========
+
+    #[test]
+    fn test_delete_fast_keeps_remaining_vectors() {
+        for quantization in [Quantization::None, Quantization::Int8] {
+            let mut db = VecDB::new().with_quantization(quantization);
+            for i in 0..5 {
+                db.insert(format!("vec{}", i), vec![1.0, i as f32, 0.5])
+                    .unwrap();
+            }
+            let expected: Vec<(String, Vec<f32>)> = db
+                .list()
+                .into_iter()
+                .filter(|(id, _)| id != "vec1")
+                .collect();
+
+            db.delete_fast("vec1").unwrap();
+            assert_eq!(db.count(), 4);
+            assert!(db.get("vec1").is_none());
+            assert!(db.verify().is_ok());
+
+            let mut remaining = db.list();
+            remaining.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(remaining, expected);
+            for (id, vector) in &expected {
+                assert_eq!(&db.get(id).unwrap(), vector);
+            }
+
+            assert_eq!(db.delete_fast("vec1").err().unwrap(), "ID not found");
+            db.delete_fast("vec4").unwrap();
+            assert_eq!(db.count(), 3);
+            assert!(db.verify().is_ok());
+        }
+
+        let mut db = VecDB::new();
+        db.insert("only".to_string(), vec![1.0, 2.0]).unwrap();
+        db.delete_fast("only").unwrap();
+        assert_eq!(db.dimension(), None);
+        db.insert("other".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+    }
+
+    #[test]
+    fn test_delete_many_reports_each_id() {
+        for quantization in [Quantization::None, Quantization::Int8, Quantization::Binary] {
+            let mut db = VecDB::new().with_quantization(quantization);
+            db.insert("a".to_string(), vec![1.0, 0.0]).unwrap();
+            db.insert("b".to_string(), vec![0.0, 1.0]).unwrap();
+            db.insert("c".to_string(), vec![1.0, 1.0]).unwrap();
+            let c = db.get("c").unwrap();
+
+            let ids: Vec<String> = ["a", "missing", "a"]
+                .iter()
+                .map(|id| id.to_string())
+                .collect();
+            let results = db.delete_many(&ids);
+            assert_eq!(
+                results,
+                vec![
+                    Ok("Success Delete".to_string()),
+                    Err("ID not found".to_string()),
+                    Err("ID not found".to_string()),
+                ]
+            );
+            assert_eq!(db.count(), 2);
+            assert_eq!(db.ids(), ["b", "c"]);
+            assert_eq!(db.get("c").unwrap(), c);
+            assert!(db.verify().is_ok());
+        }
+
+        let mut db = VecDB::new();
+        db.insert("a".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("b".to_string(), vec![0.0, 1.0]).unwrap();
+        let results = db.delete_many(&["b".to_string(), "a".to_string()]);
+        assert!(results.iter().all(|result| result.is_ok()));
+        assert_eq!(db.dimension(), None);
+        assert_eq!(
+            db.delete_many(&["a".to_string()]),
+            vec![Err("Cannot delete on empty database".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_delete_existing_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+        db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+
+        let result = db.delete("vec1");
+        assert!(result.is_ok());
+
+        // Verify vec1 is gone
+        assert!(db.get("vec1").is_none());
+
+        // Verify vec2 is still there
+        assert!(db.get("vec2").is_some());
+
+        // Verify database size
+        assert_eq!(db.ids.len(), 1);
+        assert_eq!(db.vectors.len(), 2); // 1 vector × 2 dimensions
+    }
+
+    #[test]
+    fn test_delete_nonexistent_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+
+        let result = db.delete("vec2");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), "ID not found");
+
+        // Original data should be intact
+        assert_eq!(db.ids.len(), 1);
+    }
+
+    #[test]
+    fn test_delete_from_empty_database() {
+        let mut db = VecDB::new();
+
+        let result = db.delete("vec1");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err(), "Cannot delete on empty database");
+    }
+
+    #[test]
+    fn test_delete_first_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+
+        db.delete("vec1").unwrap();
+
+        // Verify vec1 is gone
+        assert!(db.get("vec1").is_none());
+
+        // Verify vec2 and vec3 are still accessible
+        let v2 = db.get("vec2").unwrap();
+        assert!((v2[1] - 1.0).abs() < 1e-5);
+
+        let v3 = db.get("vec3").unwrap();
+        assert!((v3[2] - 1.0).abs() < 1e-5);
+
+        // Verify counts
+        assert_eq!(db.ids.len(), 2);
+        assert_eq!(db.vectors.len(), 6); // 2 vectors × 3 dimensions
+    }
+
+    #[test]
+    fn test_delete_middle_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec3".to_string(), vec![1.0, 1.0]).unwrap();
+
+        db.delete("vec2").unwrap();
+
+        // Verify vec2 is gone
+        assert!(db.get("vec2").is_none());
+
+        // Verify vec1 and vec3 are still correct
+        assert!(db.get("vec1").is_some());
+        assert!(db.get("vec3").is_some());
+
+        assert_eq!(db.ids.len(), 2);
+        assert_eq!(db.vectors.len(), 4); // 2 vectors × 2 dimensions
+    }
+
+    #[test]
+    fn test_delete_last_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+        db.insert("vec2".to_string(), vec![4.0, 5.0, 6.0]).unwrap();
+        db.insert("vec3".to_string(), vec![7.0, 8.0, 9.0]).unwrap();
+
+        db.delete("vec3").unwrap();
+
+        // Verify vec3 is gone
+        assert!(db.get("vec3").is_none());
+
+        // Verify vec1 and vec2 are still there
+        assert!(db.get("vec1").is_some());
+        assert!(db.get("vec2").is_some());
+
+        assert_eq!(db.ids.len(), 2);
+        assert_eq!(db.vectors.len(), 6); // 2 vectors × 3 dimensions
+    }
+
+    #[test]
+    fn test_delete_all_vectors_sequentially() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+        db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+
+        db.delete("vec1").unwrap();
+        assert_eq!(db.ids.len(), 1);
+
+        db.delete("vec2").unwrap();
+        assert_eq!(db.ids.len(), 0);
+        assert_eq!(db.vectors.len(), 0);
+    }
+
+    #[test]
+    fn test_delete_last_vector_resets_dimension() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+
+        db.delete("vec1").unwrap();
+        assert_eq!(db.dimension, None);
+
+        db.insert("vec2".to_string(), vec![1.0, 2.0, 3.0, 4.0, 5.0])
+            .unwrap();
+        assert_eq!(db.dimension, Some(5));
+        assert!(db.get("vec2").is_some());
+    }
+
+    #[test]
+    fn test_bulk_insert_and_delete_keeps_index_in_sync() {
+        // 10k inserts and 1k deletes; fast with the ID index, quadratic without
+        let mut db = VecDB::new();
+        for i in 0..10_000 {
+            db.insert(format!("vec{}", i), vec![i as f32 + 1.0, 1.0])
+                .unwrap();
+        }
+        for i in (0..10_000).step_by(10) {
+            db.delete(&format!("vec{}", i)).unwrap();
+        }
+
+        assert_eq!(db.count(), 9_000);
+        assert!(db.get("vec0").is_none());
+        assert!(db.get("vec9990").is_none());
+        // Shifted entries still resolve to their own vectors
+        let expected = l2_norm(&[9_999.0 + 1.0, 1.0]).unwrap();
+        assert_eq!(db.get("vec9999").unwrap(), expected);
+        let expected = l2_norm(&[12.0, 1.0]).unwrap();
+        assert_eq!(db.get("vec11").unwrap(), expected);
+    }
+
+    #[test]
+    fn test_remove_returns_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+        db.insert("vec2".to_string(), vec![4.0, 5.0, 6.0]).unwrap();
+        db.insert("vec3".to_string(), vec![7.0, 8.0, 9.0]).unwrap();
+
+        let expected = db.get("vec2").unwrap();
+        let removed = db.remove("vec2").unwrap();
+        assert_eq!(removed, expected);
+
+        assert!(db.get("vec2").is_none());
+        assert_eq!(db.count(), 2);
+        assert_eq!(db.vectors.len(), 6);
+        // Neighbours are untouched
+        assert!((db.get("vec3").unwrap()[0] - db.get_vector(1)[0]).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_remove_missing_id() {
+        let mut db = VecDB::new();
+        assert_eq!(
+            db.remove("vec1").unwrap_err(),
+            "Cannot delete on empty database"
+        );
+
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+        assert_eq!(db.remove("vec2").unwrap_err(), "ID not found");
+        assert_eq!(db.count(), 1);
+    }
+
+    #[test]
+    fn test_insert_after_delete() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+        db.delete("vec1").unwrap();
+
+        // Should be able to insert again with same ID
+        let result = db.insert("vec1".to_string(), vec![3.0, 4.0]);
+        assert!(result.is_ok());
+
+        let vec = db.get("vec1").unwrap();
+        // Normalized [3,4] = [0.6, 0.8]
+        assert!((vec[0] - 0.6).abs() < 1e-5);
+    }
+
+    // ========== Recommend Tests ==========
+
+    #[test]
+    fn test_recommend_with_positives_and_negatives() {
+        let mut db = VecDB::new();
+        db.insert("jazz".to_string(), vec![1.0, 0.2, 0.0, 0.0])
+            .unwrap();
+        db.insert("blues".to_string(), vec![0.9, 0.3, 0.1, 0.0])
+            .unwrap();
+        db.insert("swing".to_string(), vec![0.8, 0.3, 0.0, 0.2])
+            .unwrap();
+        db.insert("metal".to_string(), vec![0.5, 0.0, 0.0, 0.9])
+            .unwrap();
+        db.insert("noise".to_string(), vec![0.0, 0.0, 1.0, 0.0])
+            .unwrap();
+
+        let positives = ["jazz".to_string(), "blues".to_string()];
+        let results = db.recommend(&positives, &[], 5).unwrap();
+        assert_eq!(results[0].id, "swing");
+        assert_eq!(results.len(), 3);
+        assert!(
+            results
+                .iter()
+                .all(|result| result.id != "jazz" && result.id != "blues")
+        );
+        let metal_rank =
+            |results: &[SearchResult]| results.iter().position(|result| result.id == "metal");
+        assert_eq!(metal_rank(&results), Some(1));
+
+        // A negative that points towards metal pushes it below noise
+        let negatives = ["swing".to_string()];
+        let results = db.recommend(&positives, &negatives, 5).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "noise");
+        assert_eq!(metal_rank(&results), Some(1));
+
+        assert_eq!(
+            db.recommend(&positives, &["pop".to_string()], 5)
+                .err()
+                .unwrap(),
+            "ID not found: pop"
+        );
+        assert_eq!(
+            db.recommend(&[], &negatives, 5).err().unwrap(),
+            "At least one positive ID is required"
+        );
+        assert_eq!(
+            db.recommend(&negatives, &negatives, 5).err().unwrap(),
+            "Cannot normalize a zero vector"
+        );
+    }
+
+    #[test]
+    fn test_search_by_id_excludes_itself() {
+        let mut db = VecDB::new();
+        db.insert("a".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        db.insert("b".to_string(), vec![0.9, 0.1, 0.0]).unwrap();
+        db.insert("c".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("d".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+
+        for id in ["a", "b", "c", "d"] {
+            let results = db.search_by_id(id, 10).unwrap();
+            assert_eq!(results.len(), 3);
+            assert!(results.iter().all(|result| result.id != id));
+        }
+        assert_eq!(db.search_by_id("a", 1).unwrap()[0].id, "b");
+        assert!(db.search_by_id("a", 0).unwrap().is_empty());
+        assert_eq!(db.search_by_id("e", 1).err().unwrap(), "ID not found");
+    }
+
+    #[test]
+    fn test_search_mmr_surfaces_outlier() {
+        let mut db = VecDB::new();
+        db.insert("near1".to_string(), vec![1.0, 0.05, 0.0])
+            .unwrap();
+        db.insert("near2".to_string(), vec![1.0, 0.06, 0.0])
+            .unwrap();
+        db.insert("near3".to_string(), vec![1.0, 0.07, 0.0])
+            .unwrap();
+        db.insert("outlier".to_string(), vec![0.5, 0.0, 1.0])
+            .unwrap();
+        let query = vec![1.0, 0.0, 0.0];
+
+        let ids = |results: Vec<SearchResult>| -> Vec<String> {
+            results.into_iter().map(|result| result.id).collect()
+        };
+
+        // Pure relevance is plain search order
+        let relevant = db.search_mmr(query.clone(), 3, 1.0).unwrap();
+        assert_eq!(relevant, db.search(query.clone(), 3).unwrap());
+        assert!(!ids(relevant).contains(&"outlier".to_string()));
+
+        // Weighting diversity picks the outlier right after the best match
+        let diverse = db.search_mmr(query.clone(), 3, 0.3).unwrap();
+        assert_eq!(ids(diverse.clone())[..2], ["near1", "outlier"]);
+        let outlier_score = db.search(query.clone(), 4).unwrap()[3].score;
+        assert_eq!(diverse[1].score, outlier_score);
+
+        assert_eq!(db.search_mmr(query.clone(), 10, 0.5).unwrap().len(), 4);
+        assert!(db.search_mmr(query.clone(), 0, 0.5).unwrap().is_empty());
+        assert_eq!(
+            db.search_mmr(query, 2, 1.5).err().unwrap(),
+            "lambda must be between 0 and 1, got 1.5"
+        );
+    }
+
+    // ========== Index Tests ==========
+
+    /// Uniform vectors in [-1, 1) from a fixed xorshift seed.
+    fn random_vectors(count: usize, dim: usize, mut seed: u64) -> Vec<Vec<f32>> {
+        (0..count)
+            .map(|_| {
+                (0..dim)
+                    .map(|_| {
+                        seed ^= seed << 13;
+                        seed ^= seed >> 7;
+                        seed ^= seed << 17;
+                        (seed >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hnsw_recall_against_brute_force() {
+        let dim = 16;
+        let mut exact = VecDB::new();
+        let mut approx = VecDB::new().with_index(IndexKind::default());
+        for (i, vector) in random_vectors(1000, dim, 42).into_iter().enumerate() {
+            exact.insert(format!("vec{}", i), vector.clone()).unwrap();
+            approx.insert(format!("vec{}", i), vector).unwrap();
+        }
+
+        let queries = random_vectors(50, dim, 7);
+        let mut hits = 0;
+        let mut comparisons = 0;
+        for query in &queries {
+            let truth: HashSet<String> = exact
+                .search(query.clone(), 10)
+                .unwrap()
+                .into_iter()
+                .map(|result| result.id)
+                .collect();
+            let (found, metrics) = approx.search_instrumented(query.clone(), 10).unwrap();
+            assert_eq!(found.len(), 10);
+            assert!(found.windows(2).all(|pair| pair[0].score >= pair[1].score));
+            hits += found
+                .iter()
+                .filter(|result| truth.contains(&result.id))
+                .count();
+            comparisons += metrics.comparisons;
+        }
+
+        let recall = hits as f32 / (queries.len() * 10) as f32;
+        assert!(recall >= 0.95, "recall@10 was {}", recall);
+        // The graph visits only part of the database
+        assert!(comparisons < queries.len() * 1000);
+    }
+
+    #[test]
+    fn test_hnsw_follows_updates_and_deletes() {
+        let mut db = VecDB::new()
+            .with_metric(Metric::Euclidean)
+            .with_index(IndexKind::Hnsw {
+                m: 4,
+                ef_construction: 20,
+                ef_search: 20,
+            });
+        for (i, vector) in random_vectors(200, 4, 3).into_iter().enumerate() {
+            db.insert(format!("vec{}", i), vector).unwrap();
+        }
+        let target = vec![5.0, 5.0, 5.0, 5.0];
+        db.insert("vec10".to_string(), target.clone()).unwrap();
+        assert_eq!(db.search(target.clone(), 1).unwrap()[0].id, "vec10");
+
+        db.delete("vec10").unwrap();
+        db.delete_fast("vec3").unwrap();
+        let results = db.search(target.clone(), 5).unwrap();
+        assert!(
+            results
+                .iter()
+                .all(|result| result.id != "vec10" && result.id != "vec3")
+        );
+
+        // Inserts after the rebuild extend the new graph
+        let exact = db.search(target.clone(), 1).unwrap();
+        db.insert("late".to_string(), vec![4.0, 4.0, 4.0, 4.0])
+            .unwrap();
+        assert_eq!(db.search(target, 1).unwrap()[0].id, "late");
+        assert_ne!(exact[0].id, "late");
+    }
+
+    #[test]
+    fn test_ivf_recall_grows_with_nprobe() {
+        let dim = 16;
+        let mut exact = VecDB::new();
+        for (i, vector) in random_vectors(1000, dim, 42).into_iter().enumerate() {
+            exact.insert(format!("vec{}", i), vector).unwrap();
+        }
+        let queries = random_vectors(50, dim, 7);
+        let truths: Vec<HashSet<String>> = queries
+            .iter()
+            .map(|query| {
+                exact
+                    .search(query.clone(), 10)
+                    .unwrap()
+                    .into_iter()
+                    .map(|result| result.id)
+                    .collect()
+            })
+            .collect();
+
+        let db = exact.snapshot_memory().0.with_index(IndexKind::Ivf {
+            nlist: 16,
+            nprobe: 4,
+        });
+        let recall = |nprobe: usize| {
+            let hits: usize = queries
+                .iter()
+                .zip(&truths)
+                .map(|(query, truth)| {
+                    db.search_nprobe(query.clone(), 10, nprobe)
+                        .unwrap()
+                        .iter()
+                        .filter(|result| truth.contains(&result.id))
+                        .count()
+                })
+                .sum();
+            hits as f32 / (queries.len() * 10) as f32
+        };
+
+        let recalls: Vec<f32> = [1, 4, 8, 16].into_iter().map(recall).collect();
+        assert!(
+            recalls.windows(2).all(|pair| pair[0] <= pair[1]),
+            "{:?}",
+            recalls
+        );
+        assert!(
+            recalls[2] >= 0.9,
+            "recall@10 with nprobe 8 was {}",
+            recalls[2]
+        );
+        assert_eq!(recalls[3], 1.0);
+
+        // The configured nprobe scans only part of the database
+        let (results, metrics) = db.search_instrumented(queries[0].clone(), 10).unwrap();
+        assert_eq!(results.len(), 10);
+        assert!(metrics.comparisons < 1000);
+        assert_eq!(
+            exact
+                .search_nprobe(queries[0].clone(), 10, 1)
+                .err()
+                .unwrap(),
+            "Database has no IVF index"
+        );
+    }
+
+    #[test]
+    fn test_ivf_is_saved_and_follows_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ivf.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new().with_index(IndexKind::Ivf {
+            nlist: 4,
+            nprobe: 1,
+        });
+        for (i, vector) in random_vectors(100, 8, 5).into_iter().enumerate() {
+            db.insert(format!("vec{}", i), vector).unwrap();
+        }
+        // Set on an empty database, the index stays untrained until asked
+        assert_eq!(
+            db.search_instrumented(vec![1.0; 8], 1)
+                .unwrap()
+                .1
+                .comparisons,
+            100
+        );
+        db.train_index().unwrap();
+        assert!(
+            db.search_instrumented(vec![1.0; 8], 1)
+                .unwrap()
+                .1
+                .comparisons
+                < 100
+        );
+
+        db.delete("vec0").unwrap();
+        db.delete_fast("vec1").unwrap();
+        db.insert("vec2".to_string(), vec![1.0; 8]).unwrap();
+        db.insert("late".to_string(), vec![-1.0; 8]).unwrap();
+        assert!(db.verify().is_ok());
+        assert_eq!(db.search(vec![1.0; 8], 1).unwrap()[0].id, "vec2");
+        assert_eq!(db.search(vec![-1.0; 8], 1).unwrap()[0].id, "late");
+
+        db.save(path_str).unwrap();
+        let loaded = VecDB::load(path_str).unwrap();
+        for query in random_vectors(10, 8, 9) {
+            assert_eq!(
+                loaded.search_instrumented(query.clone(), 5).unwrap(),
+                db.search_instrumented(query, 5).unwrap()
+            );
+        }
+
+        let mut small = VecDB::new().with_index(IndexKind::Ivf {
+            nlist: 4,
+            nprobe: 1,
+        });
+        small.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        assert_eq!(
+            small.train_index().err().unwrap(),
+            "Training needs at least 4 vectors, found 1"
+        );
+        assert_eq!(
+            VecDB::new().train_index().err().unwrap(),
+            "Database has no IVF index"
+        );
+    }
+
+    // ========== Quantization Tests ==========
+
+    #[test]
+    fn test_with_quantization_int8() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new().with_quantization(Quantization::Int8);
+        db.insert("x".to_string(), vec![1.0, 0.1, 0.0]).unwrap();
+        db.insert("y".to_string(), vec![0.1, 1.0, 0.0]).unwrap();
+        db.insert("z".to_string(), vec![0.0, 0.1, 1.0]).unwrap();
+        assert!(db.vectors.is_empty());
+        assert_eq!(db.codes.len(), 9);
+
+        for (query, expected) in [([0.9, 0.2, 0.0], "x"), ([0.0, 0.3, 0.8], "z")] {
+            assert_eq!(db.search(query.to_vec(), 1).unwrap()[0].id, expected);
+        }
+
+        db.save(path_str).unwrap();
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.config().quantization, Quantization::Int8);
+        assert_eq!(loaded.search(vec![0.2, 0.9, 0.0], 1).unwrap()[0].id, "y");
+
+        // Switching back converts the stored codes to f32
+        let restored = loaded.with_quantization(Quantization::None);
+        assert!(restored.codes.is_empty());
+        assert_eq!(restored.vectors.len(), 9);
+        assert_eq!(restored.search(vec![0.9, 0.2, 0.0], 1).unwrap()[0].id, "x");
+    }
+
+    #[test]
+    fn test_binary_quantization_ranks_by_hamming() {
+        let mut db = VecDB::new().with_quantization(Quantization::Binary);
+        let dim = 96;
+        let base: Vec<f32> = (0..dim)
+            .map(|i| if i % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        // Flips 4 signs: near-parallel to the base
+        let near: Vec<f32> = (0..dim)
+            .map(|i| if i < 4 { -base[i] } else { base[i] })
+            .collect();
+        // Flips half the signs: orthogonal to the base
+        let orthogonal: Vec<f32> = (0..dim)
+            .map(|i| if i % 4 < 2 { -base[i] } else { base[i] })
+            .collect();
+        db.insert("orthogonal".to_string(), orthogonal).unwrap();
+        db.insert("near".to_string(), near).unwrap();
+        assert_eq!(db.bits.len(), 4);
+        assert!(db.vectors.is_empty());
+
+        let results = db.search(base.clone(), 2).unwrap();
+        assert_eq!(results[0].id, "near");
+        assert_eq!(results[1].id, "orthogonal");
+        assert!((results[0].score - (1.0 - 8.0 / 96.0)).abs() < 1e-6);
+        assert!(results[1].score.abs() < 1e-6);
+
+        // Reads give the unit vector with the stored signs
+        let near_read = db.get("near").unwrap();
+        assert!((near_read[0] + 1.0 / 96f32.sqrt()).abs() < 1e-6);
+        assert_eq!(
+            db.score_pairs(&[(base, "near".to_string())]).unwrap()[0],
+            Some(results[0].score)
+        );
+
+        db.delete("orthogonal").unwrap();
+        assert!(db.verify().is_ok());
+        assert_eq!(db.bits.len(), 2);
+    }
+
+    #[test]
+    fn test_quantize_int8_keeps_rankings() {
+        let mut state: u64 = 7;
+        let mut next = move || {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            ((state >> 33) as f32) / (u32::MAX as f32) * 2.0 - 1.0
+        };
+
+        let dim = 32;
+        let mut db = VecDB::new();
+        for i in 0..100 {
+            let vector: Vec<f32> = (0..dim).map(|_| next()).collect();
+            db.insert(format!("vec{}", i), vector).unwrap();
+        }
+        let queries: Vec<Vec<f32>> = (0..5).map(|_| (0..dim).map(|_| next()).collect()).collect();
+        let before: Vec<Vec<String>> = queries
+            .iter()
+            .map(|q| {
+                db.search(q.clone(), 10)
+                    .unwrap()
+                    .into_iter()
+                    .map(|r| r.id)
+                    .collect()
+            })
+            .collect();
+
+        db.quantize_int8().unwrap();
+        assert!(db.vectors.is_empty());
+        assert_eq!(db.codes.len(), 100 * dim);
+        assert!(db.verify().is_ok());
+        assert!(db.quantize_int8().is_err());
+
+        // Documented tolerance: top-10 recall of at least 90%, same top-1
+        for (query, expected) in queries.iter().zip(&before) {
+            let results = db.search(query.clone(), 10).unwrap();
+            assert_eq!(results[0].id, expected[0]);
+            let hits = results.iter().filter(|r| expected.contains(&r.id)).count();
+            assert!(hits >= 9, "recall {}/10", hits);
+        }
+    }
+
+    #[test]
+    fn test_quantized_db_insert_delete_and_reload() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("int8.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.quantize_int8().unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.6, 0.8]).unwrap();
+        db.delete("vec2").unwrap();
+        assert!(db.verify().is_ok());
+        db.save(path_str).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.config().quantization, Quantization::Int8);
+        let vec3 = loaded.get("vec3").unwrap();
+        assert!((vec3[0] - 0.6).abs() < 0.01);
+        assert!((vec3[1] - 0.8).abs() < 0.01);
+        assert_eq!(loaded.search(vec![0.0, 1.0], 1).unwrap()[0].id, "vec3");
+    }
+
+    // ========== Precision Tests ==========
+
+    #[test]
+    fn test_f64_precision_keeps_full_values() {
+        let mut db = VecDB::new().with_precision(Precision::F64);
+        db.insert_f64("a".to_string(), vec![1.0, 1e-4]).unwrap();
+        db.insert_f64("b".to_string(), vec![1.0, 2e-4]).unwrap();
+        db.insert_f64("c".to_string(), vec![0.0, 1.0]).unwrap();
+
+        // 1 / sqrt(1 + 1e-8) rounds to 1.0 in f32
+        let expected = l2_norm_f64(&[1.0, 1e-4]).unwrap();
+        let stored = db.get_f64("a").unwrap();
+        assert_eq!(stored, expected);
+        assert!(stored[0] < 1.0);
+        assert_eq!(db.get("a").unwrap()[0], 1.0);
+        assert!(db.verify().is_ok());
+
+        // In f32 "a" and "b" score the same against this query; in f64 "b" wins
+        let query = vec![1.0, 2e-4];
+        let results = db.search_f64(query.clone(), 3).unwrap();
+        assert_eq!(results[0].id, "b");
+        assert_eq!(results[1].id, "a");
+        assert!(results[0].score > results[1].score);
+        let norm_q = l2_norm_f64(&query).unwrap();
+        let exact: f64 = expected.iter().zip(&norm_q).map(|(x, y)| x * y).sum();
+        assert_eq!(results[1].score, exact);
+        assert_eq!(results[1].vector, expected);
+        let f32_results = db.search(vec![1.0, 2e-4], 2).unwrap();
+        assert_eq!(f32_results[0].score, f32_results[1].score);
+
+        // Removing a vector moves the others without losing precision
+        db.delete("a").unwrap();
+        assert_eq!(db.get_f64("b").unwrap(), l2_norm_f64(&[1.0, 2e-4]).unwrap());
+        assert_eq!(db.get("c").unwrap(), vec![0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_f64_precision_survives_save_and_conversion() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wide.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new()
+            .with_metric(Metric::Euclidean)
+            .with_precision(Precision::F64);
+        db.insert_f64("a".to_string(), vec![0.1, 1e-300]).unwrap();
+        db.save(path_str).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.config().precision, Precision::F64);
+        assert_eq!(loaded.get_f64("a").unwrap(), vec![0.1, 1e-300]);
+
+        // Converting to f32 rounds, and back to f64 keeps the rounded values
+        let db = loaded.with_precision(Precision::F32);
+        assert_eq!(db.get_f64("a").unwrap(), vec![0.1f32 as f64, 0.0]);
+        let db = db.with_precision(Precision::F64);
+        assert_eq!(db.get("a").unwrap(), vec![0.1, 0.0]);
+        assert!(db.verify().is_ok());
+    }
+
+    // ========== Stats Tests ==========
+
+    #[test]
+    fn test_stats() {
+        let db = VecDB::new();
+        assert_eq!(db.stats(), DbStats::default());
+
+        let mut db = VecDB::new();
+        db.insert("a".to_string(), vec![3.0, 4.0, 0.0]).unwrap();
+        db.insert("b".to_string(), vec![0.0, 0.0, 7.0]).unwrap();
+        db.insert("c".to_string(), vec![1.0, 1.0, 1.0]).unwrap();
+
+        let stats = db.stats();
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.dimension, Some(3));
+        assert!((stats.mean_norm - 1.0).abs() < 1e-4);
+        assert!(stats.min_norm <= stats.mean_norm && stats.mean_norm <= stats.max_norm);
+
+        // Raw vectors are kept as is under a distance metric
+        let mut db = VecDB::new().with_metric(Metric::Euclidean);
+        db.insert("a".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("b".to_string(), vec![0.0, 1.0]).unwrap();
+        let stats = db.stats();
+        assert_eq!(stats.min_norm, 1.0);
+        assert_eq!(stats.max_norm, 5.0);
+        assert_eq!(stats.mean_norm, 3.0);
+    }
+
+    #[test]
+    fn test_clear() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        db.clear();
+        assert_eq!(db.count(), 0);
+        assert_eq!(db.dimension(), None);
+        assert!(db.get("vec1").is_none());
+
+        db.insert("vec3".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(db.count(), 1);
+        assert_eq!(db.dimension(), Some(3));
+        assert_eq!(db.search(vec![1.0, 0.0, 0.0], 5).unwrap()[0].id, "vec3");
     }
 
     #[test]
-    fn test_search_basic() {
+    fn test_dimension_accessor() {
         let mut db = VecDB::new();
+        assert_eq!(db.dimension(), None);
 
-        // Insert three vectors
+        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+        assert_eq!(db.dimension(), Some(3));
+    }
+
+    #[test]
+    fn test_constant_dimensions() {
+        let mut db = VecDB::new();
+        assert!(db.constant_dimensions(1e-6).is_empty());
+
+        // Same norm for every vector, so the third component stays constant
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.5]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.5]).unwrap();
+        db.insert("vec3".to_string(), vec![0.6, 0.8, 0.5]).unwrap();
+
+        assert_eq!(db.constant_dimensions(1e-6), vec![2]);
+    }
+
+    // ========== Maintenance Tests ==========
+
+    #[test]
+    fn test_project_dimensions() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![3.0, 0.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 3.0, 4.0]).unwrap();
+
+        db.project_dimensions(&[0, 1]).unwrap();
+
+        assert_eq!(db.dimension, Some(2));
+        assert_eq!(db.vectors.len(), 4);
+        assert_eq!(db.get("vec1").unwrap(), vec![1.0, 0.0]);
+
+        let results = db.search(vec![0.0, 1.0], 1).unwrap();
+        assert_eq!(results[0].id, "vec2");
+        assert!((results[0].score - 1.0).abs() < 1e-5);
+
+        assert!(db.search(vec![0.0, 1.0, 0.0], 1).is_err());
+    }
+
+    #[test]
+    fn test_project_dimensions_invalid() {
+        let mut db = VecDB::new();
+        assert_eq!(db.project_dimensions(&[0]).unwrap_err(), "Empty database");
+
+        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        assert!(db.project_dimensions(&[]).is_err());
+        assert!(db.project_dimensions(&[0, 3]).is_err());
+        assert!(db.project_dimensions(&[1, 1]).is_err());
+
+        // Projection would leave a zero vector; nothing changes
+        assert!(db.project_dimensions(&[1, 2]).is_err());
+        assert_eq!(db.dimension, Some(3));
+        assert_eq!(db.get("vec1").unwrap(), vec![1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_repair_drops_dangling_id() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+
+        // Simulate corruption: an ID with no vector data
+        db.ids.push("dangling".to_string());
+        assert!(db.verify().is_err());
+
+        let report = db.repair();
+        assert_eq!(report.dropped_ids, vec!["dangling".to_string()]);
+        assert_eq!(report.truncated_values, 0);
+        assert!(db.verify().is_ok());
+        assert_eq!(db.count(), 2);
+        assert_eq!(db.search(vec![1.0, 0.0], 1).unwrap()[0].id, "vec1");
+    }
+
+    #[test]
+    fn test_repair_truncates_partial_vector() {
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+
+        // A trailing half-written vector with no ID
+        db.vectors.push(0.5);
+
+        let report = db.repair();
+        assert!(report.dropped_ids.is_empty());
+        assert_eq!(report.truncated_values, 1);
+        assert!(db.verify().is_ok());
+        assert_eq!(db.repair(), RepairReport::default());
+    }
+
+    // ========== Save/Load Tests ==========
+
+    #[test]
+    fn test_save_append() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+        let base = std::fs::read(path_str).unwrap();
+
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec3".to_string(), vec![1.0, 1.0]).unwrap();
+        db.save_append(path_str).unwrap();
+
+        // Only the WAL grew, with one record per new vector
+        assert_eq!(std::fs::read(path_str).unwrap(), base);
+        assert_eq!(wal::read_all(path_str).unwrap().len(), 2);
+
+        db.insert("vec4".to_string(), vec![2.0, 1.0]).unwrap();
+        db.save_append(path_str).unwrap();
+        assert_eq!(wal::read_all(path_str).unwrap().len(), 3);
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.ids, db.ids);
+        for (a, b) in loaded
+            .get("vec4")
+            .unwrap()
+            .iter()
+            .zip(db.get("vec4").unwrap())
+        {
+            assert!((a - b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn test_save_append_rewrites_after_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.save_append(path_str).unwrap();
+
+        // A delete can't be appended, so the whole file is rewritten
+        db.delete("vec2").unwrap();
+        db.insert("vec1".to_string(), vec![1.0, 1.0]).unwrap();
+        db.save_append(path_str).unwrap();
+        assert!(wal::read_all(path_str).unwrap().is_empty());
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.count(), 1);
+        assert!(!loaded.contains("vec2"));
+        assert_eq!(loaded.get("vec1").unwrap(), db.get("vec1").unwrap());
+
+        // Appending to a different file than the last one saved writes it in full
+        let other = dir.path().join("other.db");
+        let other_str = other.to_str().unwrap();
+        db.save_append(other_str).unwrap();
+        assert_eq!(VecDB::load(other_str).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_bincode_layout_is_stable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("golden.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("a".to_string(), vec![2.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+
+        #[rustfmt::skip]
+        let golden: &[u8] = &[
+            b'K', b'V', b'D', b'B',                   // header: magic
+            4, 0, 0, 0,                               //   format version 4
+            2, 0, 0, 0, 0, 0, 0, 0,                   //   dimension 2
+            1, 0, 0, 0, 0, 0, 0, 0,                   // ids: 1 entry
+            1, 0, 0, 0, 0, 0, 0, 0, b'a',             //   "a"
+            2, 0, 0, 0, 0, 0, 0, 0,                   // vectors: 2 values
+            0x00, 0x00, 0x80, 0x3f, 0, 0, 0, 0,       //   1.0, 0.0
+            0, 0, 0, 0, 0, 0, 0, 0,                   // codes: empty
+            0, 0, 0, 0, 0, 0, 0, 0,                   // scales: empty
+            0, 0, 0, 0, 0, 0, 0, 0,                   // bits: empty
+            0, 0, 0, 0, 0, 0, 0, 0,                   // wide: empty
+            1, 2, 0, 0, 0, 0, 0, 0, 0,                // dimension: Some(2)
+            0, 0, 0, 0, 1, 0, 0, 0, 0,                // config: Cosine, normalize, no quantization,
+            0, 0, 0, 0,                               //   f32 precision
+            0, 0,                                     // id_policy
+            0, 0, 0, 0, 0, 0, 0, 0,                   // metadata: empty
+            0,                                        // index_kind: None
+            0,                                        // ivf: None
+        ];
+        assert_eq!(std::fs::read(path_str).unwrap(), golden);
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.get("a").unwrap(), vec![1.0, 0.0]);
+        assert_eq!(loaded.config(), db.config());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
         db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
         db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
-        db.insert("vec3".to_string(), vec![0.7, 0.7, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+
+        db.save(path_str).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.count(), 3);
+        assert_eq!(loaded.dimension, Some(3));
+
+        // Verify vectors are preserved
+        let v1 = loaded.get("vec1").unwrap();
+        assert!((v1[0] - 1.0).abs() < 1e-5);
+        assert!((v1[1] - 0.0).abs() < 1e-5);
+
+        let v2 = loaded.get("vec2").unwrap();
+        assert!((v2[1] - 1.0).abs() < 1e-5);
+
+        let v3 = loaded.get("vec3").unwrap();
+        assert!((v3[2] - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_save_and_load_empty_db() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty.db");
+        let path_str = path.to_str().unwrap();
+
+        let db = VecDB::new();
+        db.save(path_str).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.count(), 0);
+        assert_eq!(loaded.dimension, None);
+    }
+
+    #[test]
+    fn test_load_nonexistent_file() {
+        match VecDB::load("nonexistent_file.db") {
+            Err(e) => assert!(e.contains("File not found")),
+            Ok(_) => panic!("Expected error for nonexistent file"),
+        }
+    }
+
+    #[test]
+    fn test_save_load_preserves_search() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.insert("vec3".to_string(), vec![0.7, 0.7]).unwrap();
+
+        db.save(path_str).unwrap();
+        let loaded = VecDB::load(path_str).unwrap();
+
+        // Search on loaded db should return same results
+        let results = loaded.search(vec![1.0, 0.0], 2).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].id, "vec1");
+        assert!((results[0].score - 1.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_load_replays_and_truncates_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wal.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.save(path_str).unwrap();
+
+        wal::append(
+            path_str,
+            &WalRecord::Insert {
+                id: "vec3".to_string(),
+                vector: vec![3.0, 4.0],
+            },
+        )
+        .unwrap();
+        wal::append(
+            path_str,
+            &WalRecord::Delete {
+                id: "vec1".to_string(),
+            },
+        )
+        .unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.count(), 2);
+        assert!(loaded.get("vec1").is_none());
+        assert!(loaded.get("vec2").is_some());
+        let v3 = loaded.get("vec3").unwrap();
+        assert!((v3[0] - 0.6).abs() < 1e-5);
+
+        // WAL is compacted into the base file and emptied
+        let wal_len = std::fs::metadata(wal::wal_path(path_str)).unwrap().len();
+        assert_eq!(wal_len, 0);
+        let reloaded = VecDB::load_with_options(path_str, false).unwrap();
+        assert_eq!(reloaded.count(), 2);
+        assert!(reloaded.get("vec3").is_some());
+    }
+
+    #[test]
+    fn test_load_ignores_oversized_wal_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("garbage.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+        std::fs::write(wal::wal_path(path_str), u64::MAX.to_le_bytes()).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.count(), 1);
+        assert!(loaded.contains("vec1"));
+    }
+
+    #[test]
+    fn test_append_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("append.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
+        db.save(path_str).unwrap();
+        let base_len = std::fs::metadata(path_str).unwrap().len();
+
+        append_to_file(path_str, "vec3".to_string(), vec![3.0, 4.0]).unwrap();
+        append_to_file(path_str, "vec4".to_string(), vec![1.0, 1.0]).unwrap();
+
+        // The base file isn't rewritten until the next load
+        assert_eq!(std::fs::metadata(path_str).unwrap().len(), base_len);
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.count(), 4);
+        assert_eq!(loaded.get("vec1").unwrap(), vec![1.0, 0.0]);
+        assert_eq!(loaded.get("vec2").unwrap(), vec![0.0, 1.0]);
+        let v3 = loaded.get("vec3").unwrap();
+        assert!((v3[0] - 0.6).abs() < 1e-5);
+        assert!(loaded.contains("vec4"));
+    }
+
+    #[test]
+    fn test_append_to_missing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("missing.db");
+        let path_str = path.to_str().unwrap();
+
+        let result = append_to_file(path_str, "vec1".to_string(), vec![1.0]);
+        assert!(result.unwrap_err().contains("File not found"));
+        assert!(!std::path::Path::new(&wal::wal_path(path_str)).exists());
+    }
+
+    #[test]
+    fn test_load_without_wal_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nowal.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+        wal::append(
+            path_str,
+            &WalRecord::Delete {
+                id: "vec1".to_string(),
+            },
+        )
+        .unwrap();
+
+        let loaded = VecDB::load_with_options(path_str, false).unwrap();
+        assert!(loaded.get("vec1").is_some());
+        assert_eq!(wal::read_all(path_str).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_dump_flat_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let vectors_path = dir.path().join("vectors.f32");
+        let meta_path = dir.path().join("meta.json");
+
+        let mut db = VecDB::new();
+        db.insert("vec1".to_string(), vec![3.0, 4.0, 0.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
+        db.insert("vec3".to_string(), vec![1.0, 1.0, 1.0]).unwrap();
+
+        db.dump_flat(vectors_path.to_str().unwrap(), meta_path.to_str().unwrap())
+            .unwrap();
+
+        let meta: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&meta_path).unwrap()).unwrap();
+        assert_eq!(meta["count"], 3);
+        assert_eq!(meta["dimension"], 3);
+
+        let bytes = std::fs::read(&vectors_path).unwrap();
+        assert_eq!(bytes.len(), 3 * 3 * 4);
+        let values: Vec<f32> = bytes
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+
+        let ids = meta["ids"].as_array().unwrap();
+        for (i, (id, vec)) in db.list().iter().enumerate() {
+            assert_eq!(ids[i], id.as_str());
+            assert_eq!(&values[i * 3..(i + 1) * 3], vec.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_save_load_preserves_id_policy() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("policy.db");
+        let path_str = path.to_str().unwrap();
+
+        let policy = IdPolicy {
+            trim: true,
+            lowercase: true,
+        };
+        let mut db = VecDB::new().with_id_policy(policy);
+        db.insert("Vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+
+        let mut loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.id_policy, policy);
+        assert!(loaded.get(" VEC1 ").is_some());
+        loaded.insert("VEC1".to_string(), vec![0.0, 1.0]).unwrap();
+        assert_eq!(loaded.count(), 1);
+    }
+
+    #[test]
+    fn test_save_load_preserves_metric() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("metric.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new().with_metric(Metric::Euclidean);
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.save(path_str).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.config.metric, Metric::Euclidean);
+        assert_eq!(loaded.get("vec1").unwrap(), vec![3.0, 4.0]);
+        let results = loaded.search(vec![0.0, 0.0], 1).unwrap();
+        assert!((results[0].score - 5.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_save_load_preserves_normalization_flag() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("raw.db");
+        let path_str = path.to_str().unwrap();
 
-        // Search for vector close to vec1
-        let results = db.search(vec![1.0, 0.0, 0.0], 2).unwrap();
+        let mut db = VecDB::new().with_normalization(false);
+        db.insert("vec1".to_string(), vec![0.6, 0.8]).unwrap();
+        db.save(path_str).unwrap();
 
-        assert_eq!(results.len(), 2);
-        // First result should be vec1 (exact match, similarity = 1.0)
-        assert_eq!(results[0].0, "vec1");
-        assert!((results[0].2 - 1.0).abs() < 1e-5);
+        let mut loaded = VecDB::load(path_str).unwrap();
+        assert!(!loaded.config.normalize);
+        loaded.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+        assert_eq!(loaded.get("vec2").unwrap(), vec![3.0, 4.0]);
     }
 
     #[test]
-    fn test_search_returns_top_k() {
-        let mut db = VecDB::new();
+    fn test_load_rebuilds_id_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("index.db");
+        let path_str = path.to_str().unwrap();
 
+        let mut db = VecDB::new();
         db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
         db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
-        db.insert("vec3".to_string(), vec![0.5, 0.5]).unwrap();
+        db.save(path_str).unwrap();
 
-        // Request top 2 out of 3
-        let results = db.search(vec![1.0, 1.0], 2).unwrap();
+        let mut loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.index.len(), 2);
+        assert_eq!(loaded.get("vec2").unwrap(), vec![0.0, 1.0]);
 
-        assert_eq!(results.len(), 2);
+        // Updating an existing ID must not add a duplicate
+        loaded.insert("vec1".to_string(), vec![1.0, 1.0]).unwrap();
+        assert_eq!(loaded.count(), 2);
     }
 
     #[test]
-    fn test_search_empty_database() {
-        let db = VecDB::new();
-
-        let result = db.search(vec![1.0, 2.0], 5);
-        assert!(result.is_err());
-    }
+    fn test_save_load_keeps_config_semantics() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.db");
+        let path_str = path.to_str().unwrap();
 
-    #[test]
-    fn test_search_dimension_mismatch() {
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
+        let mut db = VecDB::new()
+            .with_metric(Metric::Euclidean)
+            .with_normalization(false);
+        db.insert("near".to_string(), vec![1.0, 1.0]).unwrap();
+        db.insert("far".to_string(), vec![10.0, 10.0]).unwrap();
+        db.insert("other".to_string(), vec![-5.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
 
-        let result = db.search(vec![1.0, 2.0], 1); // Wrong dimension
-        assert!(result.is_err());
+        // No config given on load: Euclidean on raw vectors is restored
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.config(), db.config());
+        let results = loaded.search(vec![9.0, 9.0], 1).unwrap();
+        assert_eq!(results[0].id, "far");
+        assert!((results[0].score - 2.0f32.sqrt()).abs() < 1e-5);
     }
 
-    // ========== Get Tests ==========
-
     #[test]
-    fn test_get_existing_vector() {
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+    fn test_merge_requires_same_config() {
+        let mut cosine = VecDB::new();
+        cosine.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
+        let mut euclidean = VecDB::new().with_metric(Metric::Euclidean);
+        euclidean
+            .insert("vec2".to_string(), vec![3.0, 4.0])
+            .unwrap();
 
-        let result = db.get("vec1");
-        assert!(result.is_some());
+        assert!(cosine.merge(&euclidean).is_err());
+        assert_eq!(cosine.count(), 1);
 
-        let vec = result.unwrap();
-        assert_eq!(vec.len(), 2);
-        // Should be normalized [3,4] -> [0.6, 0.8]
-        assert!((vec[0] - 0.6).abs() < 1e-5);
-        assert!((vec[1] - 0.8).abs() < 1e-5);
+        let mut target = VecDB::new().with_metric(Metric::Euclidean);
+        assert_eq!(target.merge(&euclidean).unwrap(), 1);
+        assert_eq!(target.get("vec2").unwrap(), vec![3.0, 4.0]);
     }
 
     #[test]
-    fn test_get_nonexistent_vector() {
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+    fn test_merge_disjoint_shards() {
+        let mut shard1 = VecDB::new();
+        shard1.insert("a".to_string(), vec![1.0, 0.0]).unwrap();
+        shard1.insert("b".to_string(), vec![0.0, 1.0]).unwrap();
+        let mut shard2 = VecDB::new();
+        shard2.insert("c".to_string(), vec![1.0, 1.0]).unwrap();
+        shard2.insert("d".to_string(), vec![-1.0, 0.0]).unwrap();
+        shard2.insert("e".to_string(), vec![0.0, -1.0]).unwrap();
 
-        let result = db.get("vec2");
-        assert!(result.is_none());
+        let mut combined = VecDB::new();
+        assert_eq!(combined.merge(&shard1).unwrap(), 2);
+        assert_eq!(combined.merge(&shard2).unwrap(), 3);
+        assert_eq!(combined.count(), 5);
+        assert_eq!(combined.ids(), ["a", "b", "c", "d", "e"]);
+        assert_eq!(combined.get("d").unwrap(), vec![-1.0, 0.0]);
+
+        // On a collision the merged-in vector wins
+        let mut update = VecDB::new();
+        update.insert("a".to_string(), vec![0.0, 1.0]).unwrap();
+        assert_eq!(combined.merge(&update).unwrap(), 1);
+        assert_eq!(combined.count(), 5);
+        assert_eq!(combined.get("a").unwrap(), vec![0.0, 1.0]);
     }
 
     #[test]
-    fn test_get_from_empty_database() {
-        let db = VecDB::new();
+    fn test_merge_rejects_different_dimension() {
+        let mut db = VecDB::new();
+        db.insert("a".to_string(), vec![1.0, 0.0]).unwrap();
+        let mut other = VecDB::new();
+        other.insert("b".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
 
-        let result = db.get("vec1");
-        assert!(result.is_none());
+        assert_eq!(db.merge(&other).err().unwrap(), "Different dimension");
+        assert_eq!(db.count(), 1);
+        assert!(db.get("b").is_none());
     }
 
-    // ========== Delete Tests ==========
-
     #[test]
-    fn test_delete_existing_vector() {
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
-        db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+    fn test_save_as_round_trips_and_detects_format() {
+        let dir = tempfile::tempdir().unwrap();
 
-        let result = db.delete("vec1");
-        assert!(result.is_ok());
+        let mut db = VecDB::new().with_metric(Metric::Euclidean);
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![1.0, 0.0]).unwrap();
 
-        // Verify vec1 is gone
-        assert!(db.get("vec1").is_none());
+        for format in [Format::Bincode, Format::Json, Format::BincodeGz] {
+            let path = dir.path().join(format!("{:?}.db", format));
+            let path_str = path.to_str().unwrap();
+            db.save_as(path_str, format).unwrap();
 
-        // Verify vec2 is still there
-        assert!(db.get("vec2").is_some());
+            let bytes = std::fs::read(path_str).unwrap();
+            assert_eq!(Format::detect(&bytes), format);
 
-        // Verify database size
-        assert_eq!(db.ids.len(), 1);
-        assert_eq!(db.vectors.len(), 2); // 1 vector × 2 dimensions
+            for loaded in [
+                VecDB::load(path_str).unwrap(),
+                VecDB::load_as(path_str, format).unwrap(),
+            ] {
+                assert_eq!(loaded.count(), 2);
+                assert_eq!(loaded.config(), db.config());
+                assert_eq!(loaded.get("vec1").unwrap(), vec![3.0, 4.0]);
+            }
+        }
     }
 
     #[test]
-    fn test_delete_nonexistent_vector() {
+    fn test_save_compressed_is_smaller() {
+        let dir = tempfile::tempdir().unwrap();
+        let plain = dir.path().join("plain.db");
+        let packed = dir.path().join("packed.db");
+
+        // Near-duplicate vectors compress well
         let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
+        for i in 0..200 {
+            db.insert(format!("vec{}", i), vec![1.0, 0.5, (i % 4) as f32])
+                .unwrap();
+        }
+        db.save(plain.to_str().unwrap()).unwrap();
+        db.save_compressed(packed.to_str().unwrap()).unwrap();
 
-        let result = db.delete("vec2");
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "ID not found");
+        let plain_len = std::fs::metadata(&plain).unwrap().len();
+        let packed_len = std::fs::metadata(&packed).unwrap().len();
+        assert!(packed_len < plain_len / 2);
 
-        // Original data should be intact
-        assert_eq!(db.ids.len(), 1);
+        for loaded in [
+            VecDB::load(packed.to_str().unwrap()).unwrap(),
+            VecDB::load_compressed(packed.to_str().unwrap()).unwrap(),
+        ] {
+            assert_eq!(loaded.ids, db.ids);
+            assert_eq!(loaded.vectors, db.vectors);
+        }
+        assert!(VecDB::load_compressed(plain.to_str().unwrap()).is_err());
     }
 
     #[test]
-    fn test_delete_from_empty_database() {
-        let mut db = VecDB::new();
-
-        let result = db.delete("vec1");
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Cannot delete on empty database");
-    }
+    fn test_header_is_validated_on_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap();
 
-    #[test]
-    fn test_delete_first_vector() {
         let mut db = VecDB::new();
         db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
-        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
-        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
-
-        db.delete("vec1").unwrap();
+        db.save(path_str).unwrap();
+        let good = std::fs::read(path_str).unwrap();
+        assert_eq!(&good[..4], b"KVDB");
+        assert_eq!(VecDB::load(path_str).unwrap().count(), 1);
 
-        // Verify vec1 is gone
-        assert!(db.get("vec1").is_none());
+        let mut bad_magic = good.clone();
+        bad_magic[..4].copy_from_slice(b"XVDB");
+        std::fs::write(path_str, &bad_magic).unwrap();
+        assert_eq!(
+            VecDB::load(path_str).err().unwrap(),
+            "Not a kvdb file: missing KVDB header"
+        );
 
-        // Verify vec2 and vec3 are still accessible
-        let v2 = db.get("vec2").unwrap();
-        assert!((v2[1] - 1.0).abs() < 1e-5);
+        let mut future = good.clone();
+        future[4..8].copy_from_slice(&5u32.to_le_bytes());
+        std::fs::write(path_str, &future).unwrap();
+        assert_eq!(
+            VecDB::load(path_str).err().unwrap(),
+            "Unsupported db version 5, this build supports 4"
+        );
 
-        let v3 = db.get("vec3").unwrap();
-        assert!((v3[2] - 1.0).abs() < 1e-5);
+        let mut wrong_dim = good;
+        wrong_dim[8] = 7;
+        std::fs::write(path_str, &wrong_dim).unwrap();
+        assert!(
+            VecDB::load(path_str)
+                .err()
+                .unwrap()
+                .contains("header dimension 7")
+        );
 
-        // Verify counts
-        assert_eq!(db.ids.len(), 2);
-        assert_eq!(db.vectors.len(), 6); // 2 vectors × 3 dimensions
+        // Compressed files carry the header inside the gzip stream
+        db.save_compressed(path_str).unwrap();
+        assert_eq!(VecDB::load(path_str).unwrap().count(), 1);
     }
 
     #[test]
-    fn test_delete_middle_vector() {
+    fn test_export_import_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.json");
+        let path_str = path.to_str().unwrap();
+
         let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
-        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
-        db.insert("vec3".to_string(), vec![1.0, 1.0]).unwrap();
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 2.0]).unwrap();
+        db.export_json(path_str).unwrap();
 
-        db.delete("vec2").unwrap();
+        let json: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path_str).unwrap()).unwrap();
+        assert_eq!(
+            json[1],
+            serde_json::json!({"id": "vec2", "values": [0.0, 1.0]})
+        );
 
-        // Verify vec2 is gone
-        assert!(db.get("vec2").is_none());
+        let imported = VecDB::import_json(path_str).unwrap();
+        assert_eq!(imported.ids, db.ids);
+        assert_eq!(imported.dimension(), Some(2));
+        for ((_, a), (_, b)) in imported.iter().zip(db.iter()) {
+            assert!(a.iter().zip(b.iter()).all(|(x, y)| (x - y).abs() < 1e-6));
+        }
+    }
 
-        // Verify vec1 and vec3 are still correct
-        assert!(db.get("vec1").is_some());
-        assert!(db.get("vec3").is_some());
+    #[test]
+    fn test_import_json_rejects_ragged_vectors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.json");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(
+            path_str,
+            r#"[{"id": "a", "values": [1.0, 0.0]}, {"id": "b", "values": [1.0, 0.0, 0.0]}]"#,
+        )
+        .unwrap();
 
-        assert_eq!(db.ids.len(), 2);
-        assert_eq!(db.vectors.len(), 4); // 2 vectors × 2 dimensions
+        assert_eq!(
+            VecDB::import_json(path_str).err().unwrap(),
+            "Entry 1 ('b') has 3 values, expected 2"
+        );
     }
 
     #[test]
-    fn test_delete_last_vector() {
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0, 3.0]).unwrap();
-        db.insert("vec2".to_string(), vec![4.0, 5.0, 6.0]).unwrap();
-        db.insert("vec3".to_string(), vec![7.0, 8.0, 9.0]).unwrap();
+    fn test_import_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.csv");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(path_str, "a,3.0,4.0\nb, 0.0 , 1.0\n\n").unwrap();
 
-        db.delete("vec3").unwrap();
+        let db = VecDB::import_csv(path_str, false).unwrap();
+        assert_eq!(db.count(), 2);
+        assert_eq!(db.get("a").unwrap(), vec![0.6, 0.8]);
+        assert_eq!(db.get("b").unwrap(), vec![0.0, 1.0]);
+    }
 
-        // Verify vec3 is gone
-        assert!(db.get("vec3").is_none());
+    #[test]
+    fn test_import_csv_with_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.csv");
+        let path_str = path.to_str().unwrap();
+        std::fs::write(path_str, "id,x,y\na,1.0,0.0\n").unwrap();
 
-        // Verify vec1 and vec2 are still there
-        assert!(db.get("vec1").is_some());
-        assert!(db.get("vec2").is_some());
+        let db = VecDB::import_csv(path_str, true).unwrap();
+        assert_eq!(db.list(), vec![("a".to_string(), vec![1.0, 0.0])]);
 
-        assert_eq!(db.ids.len(), 2);
-        assert_eq!(db.vectors.len(), 6); // 2 vectors × 3 dimensions
+        // Without skipping it, the header row fails to parse
+        assert_eq!(
+            VecDB::import_csv(path_str, false).err().unwrap(),
+            "Line 1: invalid component 'x'"
+        );
     }
 
     #[test]
-    fn test_delete_all_vectors_sequentially() {
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
-        db.insert("vec2".to_string(), vec![3.0, 4.0]).unwrap();
+    fn test_import_csv_reports_bad_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.csv");
+        let path_str = path.to_str().unwrap();
 
-        db.delete("vec1").unwrap();
-        assert_eq!(db.ids.len(), 1);
+        std::fs::write(path_str, "id,x,y\na,1.0,0.0\nb,0.5,oops\n").unwrap();
+        assert_eq!(
+            VecDB::import_csv(path_str, true).err().unwrap(),
+            "Line 3: invalid component 'oops'"
+        );
 
-        db.delete("vec2").unwrap();
-        assert_eq!(db.ids.len(), 0);
-        assert_eq!(db.vectors.len(), 0);
+        std::fs::write(path_str, "a,1.0,0.0\nb,1.0,0.0\nc,1.0\n").unwrap();
+        assert_eq!(
+            VecDB::import_csv(path_str, false).err().unwrap(),
+            "Line 3: expected 2 components, got 1"
+        );
     }
 
     #[test]
-    fn test_insert_after_delete() {
+    fn test_export_csv_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("vectors.csv");
+        let path_str = path.to_str().unwrap();
+
         let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 2.0]).unwrap();
-        db.delete("vec1").unwrap();
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![0.0, 2.0]).unwrap();
+        db.export_csv(path_str).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path_str).unwrap(),
+            "vec1,0.6,0.8\nvec2,0,1\n"
+        );
 
-        // Should be able to insert again with same ID
-        let result = db.insert("vec1".to_string(), vec![3.0, 4.0]);
-        assert!(result.is_ok());
+        let imported = VecDB::import_csv(path_str, false).unwrap();
+        assert_eq!(imported.list(), db.list());
 
-        let vec = db.get("vec1").unwrap();
-        // Normalized [3,4] = [0.6, 0.8]
-        assert!((vec[0] - 0.6).abs() < 1e-5);
+        db.insert("a,b".to_string(), vec![1.0, 0.0]).unwrap();
+        assert_eq!(
+            db.export_csv(path_str).unwrap_err(),
+            "ID 'a,b' can't be written to CSV"
+        );
     }
 
-    // ========== Save/Load Tests ==========
+    /// Encodes vectors in the `.fvecs` layout.
+    fn fvecs(vectors: &[&[f32]]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for vector in vectors {
+            bytes.extend((vector.len() as i32).to_le_bytes());
+            for x in *vector {
+                bytes.extend(x.to_le_bytes());
+            }
+        }
+        bytes
+    }
 
     #[test]
-    fn test_save_and_load() {
+    fn test_from_fvecs() {
         let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("test.db");
+        let path = dir.path().join("tiny.fvecs");
         let path_str = path.to_str().unwrap();
+        std::fs::write(path_str, fvecs(&[&[3.0, 4.0], &[0.0, 2.0], &[1.0, 0.0]])).unwrap();
 
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
-        db.insert("vec2".to_string(), vec![0.0, 1.0, 0.0]).unwrap();
-        db.insert("vec3".to_string(), vec![0.0, 0.0, 1.0]).unwrap();
+        let db = VecDB::from_fvecs(path_str, "sift").unwrap();
+        assert_eq!(db.count(), 3);
+        assert_eq!(db.ids, vec!["sift_0", "sift_1", "sift_2"]);
+        assert_eq!(db.get("sift_0").unwrap(), vec![0.6, 0.8]);
+        assert_eq!(db.get("sift_1").unwrap(), vec![0.0, 1.0]);
+    }
 
-        db.save(path_str).unwrap();
+    #[test]
+    fn test_from_fvecs_truncated() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tiny.fvecs");
+        let path_str = path.to_str().unwrap();
+        let bytes = fvecs(&[&[3.0, 4.0], &[0.0, 2.0]]);
 
-        let loaded = VecDB::load(path_str).unwrap();
-        assert_eq!(loaded.count(), 3);
-        assert_eq!(loaded.dimension, Some(3));
+        // Cut inside the second vector's components, then inside its dimension
+        for cut in [bytes.len() - 2, 14] {
+            std::fs::write(path_str, &bytes[..cut]).unwrap();
+            assert_eq!(
+                VecDB::from_fvecs(path_str, "sift").err().unwrap(),
+                "Truncated fvecs file at vector 1"
+            );
+        }
 
-        // Verify vectors are preserved
-        let v1 = loaded.get("vec1").unwrap();
-        assert!((v1[0] - 1.0).abs() < 1e-5);
-        assert!((v1[1] - 0.0).abs() < 1e-5);
+        std::fs::write(path_str, fvecs(&[&[1.0, 0.0], &[1.0, 0.0, 0.0]])).unwrap();
+        assert!(VecDB::from_fvecs(path_str, "sift").is_err());
+    }
 
-        let v2 = loaded.get("vec2").unwrap();
-        assert!((v2[1] - 1.0).abs() < 1e-5);
+    #[test]
+    fn test_save_to_load_from_memory() {
+        let mut db = VecDB::new().with_metric(Metric::Euclidean);
+        db.insert("vec1".to_string(), vec![3.0, 4.0]).unwrap();
+        db.insert("vec2".to_string(), vec![1.0, 0.0]).unwrap();
 
-        let v3 = loaded.get("vec3").unwrap();
-        assert!((v3[2] - 1.0).abs() < 1e-5);
+        let mut buffer = Vec::new();
+        db.save_to(&mut buffer).unwrap();
+        assert_eq!(&buffer[..4], b"KVDB");
+
+        let loaded = VecDB::load_from(buffer.as_slice()).unwrap();
+        assert_eq!(loaded.ids, db.ids);
+        assert_eq!(loaded.vectors, db.vectors);
+        assert_eq!(loaded.config(), db.config());
+        assert_eq!(loaded.get("vec2").unwrap(), vec![1.0, 0.0]);
+
+        // The path-based save writes the same bytes
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        db.save(path.to_str().unwrap()).unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), buffer);
     }
 
     #[test]
-    fn test_save_and_load_empty_db() {
+    fn test_save_is_complete_on_return() {
         let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("empty.db");
+        let path = dir.path().join("test.db");
         let path_str = path.to_str().unwrap();
 
-        let db = VecDB::new();
-        db.save(path_str).unwrap();
+        let mut db = VecDB::new();
+        for i in 0..1000 {
+            db.insert(format!("vec{}", i), vec![i as f32, 1.0, 2.0])
+                .unwrap();
+        }
+        let mut expected = Vec::new();
+        db.save_to(&mut expected).unwrap();
 
-        let loaded = VecDB::load(path_str).unwrap();
-        assert_eq!(loaded.count(), 0);
-        assert_eq!(loaded.dimension, None);
-    }
+        db.save(path_str).unwrap();
+        let len = std::fs::metadata(path_str).unwrap().len();
+        assert_eq!(len, expected.len() as u64);
 
-    #[test]
-    fn test_load_nonexistent_file() {
-        match VecDB::load("nonexistent_file.db") {
-            Err(e) => assert!(e.contains("File not found")),
-            Ok(_) => panic!("Expected error for nonexistent file"),
+        // Appended records are complete on return too
+        for i in 1000..1100 {
+            db.insert(format!("vec{}", i), vec![i as f32, 1.0, 2.0])
+                .unwrap();
         }
+        db.save_append(path_str).unwrap();
+        assert_eq!(std::fs::metadata(path_str).unwrap().len(), len);
+        assert_eq!(wal::read_all(path_str).unwrap().len(), 100);
+        append_to_file(path_str, "extra".to_string(), vec![1.0, 0.0, 0.0]).unwrap();
+        assert_eq!(wal::read_all(path_str).unwrap().len(), 101);
+        assert_eq!(VecDB::load(path_str).unwrap().count(), 1101);
     }
 
     #[test]
-    fn test_save_load_preserves_search() {
+    fn test_load_headerless_legacy_file() {
         let dir = tempfile::tempdir().unwrap();
-        let path = dir.path().join("search.db");
+        let path = dir.path().join("legacy.db");
         let path_str = path.to_str().unwrap();
 
-        let mut db = VecDB::new();
-        db.insert("vec1".to_string(), vec![1.0, 0.0]).unwrap();
-        db.insert("vec2".to_string(), vec![0.0, 1.0]).unwrap();
-        db.insert("vec3".to_string(), vec![0.7, 0.7]).unwrap();
+        // kvdb 0.3 wrote `ids, vectors, dimension` with no header; 123 IDs make
+        // the leading count byte 0x7b, i.e. '{', and 0x8b1f IDs the gzip magic
+        for count in [2usize, 123, 0x8b1f] {
+            let ids: Vec<String> = (0..count).map(|i| format!("vec{}", i)).collect();
+            let vectors: Vec<f32> = (0..count).flat_map(|_| [0.6, 0.8]).collect();
+            let bytes = bincode::serialize(&(&ids, &vectors, Some(2usize))).unwrap();
+            assert_eq!(bytes[0] == b'{', count == 123);
+            assert_eq!(bytes.starts_with(&[0x1f, 0x8b]), count == 0x8b1f);
+            std::fs::write(path_str, &bytes).unwrap();
+            append_to_file(path_str, "extra".to_string(), vec![1.0, 0.0]).unwrap();
 
-        db.save(path_str).unwrap();
-        let loaded = VecDB::load(path_str).unwrap();
+            let db = VecDB::load(path_str).unwrap();
+            assert_eq!(db.count(), count + 1);
+            assert_eq!(db.config(), DbConfig::default());
+            assert_eq!(db.get("vec1").unwrap(), vec![0.6, 0.8]);
+            assert!(db.verify().is_ok());
 
-        // Search on loaded db should return same results
-        let results = loaded.search(vec![1.0, 0.0], 2).unwrap();
-        assert_eq!(results.len(), 2);
-        assert_eq!(results[0].0, "vec1");
-        assert!((results[0].2 - 1.0).abs() < 1e-5);
+            // Replaying the WAL rewrites the file in the current bincode format
+            assert_eq!(&std::fs::read(path_str).unwrap()[..4], b"KVDB");
+        }
+
+        // IDs and values that don't line up are not a kvdb file
+        let bytes =
+            bincode::serialize(&(vec!["a".to_string()], vec![1.0f32], Some(2usize))).unwrap();
+        std::fs::write(path_str, &bytes).unwrap();
+        assert_eq!(
+            VecDB::load(path_str).err().unwrap(),
+            "Not a kvdb file: missing KVDB header"
+        );
     }
 
     #[test]
@@ -786,4 +7715,25 @@ mod db_test {
         assert!(loaded.get("new1").is_some());
         assert!(loaded.get("new2").is_some());
     }
+
+    #[test]
+    fn test_save_overwrite_discards_stale_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("overwrite.db");
+        let path_str = path.to_str().unwrap();
+
+        let mut db = VecDB::new();
+        db.insert("old".to_string(), vec![1.0, 0.0]).unwrap();
+        db.save(path_str).unwrap();
+        append_to_file(path_str, "ghost".to_string(), vec![0.0, 1.0]).unwrap();
+
+        // A fresh database never saw the WAL, but saving over the file replaces it
+        let mut fresh = VecDB::new();
+        fresh.insert("new".to_string(), vec![1.0, 1.0]).unwrap();
+        fresh.save(path_str).unwrap();
+
+        let loaded = VecDB::load(path_str).unwrap();
+        assert_eq!(loaded.ids(), ["new".to_string()]);
+        assert!(loaded.get("ghost").is_none());
+    }
 }
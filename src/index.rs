@@ -0,0 +1,478 @@
+//! Approximate nearest neighbor indexes
+//! A Hierarchical Navigable Small World (HNSW) graph and an inverted-file
+//! (IVF) index, both over storage positions
+
+use serde::{Deserialize, Serialize};
+use std::{
+    borrow::Cow,
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+};
+
+/// Approximate index used by [`VecDB::search`](crate::VecDB::search).
+///
+/// Enable it with [`VecDB::with_index`](crate::VecDB::with_index). Without an
+/// index, search is brute force and exact.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexKind {
+    /// Hierarchical Navigable Small World graph
+    Hnsw {
+        /// Links kept per node on upper layers; layer 0 keeps `2 * m`
+        m: usize,
+        /// Candidate list size while inserting; higher builds a better graph, slower
+        ef_construction: usize,
+        /// Candidate list size while searching, raised to `top_k` if smaller
+        ef_search: usize,
+    },
+    /// Inverted file: vectors are grouped around k-means centroids and a
+    /// search only scans the groups closest to the query
+    Ivf {
+        /// Number of centroids (groups)
+        nlist: usize,
+        /// Number of groups scanned per search; higher is slower but finds more
+        nprobe: usize,
+    },
+}
+
+impl Default for IndexKind {
+    fn default() -> Self {
+        IndexKind::Hnsw {
+            m: 16,
+            ef_construction: 200,
+            ef_search: 64,
+        }
+    }
+}
+
+/// A node at some distance from the current target, ordered by distance
+/// (ties broken by node).
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Near {
+    dist: f32,
+    node: usize,
+}
+
+impl Eq for Near {}
+
+impl PartialOrd for Near {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Near {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist
+            .total_cmp(&other.dist)
+            .then(self.node.cmp(&other.node))
+    }
+}
+
+/// HNSW graph whose nodes are storage positions `0..len()`.
+///
+/// The graph holds no vectors: callers pass distance functions where lower
+/// means closer. Nodes can only be appended; anything that moves or changes
+/// a stored vector requires building a new graph.
+#[derive(Debug, Clone)]
+pub(crate) struct Hnsw {
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+    /// `links[node][layer]` lists the node's neighbors on that layer
+    links: Vec<Vec<Vec<usize>>>,
+    /// Node on the highest layer, where every search starts
+    entry: Option<usize>,
+    /// Xorshift state for drawing node levels, seeded so builds are repeatable
+    rng: u64,
+}
+
+impl Hnsw {
+    pub(crate) fn new(m: usize, ef_construction: usize, ef_search: usize) -> Self {
+        Hnsw {
+            m: m.max(2),
+            ef_construction: ef_construction.max(1),
+            ef_search: ef_search.max(1),
+            links: Vec::new(),
+            entry: None,
+            rng: 0x9E37_79B9_7F4A_7C15,
+        }
+    }
+
+    /// Returns the number of nodes in the graph.
+    pub(crate) fn len(&self) -> usize {
+        self.links.len()
+    }
+
+    /// Appends node `len()` and links it into the graph.
+    ///
+    /// `dist(a, b)` is the distance between the stored vectors at positions
+    /// `a` and `b`; both are at most the new node.
+    pub(crate) fn insert(&mut self, dist: impl Fn(usize, usize) -> f32) {
+        let node = self.links.len();
+        let level = self.random_level();
+        self.links.push(vec![Vec::new(); level + 1]);
+
+        let Some(entry) = self.entry else {
+            self.entry = Some(node);
+            return;
+        };
+
+        let top = self.links[entry].len() - 1;
+        let mut to_node = |other: usize| dist(node, other);
+        let mut nearest = Near {
+            dist: to_node(entry),
+            node: entry,
+        };
+        for layer in (level + 1..=top).rev() {
+            nearest = self.greedy(nearest, layer, &mut to_node);
+        }
+
+        for layer in (0..=level.min(top)).rev() {
+            let found = self.search_layer(nearest, self.ef_construction, layer, &mut to_node);
+            nearest = found[0];
+
+            let max_links = self.max_links(layer);
+            let neighbors = select_neighbors(&found, max_links, &dist);
+            for &neighbor in &neighbors {
+                let links = &mut self.links[neighbor][layer];
+                links.push(node);
+                if links.len() > max_links {
+                    let mut scored: Vec<Near> = links
+                        .iter()
+                        .map(|&other| Near {
+                            dist: dist(neighbor, other),
+                            node: other,
+                        })
+                        .collect();
+                    scored.sort();
+                    *links = select_neighbors(&scored, max_links, &dist);
+                }
+            }
+            self.links[node][layer] = neighbors;
+        }
+
+        if level > top {
+            self.entry = Some(node);
+        }
+    }
+
+    /// Returns up to `top_k` `(node, distance)` pairs closest to a query,
+    /// closest first.
+    ///
+    /// `dist(node)` is the distance from the query to the vector at `node`.
+    pub(crate) fn search(
+        &self,
+        top_k: usize,
+        mut dist: impl FnMut(usize) -> f32,
+    ) -> Vec<(usize, f32)> {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+
+        let mut nearest = Near {
+            dist: dist(entry),
+            node: entry,
+        };
+        for layer in (1..self.links[entry].len()).rev() {
+            nearest = self.greedy(nearest, layer, &mut dist);
+        }
+
+        self.search_layer(nearest, self.ef_search.max(top_k), 0, &mut dist)
+            .into_iter()
+            .take(top_k)
+            .map(|n| (n.node, n.dist))
+            .collect()
+    }
+
+    /// Walks `layer` from `start`, moving to a closer neighbor until none is.
+    fn greedy(&self, start: Near, layer: usize, dist: &mut impl FnMut(usize) -> f32) -> Near {
+        let mut current = start;
+        loop {
+            let mut moved = false;
+            for &neighbor in &self.links[current.node][layer] {
+                let candidate = Near {
+                    dist: dist(neighbor),
+                    node: neighbor,
+                };
+                if candidate < current {
+                    current = candidate;
+                    moved = true;
+                }
+            }
+            if !moved {
+                return current;
+            }
+        }
+    }
+
+    /// Beam search over `layer` keeping the `ef` closest nodes found.
+    ///
+    /// Returns them closest first.
+    fn search_layer(
+        &self,
+        start: Near,
+        ef: usize,
+        layer: usize,
+        dist: &mut impl FnMut(usize) -> f32,
+    ) -> Vec<Near> {
+        let mut visited = vec![false; self.links.len()];
+        visited[start.node] = true;
+        // Closest unexpanded node first
+        let mut candidates = BinaryHeap::from([Reverse(start)]);
+        // Farthest kept node on top, the one to evict
+        let mut found = BinaryHeap::from([start]);
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            if found.len() >= ef && current > *found.peek().unwrap() {
+                break;
+            }
+            for &neighbor in &self.links[current.node][layer] {
+                if visited[neighbor] {
+                    continue;
+                }
+                visited[neighbor] = true;
+
+                let candidate = Near {
+                    dist: dist(neighbor),
+                    node: neighbor,
+                };
+                if found.len() < ef || candidate < *found.peek().unwrap() {
+                    candidates.push(Reverse(candidate));
+                    found.push(candidate);
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        found.into_sorted_vec()
+    }
+
+    /// Maximum number of links a node keeps on `layer`.
+    fn max_links(&self, layer: usize) -> usize {
+        if layer == 0 { 2 * self.m } else { self.m }
+    }
+
+    /// Draws a node level from an exponential distribution with scale 1/ln(m),
+    /// so each layer holds about 1/m of the nodes of the layer below.
+    fn random_level(&mut self) -> usize {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        // Uniform in (0, 1], so the logarithm is finite
+        let uniform = ((self.rng >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (self.m as f64).ln()) as usize
+    }
+}
+
+/// Number of k-means iterations when training an [`Ivf`] index.
+const KMEANS_ITERATIONS: usize = 10;
+
+/// Inverted-file index: k-means centroids and the centroid each stored
+/// vector is assigned to.
+///
+/// Assignments follow storage positions, so every storage change has a
+/// matching method here. Vectors are grouped by squared Euclidean distance.
+/// Until trained, it has no centroids and assigns nothing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct Ivf {
+    nlist: usize,
+    /// `nlist` centroids, flattened; empty until trained
+    centroids: Vec<f32>,
+    /// Centroid of the vector at each storage position
+    assignments: Vec<usize>,
+}
+
+impl Ivf {
+    pub(crate) fn new(nlist: usize) -> Self {
+        Ivf {
+            nlist: nlist.max(1),
+            centroids: Vec::new(),
+            assignments: Vec::new(),
+        }
+    }
+
+    pub(crate) fn nlist(&self) -> usize {
+        self.nlist
+    }
+
+    pub(crate) fn is_trained(&self) -> bool {
+        !self.centroids.is_empty()
+    }
+
+    /// Returns true if every one of `count` stored vectors has an assignment.
+    pub(crate) fn covers(&self, count: usize) -> bool {
+        !self.is_trained() || self.assignments.len() == count
+    }
+
+    /// Runs k-means over the `count` vectors returned by `vector` and assigns
+    /// each of them to its centroid.
+    ///
+    /// Centroids start at evenly spaced vectors, so training is repeatable.
+    /// Requires at least `nlist` vectors.
+    pub(crate) fn train<'a>(&mut self, count: usize, vector: impl Fn(usize) -> Cow<'a, [f32]>) {
+        let dim = vector(0).len();
+        self.centroids = (0..self.nlist)
+            .flat_map(|c| vector(c * count / self.nlist).into_owned())
+            .collect();
+
+        for _ in 0..KMEANS_ITERATIONS {
+            self.reassign(count, &vector);
+            let mut sums = vec![0.0f32; self.nlist * dim];
+            let mut sizes = vec![0usize; self.nlist];
+            for (i, &c) in self.assignments.iter().enumerate() {
+                sizes[c] += 1;
+                for (sum, x) in sums[c * dim..(c + 1) * dim]
+                    .iter_mut()
+                    .zip(vector(i).iter())
+                {
+                    *sum += x;
+                }
+            }
+            // An empty group keeps its previous centroid
+            for (c, &size) in sizes.iter().enumerate().filter(|(_, size)| **size > 0) {
+                for (centroid, sum) in self.centroids[c * dim..(c + 1) * dim]
+                    .iter_mut()
+                    .zip(&sums[c * dim..(c + 1) * dim])
+                {
+                    *centroid = sum / size as f32;
+                }
+            }
+        }
+        self.reassign(count, &vector);
+    }
+
+    /// Assigns each of the `count` vectors to its nearest centroid, from scratch.
+    pub(crate) fn reassign<'a>(&mut self, count: usize, vector: impl Fn(usize) -> Cow<'a, [f32]>) {
+        if self.is_trained() {
+            self.assignments = (0..count).map(|i| self.nearest(&vector(i))).collect();
+        }
+    }
+
+    /// Assigns a vector appended to the storage.
+    ///
+    /// A vector of another dimension than the centroids (the database was
+    /// emptied and refilled) discards the training.
+    pub(crate) fn push(&mut self, vector: &[f32]) {
+        if !self.is_trained() {
+            return;
+        }
+        if vector.len() * self.nlist != self.centroids.len() {
+            self.centroids.clear();
+            self.assignments.clear();
+            return;
+        }
+        let centroid = self.nearest(vector);
+        self.assignments.push(centroid);
+    }
+
+    /// Reassigns the vector at `index` after it changed.
+    pub(crate) fn set(&mut self, index: usize, vector: &[f32]) {
+        if self.is_trained() {
+            self.assignments[index] = self.nearest(vector);
+        }
+    }
+
+    /// Drops the assignment at `index`, shifting the ones after it.
+    pub(crate) fn remove(&mut self, index: usize) {
+        if self.is_trained() {
+            self.assignments.remove(index);
+        }
+    }
+
+    /// Drops the assignment at `index`, moving the last one into its place.
+    pub(crate) fn swap_remove(&mut self, index: usize) {
+        if self.is_trained() {
+            self.assignments.swap_remove(index);
+        }
+    }
+
+    /// Keeps only the assignments whose `keep` flag is set, in order.
+    pub(crate) fn retain(&mut self, keep: &[bool]) {
+        if self.is_trained() {
+            let mut flags = keep.iter();
+            self.assignments.retain(|_| *flags.next().unwrap());
+        }
+    }
+
+    /// Keeps only the first `count` assignments.
+    pub(crate) fn truncate(&mut self, count: usize) {
+        self.assignments.truncate(count);
+    }
+
+    /// Returns the positions of the vectors in the `nprobe` groups whose
+    /// centroids are closest to `query`, in storage order.
+    pub(crate) fn probe(&self, query: &[f32], nprobe: usize) -> impl Iterator<Item = usize> {
+        let mut order: Vec<Near> = self
+            .centroids
+            .chunks(query.len())
+            .enumerate()
+            .map(|(c, centroid)| Near {
+                dist: squared_distance(centroid, query),
+                node: c,
+            })
+            .collect();
+        order.sort();
+
+        let mut probed = vec![false; self.nlist];
+        for near in order.iter().take(nprobe) {
+            probed[near.node] = true;
+        }
+        self.assignments
+            .iter()
+            .enumerate()
+            .filter(move |(_, c)| probed[**c])
+            .map(|(index, _)| index)
+    }
+
+    /// Returns the centroid closest to `vector`.
+    fn nearest(&self, vector: &[f32]) -> usize {
+        self.centroids
+            .chunks(vector.len())
+            .enumerate()
+            .map(|(c, centroid)| Near {
+                dist: squared_distance(centroid, vector),
+                node: c,
+            })
+            .min()
+            .unwrap()
+            .node
+    }
+}
+
+/// Squared Euclidean distance, enough to compare distances.
+fn squared_distance(left: &[f32], right: &[f32]) -> f32 {
+    left.iter().zip(right).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Picks up to `max` links among `candidates`, sorted closest first.
+///
+/// A candidate is skipped while it is closer to an already picked neighbor
+/// than to the base node, so links spread in different directions and
+/// outlying nodes stay reachable. Skipped candidates fill any remaining slots.
+fn select_neighbors(
+    candidates: &[Near],
+    max: usize,
+    dist: &impl Fn(usize, usize) -> f32,
+) -> Vec<usize> {
+    let mut picked: Vec<usize> = Vec::with_capacity(max);
+    let mut skipped = Vec::new();
+    for candidate in candidates {
+        if picked.len() == max {
+            break;
+        }
+        if picked
+            .iter()
+            .all(|&other| dist(candidate.node, other) > candidate.dist)
+        {
+            picked.push(candidate.node);
+        } else {
+            skipped.push(candidate.node);
+        }
+    }
+
+    let missing = max - picked.len();
+    picked.extend(skipped.into_iter().take(missing));
+    picked
+}
@@ -3,6 +3,7 @@
 //! KVDB is a learning project implementing a simple in-memory vector database.
 //! Vectors are automatically L2-normalized on insertion and searched using
 //! dot product similarity (equivalent to cosine similarity for normalized vectors).
+//! Other metrics (dot product, Euclidean, Manhattan) can be chosen per database.
 //!
 //! ## Example
 //!
@@ -18,12 +19,21 @@
 //!
 //! // Search for similar vectors
 //! let results = db.search(vec![1.0, 0.0, 0.0], 2).unwrap();
-//! assert_eq!(results[0].0, "vec1"); // Most similar vector
+//! assert_eq!(results[0].id, "vec1"); // Most similar vector
 //! ```
 
+pub mod bench;
 mod db;
+mod index;
 pub mod server;
 pub mod vector;
+pub mod wal;
 
 // Re-export VecDB as the primary public API
-pub use db::VecDB;
+pub use db::{
+    DbConfig, DbSnapshot, DbStats, FORMAT_VERSION, Format, IdPolicy, Iter, Metadata, Precision,
+    Quantization, RepairReport, SearchMetrics, SearchResult, VecDB, append_to_file, id_hash,
+    search_multi,
+};
+pub use index::IndexKind;
+pub use vector::{Metric, prepare_flat};
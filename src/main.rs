@@ -2,6 +2,7 @@ mod cli;
 
 use actix_web::{App, HttpServer};
 use kvdb::VecDB;
+use kvdb::server::{CorsConfig, ServerOptions};
 
 #[actix_web::main]
 async fn main() -> Result<(), std::io::Error> {
@@ -9,14 +10,29 @@ async fn main() -> Result<(), std::io::Error> {
 
     if args.len() == 1 {
         let mut db = VecDB::new();
-        cli::run_repl(&mut db);
+        cli::run_repl(&mut db, &cli::CommandRegistry::new());
     } else if args[1] == "serve" {
-        HttpServer::new(|| App::new().configure(kvdb::server::config))
-            .bind("0.0.0.0:7878")?
-            .run()
-            .await?;
+        // --cors allows cross-origin requests from any origin, and
+        // KVDB_API_KEY, if set, is required on every request
+        let mut options = ServerOptions::new();
+        if args[2..].iter().any(|arg| arg == "--cors") {
+            options = options.with_cors(CorsConfig::new());
+        }
+        // A blank KVDB_API_KEY counts as unset rather than as an empty key
+        match std::env::var("KVDB_API_KEY") {
+            Ok(key) if !key.trim().is_empty() => options = options.with_api_key(&key),
+            Ok(_) => eprintln!("Warning: KVDB_API_KEY is blank, ignoring it"),
+            Err(_) => {}
+        }
+        HttpServer::new(move || {
+            let options = options.clone();
+            App::new().configure(move |cfg| kvdb::server::config_with(cfg, &options))
+        })
+        .bind("0.0.0.0:7878")?
+        .run()
+        .await?;
     } else {
-        cli::run_single_command();
+        cli::run_single_command(&cli::CommandRegistry::new());
     }
 
     Ok(())
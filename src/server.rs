@@ -1,15 +1,24 @@
 //! REST API for kvdb.
 //!
-//! Provides a stateless HTTP server with JSON endpoints for vector operations.
+//! Provides an HTTP server with JSON endpoints for vector operations.
 //! Each request includes a `db` field specifying the database file path.
-//! The server loads the database from disk per request and saves after mutations.
+//! The server loads each database from disk on first use, keeps it cached in
+//! memory, and saves it after mutations.
 //!
 //! ## Endpoints
 //!
 //! - `POST /insert` - Insert or update vectors
 //! - `POST /search` - Search for similar vectors
+//! - `POST /search_stream` - Search one query, streaming matches as NDJSON
 //! - `POST /get` - Retrieve vectors by ID
 //! - `POST /delete` - Delete vectors by ID
+//! - `POST /rename` - Rename a vector ID
+//! - `POST /similarity` - Cosine similarity between two vectors (no db needed)
+//! - `POST /score_pairs` - Score explicit (query, id) pairs
+//!
+//! Browser clients need CORS headers; use [`config_with_cors`] with a
+//! [`CorsConfig`] instead of [`config`] for that. [`config_with`] also
+//! supports requiring an API key (see [`ServerOptions`]).
 //!
 //! ## Usage
 //!
@@ -25,10 +34,21 @@
 //! }
 //! ```
 
-use crate::VecDB;
-use actix_web::{HttpResponse, Responder, web};
+use crate::vector::cosine_similarity;
+use crate::{VecDB, id_hash};
+use actix_cors::Cors;
+use actix_web::body::BoxBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::header;
+use actix_web::middleware::{Next, from_fn};
+use actix_web::{HttpRequest, HttpResponse, Responder, web};
+use futures_util::stream;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::Path;
+use std::rc::Rc;
+use std::sync::{Arc, LazyLock, RwLock};
 
 // --- Request structs ---
 
@@ -41,19 +61,43 @@ struct VectorEntry {
 #[derive(Deserialize)]
 struct Query {
     value: Vec<f32>,
-    top_k: usize,
+    /// Maximum number of matches; optional when `min_score` is given
+    #[serde(default)]
+    top_k: Option<usize>,
+    /// Return every match scoring at least this much
+    #[serde(default)]
+    min_score: Option<f32>,
+    /// Include the stored vector only for this many of the best matches
+    #[serde(default)]
+    values_for_top: Option<usize>,
 }
 
 #[derive(Deserialize)]
 struct InsertRequest {
     db: String,
     vectors: Vec<VectorEntry>,
+    /// Reject the whole batch if any vector's dimension differs from the rest
+    #[serde(default)]
+    strict_batch: bool,
 }
 
 #[derive(Deserialize)]
 struct SearchRequest {
     db: String,
     queries: Vec<Query>,
+    /// Include the stable 64-bit hash of each matched ID
+    #[serde(default)]
+    with_hash: bool,
+    /// Leave the stored vectors out of the matches
+    #[serde(default)]
+    ids_only: bool,
+}
+
+#[derive(Deserialize)]
+struct SearchStreamRequest {
+    db: String,
+    value: Vec<f32>,
+    top_k: usize,
 }
 
 #[derive(Deserialize)]
@@ -68,6 +112,31 @@ struct DeleteRequest {
     ids: Vec<String>,
 }
 
+#[derive(Deserialize)]
+struct RenameRequest {
+    db: String,
+    old: String,
+    new: String,
+}
+
+#[derive(Deserialize)]
+struct SimilarityRequest {
+    a: Vec<f32>,
+    b: Vec<f32>,
+}
+
+#[derive(Deserialize)]
+struct ScorePair {
+    query: Vec<f32>,
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct ScorePairsRequest {
+    db: String,
+    pairs: Vec<ScorePair>,
+}
+
 // --- Response structs ---
 
 #[derive(Serialize)]
@@ -97,8 +166,11 @@ struct SearchResultGroup {
 #[derive(Serialize)]
 struct MatchResult {
     id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hash: Option<u64>,
     score: f32,
-    values: Vec<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    values: Option<Vec<f32>>,
 }
 
 #[derive(Serialize)]
@@ -125,6 +197,32 @@ struct DeleteResult {
     message: String,
 }
 
+#[derive(Serialize)]
+struct RenameResponse {
+    old: String,
+    new: String,
+}
+
+#[derive(Serialize)]
+struct SimilarityResponse {
+    score: f32,
+}
+
+#[derive(Serialize)]
+struct ScorePairsResponse {
+    scores: Vec<Option<f32>>,
+}
+
+/// Databases loaded so far, keyed by file path.
+///
+/// Each database is read from disk on first access and kept in memory after
+/// that; mutating endpoints update the cached copy and still save it to disk.
+/// If that save fails, the copy is dropped so unsaved changes are never served.
+/// Every database has its own lock: searches and gets take it shared and run
+/// in parallel, inserts and deletes take it exclusively.
+static CACHE: LazyLock<RwLock<HashMap<String, Arc<RwLock<VecDB>>>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
 /// Helper function for load or create database
 fn load_or_create(path: &str) -> Result<VecDB, String> {
     if Path::new(path).exists() {
@@ -134,19 +232,102 @@ fn load_or_create(path: &str) -> Result<VecDB, String> {
     Ok(VecDB::new())
 }
 
+/// Returns the cached database at `path`, loading it on first access.
+///
+/// The cache is locked exclusively while a database is loaded, so two
+/// concurrent requests for the same path can't both load it and overwrite
+/// each other.
+fn cached_db(path: &str) -> Result<Arc<RwLock<VecDB>>, String> {
+    if let Some(db) = CACHE
+        .read()
+        .map_err(|_| "Database cache poisoned".to_string())?
+        .get(path)
+    {
+        return Ok(Arc::clone(db));
+    }
+
+    let mut cache = CACHE
+        .write()
+        .map_err(|_| "Database cache poisoned".to_string())?;
+    // Another request may have loaded it while we waited for the lock
+    if let Some(db) = cache.get(path) {
+        return Ok(Arc::clone(db));
+    }
+    let db = Arc::new(RwLock::new(load_or_create(path)?));
+    cache.insert(path.to_string(), Arc::clone(&db));
+    Ok(db)
+}
+
+/// Runs a read-only `f` on the cached database at `path`. Used by search,
+/// search_stream, get and score_pairs.
+fn read_db<R>(path: &str, f: impl FnOnce(&VecDB) -> R) -> Result<R, String> {
+    let db = cached_db(path)?;
+    let db = db
+        .read()
+        .map_err(|_| "Database lock poisoned".to_string())?;
+    Ok(f(&db))
+}
+
+/// Runs a mutating `f` on the cached database at `path`, with no reader
+/// running at the same time. Used by insert and delete.
+fn write_db<R>(path: &str, f: impl FnOnce(&mut VecDB) -> R) -> Result<R, String> {
+    let db = cached_db(path)?;
+    let mut db = db
+        .write()
+        .map_err(|_| "Database lock poisoned".to_string())?;
+    Ok(f(&mut db))
+}
+
+/// Saves the cached database at `path` after a change.
+///
+/// On failure the cached copy holds changes that never reached disk, so it is
+/// dropped from the cache and the next request reloads the file.
+fn save_cached(db: &VecDB, path: &str) -> Result<(), String> {
+    db.save(path).inspect_err(|_| {
+        if let Ok(mut cache) = CACHE.write() {
+            cache.remove(path);
+        }
+    })
+}
+
 // --- Handlers ---
 
 async fn insert_handler(body: web::Json<InsertRequest>) -> impl Responder {
-    let mut db = match load_or_create(&body.db) {
-        Ok(db) => db,
-        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
+    let entries: Vec<(String, Vec<f32>)> = body
+        .vectors
+        .iter()
+        .map(|entry| (entry.id.clone(), entry.values.clone()))
+        .collect();
+
+    let outcomes = write_db(&body.db, |db| {
+        let outcomes = if body.strict_batch {
+            db.insert_many_strict(entries)
+        } else {
+            Ok(db.insert_many(entries))
+        };
+        // A rejected strict batch changed nothing, so there is nothing to save
+        let saved = match outcomes {
+            Ok(_) => save_cached(db, &body.db),
+            Err(_) => Ok(()),
+        };
+        (outcomes, saved)
+    });
+
+    let outcomes = match outcomes {
+        Ok((Ok(outcomes), Ok(()))) => outcomes,
+        Ok((Err(e), _)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({"error": e}));
+        }
+        Ok((_, Err(e))) | Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({"error": e}));
+        }
     };
 
     let mut results = Vec::new();
     let mut inserted = 0;
 
-    for entry in &body.vectors {
-        match db.insert(entry.id.clone(), entry.values.clone()) {
+    for (entry, outcome) in body.vectors.iter().zip(outcomes) {
+        match outcome {
             Ok(msg) => {
                 inserted += 1;
                 results.push(InsertResult {
@@ -165,34 +346,72 @@ async fn insert_handler(body: web::Json<InsertRequest>) -> impl Responder {
         }
     }
 
-    if let Err(e) = db.save(&body.db) {
-        return HttpResponse::InternalServerError().json(serde_json::json!({"error": e}));
-    }
-
     HttpResponse::Ok().json(InsertResponse { inserted, results })
 }
 
 async fn search_handler(body: web::Json<SearchRequest>) -> impl Responder {
-    // load the db
-    let db = match load_or_create(&body.db) {
-        Ok(db) => db,
+    let to_match = |id: String, values: Option<Vec<f32>>, score: f32| MatchResult {
+        hash: body.with_hash.then(|| id_hash(&id)),
+        id,
+        score,
+        values,
+    };
+
+    let outcomes = read_db(&body.db, |db| {
+        body.queries
+            .iter()
+            .map(|entry| match (entry.min_score, entry.top_k) {
+                (Some(min_score), top_k) => db
+                    .search_threshold(entry.value.clone(), min_score)
+                    .map(|res| {
+                        res.into_iter()
+                            .take(top_k.unwrap_or(usize::MAX))
+                            .map(|result| {
+                                to_match(
+                                    result.id,
+                                    (!body.ids_only).then_some(result.vector),
+                                    result.score,
+                                )
+                            })
+                            .collect()
+                    }),
+                (None, Some(top_k)) if body.ids_only => {
+                    db.search_ids(entry.value.clone(), top_k).map(|res| {
+                        res.into_iter()
+                            .map(|(id, score)| to_match(id, None, score))
+                            .collect()
+                    })
+                }
+                (None, Some(top_k)) => db.search(entry.value.clone(), top_k).map(|res| {
+                    res.into_iter()
+                        .map(|result| to_match(result.id, Some(result.vector), result.score))
+                        .collect()
+                }),
+                (None, None) => Err("Query needs top_k or min_score".to_string()),
+            })
+            .zip(&body.queries)
+            .map(|(outcome, entry)| {
+                outcome.map(|mut matches: Vec<MatchResult>| {
+                    if let Some(n) = entry.values_for_top {
+                        matches.iter_mut().skip(n).for_each(|m| m.values = None);
+                    }
+                    matches
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+    let outcomes = match outcomes {
+        Ok(outcomes) => outcomes,
         Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
     };
 
     let mut results = Vec::new();
 
-    for entry in &body.queries {
-        match db.search(entry.value.clone(), entry.top_k) {
-            Ok(res) => {
+    for outcome in outcomes {
+        match outcome {
+            Ok(matches) => {
                 results.push(SearchResultGroup {
-                    matches: res
-                        .iter()
-                        .map(|(id, vec, score)| MatchResult {
-                            id: id.clone(),
-                            score: *score,
-                            values: vec.clone(),
-                        })
-                        .collect(),
+                    matches,
                     message: "Search Success".to_string(),
                 });
             }
@@ -209,34 +428,37 @@ async fn search_handler(body: web::Json<SearchRequest>) -> impl Responder {
 }
 
 async fn get_handler(body: web::Json<GetRequest>) -> impl Responder {
-    let db = match load_or_create(&body.db) {
-        Ok(db) => db,
-        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
-    };
-
-    let mut results = Vec::new();
-
-    for entry in &body.ids {
-        results.push(GetResult {
-            id: entry.clone(),
-            values: db.get(entry),
-        });
+    let results = read_db(&body.db, |db| {
+        body.ids
+            .iter()
+            .map(|entry| GetResult {
+                id: entry.clone(),
+                values: db.get(entry),
+            })
+            .collect()
+    });
+
+    match results {
+        Ok(results) => HttpResponse::Ok().json(GetResponse { results }),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
     }
-
-    HttpResponse::Ok().json(GetResponse { results })
 }
 
 async fn delete_handler(body: web::Json<DeleteRequest>) -> impl Responder {
-    let mut db = match load_or_create(&body.db) {
-        Ok(db) => db,
+    let outcomes = write_db(&body.db, |db| {
+        let outcomes = db.delete_many(&body.ids);
+        save_cached(db, &body.db).map(|_| outcomes)
+    });
+    let outcomes = match outcomes.and_then(|saved| saved) {
+        Ok(outcomes) => outcomes,
         Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
     };
 
     let mut results = Vec::new();
     let mut deleted = 0;
 
-    for entry in &body.ids {
-        match db.delete(entry) {
+    for (entry, outcome) in body.ids.iter().zip(outcomes) {
+        match outcome {
             Ok(msg) => {
                 results.push(DeleteResult {
                     id: entry.clone(),
@@ -256,16 +478,299 @@ async fn delete_handler(body: web::Json<DeleteRequest>) -> impl Responder {
         }
     }
 
-    if let Err(e) = db.save(&body.db) {
-        return HttpResponse::InternalServerError().json(serde_json::json!({"error": e}));
+    HttpResponse::Ok().json(DeleteResponse { results, deleted })
+}
+
+async fn rename_handler(body: web::Json<RenameRequest>) -> impl Responder {
+    let outcome = write_db(&body.db, |db| {
+        db.rename(&body.old, body.new.clone())
+            .map(|_| save_cached(db, &body.db))
+    });
+
+    match outcome {
+        Ok(Ok(Ok(()))) => HttpResponse::Ok().json(RenameResponse {
+            old: body.old.clone(),
+            new: body.new.clone(),
+        }),
+        // Missing old ID or colliding new ID; nothing changed
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+        Ok(Ok(Err(e))) | Err(e) => {
+            HttpResponse::InternalServerError().json(serde_json::json!({"error": e}))
+        }
     }
+}
 
-    HttpResponse::Ok().json(DeleteResponse { results, deleted })
+async fn similarity_handler(body: web::Json<SimilarityRequest>) -> impl Responder {
+    match cosine_similarity(&body.a, &body.b) {
+        Ok(score) => HttpResponse::Ok().json(SimilarityResponse { score }),
+        Err(e) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+    }
+}
+
+async fn score_pairs_handler(body: web::Json<ScorePairsRequest>) -> impl Responder {
+    let pairs: Vec<(Vec<f32>, String)> = body
+        .pairs
+        .iter()
+        .map(|pair| (pair.query.clone(), pair.id.clone()))
+        .collect();
+
+    match read_db(&body.db, |db| db.score_pairs(&pairs)) {
+        Ok(Ok(scores)) => HttpResponse::Ok().json(ScorePairsResponse { scores }),
+        Ok(Err(e)) => HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
+    }
+}
+
+/// Streams the matches of a single query as newline-delimited JSON, one
+/// match per line in ranked order, so clients can consume them incrementally.
+async fn search_stream_handler(body: web::Json<SearchStreamRequest>) -> impl Responder {
+    let results = match read_db(&body.db, |db| db.search(body.value.clone(), body.top_k)) {
+        Ok(Ok(results)) => results,
+        Ok(Err(e)) => return HttpResponse::BadRequest().json(serde_json::json!({"error": e})),
+        Err(e) => return HttpResponse::InternalServerError().json(serde_json::json!({"error": e})),
+    };
+
+    let lines = results.into_iter().map(|result| {
+        let mut line = serde_json::to_vec(&MatchResult {
+            id: result.id,
+            hash: None,
+            score: result.score,
+            values: Some(result.vector),
+        })?;
+        line.push(b'\n');
+        Ok::<_, serde_json::Error>(web::Bytes::from(line))
+    });
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(stream::iter(lines))
+}
+
+/// Cross-origin (CORS) settings for [`config_with_cors`].
+///
+/// The default is permissive, meant for local development: any origin,
+/// method and header is allowed. Listing origins, methods or headers
+/// restricts that part to exactly the listed values.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use actix_web::{App, HttpServer};
+/// use kvdb::server::CorsConfig;
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     let cors = CorsConfig::new()
+///         .allow_origin("https://app.example.com")
+///         .allow_methods(&["POST"]);
+///
+///     HttpServer::new(move || {
+///         let cors = cors.clone();
+///         App::new().configure(move |cfg| kvdb::server::config_with_cors(cfg, &cors))
+///     })
+///     .bind("0.0.0.0:7878")?
+///     .run()
+///     .await
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CorsConfig {
+    origins: Vec<String>,
+    methods: Vec<String>,
+    headers: Vec<String>,
+}
+
+impl CorsConfig {
+    /// Creates the permissive default configuration.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Allows requests from `origin`, e.g. `"https://app.example.com"`.
+    /// Once any origin is listed, all others are rejected.
+    pub fn allow_origin(mut self, origin: &str) -> Self {
+        self.origins.push(origin.to_string());
+        self
+    }
+
+    /// Restricts the allowed request methods, e.g. `&["POST"]`.
+    pub fn allow_methods(mut self, methods: &[&str]) -> Self {
+        self.methods.extend(methods.iter().map(|m| m.to_string()));
+        self
+    }
+
+    /// Restricts the allowed request headers, e.g. `&["content-type"]`.
+    pub fn allow_headers(mut self, headers: &[&str]) -> Self {
+        self.headers.extend(headers.iter().map(|h| h.to_string()));
+        self
+    }
+
+    /// Builds the actix middleware for these settings.
+    fn build(&self) -> Cors {
+        let mut cors = Cors::default().max_age(3600);
+
+        cors = if self.origins.is_empty() {
+            cors.allow_any_origin()
+        } else {
+            self.origins
+                .iter()
+                .fold(cors, |cors, origin| cors.allowed_origin(origin))
+        };
+        cors = if self.methods.is_empty() {
+            cors.allow_any_method()
+        } else {
+            cors.allowed_methods(self.methods.iter().map(String::as_str))
+        };
+        if self.headers.is_empty() {
+            cors.allow_any_header()
+        } else {
+            cors.allowed_headers(self.headers.iter().map(String::as_str))
+        }
+    }
+}
+
+/// Like [`config`], but every endpoint also answers CORS preflight requests
+/// and sends CORS headers as set by `cors`, so browsers can call the server
+/// directly.
+pub fn config_with_cors(cfg: &mut web::ServiceConfig, cors: &CorsConfig) {
+    config_with(cfg, &ServerOptions::new().with_cors(cors.clone()));
+}
+
+/// Optional server features for [`config_with`]; nothing is enabled by default.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use actix_web::{App, HttpServer};
+/// use kvdb::server::{CorsConfig, ServerOptions};
+///
+/// #[actix_web::main]
+/// async fn main() -> std::io::Result<()> {
+///     let options = ServerOptions::new()
+///         .with_cors(CorsConfig::new())
+///         .with_api_key("secret");
+///
+///     HttpServer::new(move || {
+///         let options = options.clone();
+///         App::new().configure(move |cfg| kvdb::server::config_with(cfg, &options))
+///     })
+///     .bind("0.0.0.0:7878")?
+///     .run()
+///     .await
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    cors: Option<CorsConfig>,
+    api_key: Option<String>,
+}
+
+impl ServerOptions {
+    /// Creates options with every feature disabled, same as [`config`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sends CORS headers as set by `cors` (see [`config_with_cors`]).
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(cors);
+        self
+    }
+
+    /// Requires every request to carry `key`, either as
+    /// `Authorization: Bearer <key>` or as `X-API-Key: <key>`. Other requests
+    /// are rejected with 401. CORS preflight requests don't need the key.
+    /// An empty key is never accepted, so `with_api_key("")` rejects everything.
+    pub fn with_api_key(mut self, key: &str) -> Self {
+        self.api_key = Some(key.to_string());
+        self
+    }
+}
+
+/// Like [`config`], with the optional features enabled in `options`.
+pub fn config_with(cfg: &mut web::ServiceConfig, options: &ServerOptions) {
+    let api_key = options.api_key.clone();
+    let endpoints = move |cfg: &mut web::ServiceConfig| match api_key {
+        Some(key) => config_with_api_key(cfg, key),
+        None => config(cfg),
+    };
+
+    match &options.cors {
+        // CORS goes outside so preflight requests are answered before authentication
+        Some(cors) => {
+            cfg.service(web::scope("").wrap(cors.build()).configure(endpoints));
+        }
+        None => endpoints(cfg),
+    }
+}
+
+/// Registers the endpoints behind a check for `key`.
+fn config_with_api_key(cfg: &mut web::ServiceConfig, key: String) {
+    let key = Rc::new(key);
+    cfg.service(
+        web::scope("")
+            .wrap(from_fn(move |req, next| {
+                require_api_key(Rc::clone(&key), req, next)
+            }))
+            .configure(config),
+    );
+}
+
+/// Passes the request on if it carries `key`, otherwise answers 401.
+async fn require_api_key(
+    key: Rc<String>,
+    req: ServiceRequest,
+    next: Next<BoxBody>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let headers = req.headers();
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let api_key = headers
+        .get("x-api-key")
+        .and_then(|value| value.to_str().ok());
+
+    if key_matches(bearer, &key) || key_matches(api_key, &key) {
+        return next.call(req).await;
+    }
+
+    let response = HttpResponse::Unauthorized()
+        .json(serde_json::json!({"error": "Missing or invalid API key"}));
+    Ok(req.into_response(response))
+}
+
+/// Checks a presented key against the expected one. Keys of the same length
+/// are compared in constant time, so response timing doesn't reveal how much
+/// of a guess was right. An empty key never matches.
+fn key_matches(given: Option<&str>, key: &str) -> bool {
+    match given {
+        Some(given) if !given.is_empty() && given.len() == key.len() => {
+            given
+                .bytes()
+                .zip(key.bytes())
+                .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+                == 0
+        }
+        _ => false,
+    }
+}
+
+/// Reports a request body that can't be parsed in the usual `{"error": ...}`
+/// shape, with status 400.
+fn json_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    let response = HttpResponse::BadRequest().json(serde_json::json!({"error": err.to_string()}));
+    InternalError::from_response(err, response).into()
 }
 
 pub fn config(cfg: &mut web::ServiceConfig) {
-    cfg.service(web::resource("/insert").route(web::post().to(insert_handler)))
+    cfg.app_data(web::JsonConfig::default().error_handler(json_error_handler))
+        .service(web::resource("/insert").route(web::post().to(insert_handler)))
         .service(web::resource("/search").route(web::post().to(search_handler)))
+        .service(web::resource("/search_stream").route(web::post().to(search_stream_handler)))
         .service(web::resource("/get").route(web::post().to(get_handler)))
-        .service(web::resource("/delete").route(web::post().to(delete_handler)));
+        .service(web::resource("/delete").route(web::post().to(delete_handler)))
+        .service(web::resource("/rename").route(web::post().to(rename_handler)))
+        .service(web::resource("/similarity").route(web::post().to(similarity_handler)))
+        .service(web::resource("/score_pairs").route(web::post().to(score_pairs_handler)));
 }
@@ -1,13 +1,29 @@
 //! This is the vector math module
-//! Provide L2 normalization and dot product
+//! Provide L2 normalization (single vectors, batches, flat arrays and `f64` vectors), dot product, cosine similarity, Euclidean distance,
+//! int8 and binary quantization, Hamming distance and the search metrics
+
+use serde::{Deserialize, Serialize};
+
+/// Finite Check
+/// Rejects NaN and infinite components, which would poison every score
+pub fn check_finite(vector: &[f32]) -> Result<(), String> {
+    match vector.iter().position(|x| !x.is_finite()) {
+        Some(index) => Err(format!(
+            "Vector contains non-finite value at index {}",
+            index
+        )),
+        None => Ok(()),
+    }
+}
 
 /// L2 Normalization
 /// norm_vec = vec / ||vec||
-/// Zero vector cannot be normalized
+/// Zero vector and non-finite values cannot be normalized
 pub fn l2_norm(vector: &[f32]) -> Result<Vec<f32>, String> {
     if vector.is_empty() {
         return Err("Cannot normalize an empty vector".to_string());
     }
+    check_finite(vector)?;
 
     let norm = vector.iter().map(|x| x * x).sum::<f32>().sqrt();
 
@@ -20,6 +36,75 @@ pub fn l2_norm(vector: &[f32]) -> Result<Vec<f32>, String> {
     Ok(normed_vec)
 }
 
+/// L2 Normalization in f64
+/// Same as `l2_norm`, for vectors kept in full `f64` precision
+pub fn l2_norm_f64(vector: &[f64]) -> Result<Vec<f64>, String> {
+    if vector.is_empty() {
+        return Err("Cannot normalize an empty vector".to_string());
+    }
+    check_finite_f64(vector)?;
+
+    let norm = vector.iter().map(|x| x * x).sum::<f64>().sqrt();
+
+    if norm == 0.0 {
+        return Err("Cannot normalize a zero vector".to_string());
+    }
+
+    Ok(vector.iter().map(|x| x / norm).collect())
+}
+
+/// Finite Check in f64
+/// Same as `check_finite`, for `f64` vectors
+pub(crate) fn check_finite_f64(vector: &[f64]) -> Result<(), String> {
+    match vector.iter().position(|x| !x.is_finite()) {
+        Some(index) => Err(format!(
+            "Vector contains non-finite value at index {}",
+            index
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Batch L2 Normalization
+/// Normalizes each vector on its own, so one bad vector does not fail the batch
+/// Results line up with the input, with the same errors as `l2_norm`
+pub fn l2_norm_batch(vectors: &[Vec<f32>]) -> Vec<Result<Vec<f32>, String>> {
+    vectors.iter().map(|vector| l2_norm(vector)).collect()
+}
+
+/// Flat Array Normalization
+/// Normalizes each `dimension`-sized chunk of a flat array in place
+/// Every chunk is checked first, so on error the values are left untouched
+/// and the message names the offending chunk
+pub fn prepare_flat(values: &mut [f32], dimension: usize) -> Result<(), String> {
+    if dimension == 0 {
+        return Err("Dimension must be at least 1".to_string());
+    }
+    if !values.len().is_multiple_of(dimension) {
+        return Err(format!(
+            "Flat array of {} values is not a multiple of dimension {}",
+            values.len(),
+            dimension
+        ));
+    }
+
+    let mut norms = Vec::with_capacity(values.len() / dimension);
+    for (index, chunk) in values.chunks(dimension).enumerate() {
+        check_finite(chunk).map_err(|e| format!("Chunk {}: {}", index, e))?;
+        let norm = chunk.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm == 0.0 {
+            return Err(format!("Chunk {}: Cannot normalize a zero vector", index));
+        }
+        norms.push(norm);
+    }
+
+    for (chunk, norm) in values.chunks_mut(dimension).zip(norms) {
+        chunk.iter_mut().for_each(|x| *x /= norm);
+    }
+
+    Ok(())
+}
+
 /// Dot Product
 /// dot_prod = sum(a'\[i\] * b\[i\]) for i = 0..a.len()
 /// Can only process vectors with same dimensions
@@ -28,9 +113,197 @@ pub fn dot_product(left: &[f32], right: &[f32]) -> Result<f32, String> {
         return Err("Different dimentions".to_string());
     }
 
-    let dot_prod = left.iter().zip(right.iter()).map(|(x, y)| x * y).sum();
+    if cfg!(feature = "simd") {
+        Ok(dot_lanes(left, right))
+    } else {
+        Ok(dot_scalar(left, right))
+    }
+}
+
+/// Scalar Dot Product Kernel
+/// One multiply-add per component, in order
+fn dot_scalar(left: &[f32], right: &[f32]) -> f32 {
+    left.iter().zip(right.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Lane Dot Product Kernel (`simd` feature)
+/// Keeps 8 independent partial sums so the compiler can use 8-wide SIMD
+/// registers on stable Rust, then adds the scalar remainder tail
+/// Summation order differs from the scalar kernel, so results may differ in
+/// the last bits
+fn dot_lanes(left: &[f32], right: &[f32]) -> f32 {
+    const LANES: usize = 8;
+
+    let left_chunks = left.chunks_exact(LANES);
+    let right_chunks = right.chunks_exact(LANES);
+    let tail = dot_scalar(left_chunks.remainder(), right_chunks.remainder());
+
+    let mut sums = [0.0f32; LANES];
+    for (l, r) in left_chunks.zip(right_chunks) {
+        for lane in 0..LANES {
+            sums[lane] += l[lane] * r[lane];
+        }
+    }
+
+    sums.iter().sum::<f32>() + tail
+}
+
+/// Cosine Similarity
+/// cos_sim = dot(a / ||a||, b / ||b||)
+/// Works on raw vectors; errors on different dimensions or zero vectors
+pub fn cosine_similarity(left: &[f32], right: &[f32]) -> Result<f32, String> {
+    if left.len() != right.len() {
+        return Err("Different dimentions".to_string());
+    }
 
-    Ok(dot_prod)
+    dot_product(&l2_norm(left)?, &l2_norm(right)?)
+}
+
+/// Euclidean Distance
+/// dist = sqrt(sum((a\[i\] - b\[i\])^2)) for i = 0..a.len()
+/// Can only process vectors with same dimensions
+pub fn euclidean_distance(left: &[f32], right: &[f32]) -> Result<f32, String> {
+    if left.len() != right.len() {
+        return Err("Different dimentions".to_string());
+    }
+
+    let sum_sq: f32 = left
+        .iter()
+        .zip(right.iter())
+        .map(|(x, y)| (x - y) * (x - y))
+        .sum();
+
+    Ok(sum_sq.sqrt())
+}
+
+/// Manhattan Distance
+/// dist = sum(|a\[i\] - b\[i\]|) for i = 0..a.len()
+/// Can only process vectors with same dimensions
+pub(crate) fn manhattan_distance(left: &[f32], right: &[f32]) -> Result<f32, String> {
+    if left.len() != right.len() {
+        return Err("Different dimentions".to_string());
+    }
+
+    Ok(left
+        .iter()
+        .zip(right.iter())
+        .map(|(x, y)| (x - y).abs())
+        .sum())
+}
+
+/// Int8 Scalar Quantization
+/// scale = max(|x\[i\]|) / 127, code\[i\] = round(x\[i\] / scale)
+/// Returns the codes and the scale; a zero vector gets scale 0
+pub fn quantize_int8(vector: &[f32]) -> (Vec<i8>, f32) {
+    let max_abs = vector.iter().fold(0.0f32, |max, x| max.max(x.abs()));
+    if max_abs == 0.0 {
+        return (vec![0; vector.len()], 0.0);
+    }
+
+    let scale = max_abs / 127.0;
+    let codes = vector.iter().map(|x| (x / scale).round() as i8).collect();
+
+    (codes, scale)
+}
+
+/// Int8 Dequantization
+/// x\[i\] = code\[i\] * scale
+pub fn dequantize_int8(codes: &[i8], scale: f32) -> Vec<f32> {
+    codes.iter().map(|&c| c as f32 * scale).collect()
+}
+
+/// Binary Quantization
+/// bit\[i\] = 1 if x\[i\] > 0, packed 64 components per `u64` word (component
+/// i is bit i % 64 of word i / 64); unused bits of the last word stay 0
+pub fn quantize_binary(vector: &[f32]) -> Vec<u64> {
+    vector
+        .chunks(64)
+        .map(|chunk| {
+            chunk
+                .iter()
+                .enumerate()
+                .filter(|(_, x)| **x > 0.0)
+                .fold(0u64, |word, (bit, _)| word | (1 << bit))
+        })
+        .collect()
+}
+
+/// Binary Dequantization
+/// x\[i\] = ±1 / sqrt(dim), i.e. the unit vector with the stored signs
+pub fn dequantize_binary(bits: &[u64], dim: usize) -> Vec<f32> {
+    let magnitude = 1.0 / (dim as f32).sqrt();
+    (0..dim)
+        .map(|i| {
+            if bits[i / 64] >> (i % 64) & 1 == 1 {
+                magnitude
+            } else {
+                -magnitude
+            }
+        })
+        .collect()
+}
+
+/// Hamming Distance
+/// hamming = sum(popcount(a\[i\] XOR b\[i\])), the number of differing bits
+/// Both slices are expected to hold the same number of words
+pub fn hamming_distance(left: &[u64], right: &[u64]) -> u32 {
+    left.iter()
+        .zip(right.iter())
+        .map(|(a, b)| (a ^ b).count_ones())
+        .sum()
+}
+
+/// Metric used by `VecDB` to score and rank vectors
+/// Cosine is the default; it is the only metric that normalizes stored vectors
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Metric {
+    /// Cosine similarity, higher is closer
+    #[default]
+    Cosine,
+    /// Raw dot product, higher is closer
+    DotProduct,
+    /// Euclidean (L2) distance, lower is closer
+    Euclidean,
+    /// Manhattan (L1) distance, lower is closer
+    Manhattan,
+}
+
+impl Metric {
+    /// Returns true for distance metrics, where lower scores rank first
+    pub fn is_distance(&self) -> bool {
+        matches!(self, Metric::Euclidean | Metric::Manhattan)
+    }
+
+    /// Returns true if vectors are L2-normalized before storing and scoring
+    pub fn normalizes(&self) -> bool {
+        matches!(self, Metric::Cosine)
+    }
+
+    /// Scores two vectors under this metric
+    /// Cosine expects both inputs to be normalized already
+    pub(crate) fn score(&self, left: &[f32], right: &[f32]) -> Result<f32, String> {
+        match self {
+            Metric::Cosine | Metric::DotProduct => dot_product(left, right),
+            Metric::Euclidean => euclidean_distance(left, right),
+            Metric::Manhattan => manhattan_distance(left, right),
+        }
+    }
+
+    /// Scores two `f64` vectors of the same length under this metric
+    /// Cosine expects both inputs to be normalized already
+    pub(crate) fn score_f64(&self, left: &[f64], right: &[f64]) -> f64 {
+        let pairs = left.iter().zip(right);
+        match self {
+            Metric::Cosine | Metric::DotProduct => pairs.map(|(l, r)| l * r).sum(),
+            Metric::Euclidean => pairs.map(|(l, r)| (l - r).powi(2)).sum::<f64>().sqrt(),
+            Metric::Manhattan => pairs.map(|(l, r)| (l - r).abs()).sum(),
+        }
+    }
+
+    /// Maps a score to a key where higher always means closer
+    pub(crate) fn rank_key(&self, score: f32) -> f32 {
+        if self.is_distance() { -score } else { score }
+    }
 }
 
 #[cfg(test)]
@@ -100,6 +373,79 @@ mod vector_test {
         assert_eq!(result.unwrap_err(), "Cannot normalize an empty vector");
     }
 
+    #[test]
+    fn test_l2_norm_non_finite() {
+        let result = l2_norm(&[1.0, f32::NAN, 0.0]);
+        assert_eq!(
+            result.unwrap_err(),
+            "Vector contains non-finite value at index 1"
+        );
+
+        let result = l2_norm(&[f32::INFINITY, 1.0]);
+        assert_eq!(
+            result.unwrap_err(),
+            "Vector contains non-finite value at index 0"
+        );
+    }
+
+    #[test]
+    fn test_l2_norm_f64() {
+        let result = l2_norm_f64(&[1.0, 1e-10]).unwrap();
+        assert_eq!(result[1], 1e-10 / (1.0f64 + 1e-20).sqrt());
+        assert_ne!(result[1], 0.0);
+
+        assert_eq!(
+            l2_norm_f64(&[0.0, 0.0]).unwrap_err(),
+            "Cannot normalize a zero vector"
+        );
+        assert_eq!(
+            l2_norm_f64(&[1.0, f64::NAN]).unwrap_err(),
+            "Vector contains non-finite value at index 1"
+        );
+    }
+
+    #[test]
+    fn test_l2_norm_batch_reports_per_vector() {
+        let vectors = vec![vec![3.0, 4.0], vec![0.0, 0.0], vec![], vec![0.0, 2.0]];
+        let results = l2_norm_batch(&vectors);
+
+        assert_eq!(results.len(), 4);
+        assert_eq!(results[0], Ok(vec![0.6, 0.8]));
+        assert_eq!(
+            results[1],
+            Err("Cannot normalize a zero vector".to_string())
+        );
+        assert_eq!(
+            results[2],
+            Err("Cannot normalize an empty vector".to_string())
+        );
+        assert_eq!(results[3], Ok(vec![0.0, 1.0]));
+        assert!(l2_norm_batch(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_prepare_flat() {
+        let mut values = vec![3.0, 4.0, 0.0, 2.0];
+        prepare_flat(&mut values, 2).unwrap();
+        assert_eq!(values, vec![0.6, 0.8, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn test_prepare_flat_reports_bad_chunk() {
+        let mut values = vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let err = prepare_flat(&mut values, 2).unwrap_err();
+        assert_eq!(err, "Chunk 1: Cannot normalize a zero vector");
+        // Nothing was normalized
+        assert_eq!(values, vec![1.0, 0.0, 0.0, 0.0, 0.0, 1.0]);
+
+        let mut values = vec![1.0, 0.0, f32::NAN, 1.0];
+        let err = prepare_flat(&mut values, 2).unwrap_err();
+        assert!(err.starts_with("Chunk 1:"));
+
+        assert!(prepare_flat(&mut [1.0, 2.0, 3.0], 2).is_err());
+        assert!(prepare_flat(&mut [1.0], 0).is_err());
+    }
+
     // ========== Dot Product Tests ==========
 
     #[test]
@@ -112,6 +458,30 @@ mod vector_test {
         assert!((result - 32.0).abs() < 1e-6);
     }
 
+    #[test]
+    fn test_dot_lanes_matches_scalar() {
+        // Small LCG, so the test needs no random number crate
+        let mut state = 42u32;
+        let mut random = || {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 8) as f32 / (1u32 << 24) as f32 - 0.5
+        };
+
+        for dim in [768, 771, 5, 0] {
+            let left: Vec<f32> = (0..dim).map(|_| random()).collect();
+            let right: Vec<f32> = (0..dim).map(|_| random()).collect();
+            let scalar = dot_scalar(&left, &right);
+            let lanes = dot_lanes(&left, &right);
+            assert!(
+                (scalar - lanes).abs() < 1e-5,
+                "dim {}: {} vs {}",
+                dim,
+                scalar,
+                lanes
+            );
+        }
+    }
+
     #[test]
     fn test_dot_product_orthogonal() {
         // Orthogonal vectors should have dot product = 0
@@ -150,6 +520,145 @@ mod vector_test {
         assert!((result - 0.0).abs() < 1e-6);
     }
 
+    // ========== Cosine Similarity Tests ==========
+
+    #[test]
+    fn test_cosine_similarity_scale_invariant() {
+        // Raw vectors pointing the same way are fully similar regardless of length
+        let a = vec![1.0, 2.0, 3.0];
+        let b = vec![10.0, 20.0, 30.0];
+        let result = cosine_similarity(&a, &b).unwrap();
+
+        assert!((result - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cosine_similarity_orthogonal_identical_opposite() {
+        let a = vec![3.0, 0.0];
+        let b = vec![0.0, 5.0];
+        let c = vec![-2.0, 0.0];
+
+        assert!(cosine_similarity(&a, &b).unwrap().abs() < 1e-6);
+        assert!((cosine_similarity(&a, &a).unwrap() - 1.0).abs() < 1e-6);
+        assert!((cosine_similarity(&a, &c).unwrap() + 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_cosine_similarity_zero_vector() {
+        let result = cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]);
+        assert_eq!(result.unwrap_err(), "Cannot normalize a zero vector");
+    }
+
+    #[test]
+    fn test_cosine_similarity_dimension_mismatch() {
+        let a = vec![1.0, 2.0, 3.0];
+        let b = vec![4.0, 5.0];
+
+        let result = cosine_similarity(&a, &b);
+        assert_eq!(result.unwrap_err(), "Different dimentions");
+    }
+
+    // ========== Quantization Tests ==========
+
+    #[test]
+    fn test_quantize_int8_round_trip() {
+        let v = vec![0.5, -1.0, 0.25, 0.0];
+        let (codes, scale) = quantize_int8(&v);
+
+        // Largest magnitude maps to the end of the i8 range
+        assert_eq!(codes[1], -127);
+        let restored = dequantize_int8(&codes, scale);
+        for (a, b) in v.iter().zip(restored.iter()) {
+            assert!((a - b).abs() <= scale / 2.0 + 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_quantize_int8_zero_vector() {
+        let (codes, scale) = quantize_int8(&[0.0, 0.0]);
+
+        assert_eq!(codes, vec![0, 0]);
+        assert_eq!(dequantize_int8(&codes, scale), vec![0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_quantize_binary_round_trip() {
+        let vector: Vec<f32> = (0..70)
+            .map(|i| if i % 3 == 0 { 1.0 } else { -0.5 })
+            .collect();
+        let bits = quantize_binary(&vector);
+        assert_eq!(bits.len(), 2);
+        assert_eq!(bits[0] & 0b1111, 0b1001);
+
+        let restored = dequantize_binary(&bits, vector.len());
+        let magnitude = 1.0 / 70f32.sqrt();
+        for (x, r) in vector.iter().zip(&restored) {
+            assert_eq!(*r, magnitude.copysign(*x));
+        }
+    }
+
+    #[test]
+    fn test_hamming_distance() {
+        assert_eq!(hamming_distance(&[0b1010], &[0b1010]), 0);
+        assert_eq!(hamming_distance(&[0b1010], &[0b0101]), 4);
+        assert_eq!(hamming_distance(&[u64::MAX, 0], &[0, 1]), 65);
+    }
+
+    // ========== Metric Tests ==========
+
+    #[test]
+    fn test_euclidean_distance_basic() {
+        // 3-4-5 triangle
+        let a = vec![0.0, 0.0];
+        let b = vec![3.0, 4.0];
+        let result = euclidean_distance(&a, &b).unwrap();
+
+        assert!((result - 5.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_euclidean_distance_identical() {
+        let a = vec![1.0, -2.0, 3.0];
+        let result = euclidean_distance(&a, &a).unwrap();
+
+        assert_eq!(result, 0.0);
+    }
+
+    #[test]
+    fn test_euclidean_distance_empty_vectors() {
+        let result = euclidean_distance(&[], &[]).unwrap();
+
+        assert_eq!(result, 0.0);
+    }
+
+    #[test]
+    fn test_euclidean_distance_dimension_mismatch() {
+        let a = vec![1.0, 2.0, 3.0];
+        let b = vec![4.0, 5.0];
+
+        let result = euclidean_distance(&a, &b);
+        assert_eq!(result.unwrap_err(), "Different dimentions");
+    }
+
+    #[test]
+    fn test_manhattan_distance_basic() {
+        let a = vec![1.0, -2.0, 3.0];
+        let b = vec![4.0, 2.0, 3.0];
+        // Expected: |1-4| + |-2-2| + |3-3| = 3 + 4 + 0 = 7
+        let result = manhattan_distance(&a, &b).unwrap();
+
+        assert!((result - 7.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_metric_rank_key() {
+        // Similarities rank higher-first, distances lower-first
+        assert!(Metric::Cosine.rank_key(0.9) > Metric::Cosine.rank_key(0.1));
+        assert!(Metric::DotProduct.rank_key(5.0) > Metric::DotProduct.rank_key(1.0));
+        assert!(Metric::Euclidean.rank_key(0.1) > Metric::Euclidean.rank_key(0.9));
+        assert!(Metric::Manhattan.rank_key(1.0) > Metric::Manhattan.rank_key(5.0));
+    }
+
     // ========== Integration Test ==========
 
     #[test]
@@ -0,0 +1,182 @@
+//! The write-ahead log module
+//! Provide the sidecar log that `VecDB::load` replays on top of a base file
+//!
+//! By convention the WAL for a database saved at `path` lives at `path.wal`.
+//! Each record is stored as a little-endian `u64` byte length followed by the
+//! bincode-encoded [`WalRecord`].
+
+use crate::db::bincode_options;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+};
+
+/// A single mutation recorded in the write-ahead log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum WalRecord {
+    /// Insert or update a vector (stored as given, normalized on replay)
+    Insert { id: String, vector: Vec<f32> },
+    /// Delete a vector by ID
+    Delete { id: String },
+}
+
+/// Returns the sidecar WAL path for the database file at `path`.
+pub fn wal_path(path: &str) -> String {
+    format!("{}.wal", path)
+}
+
+/// Appends a record to the WAL of the database file at `path`.
+///
+/// The WAL file is created if it doesn't exist yet.
+pub fn append(path: &str, record: &WalRecord) -> Result<(), String> {
+    append_all(path, std::slice::from_ref(record))
+}
+
+/// Appends several records to the WAL of the database file at `path`,
+/// opening it only once.
+///
+/// The WAL file is created if it doesn't exist yet. The records are synced to
+/// disk before returning, so a successful append survives a crash.
+pub fn append_all(path: &str, records: &[WalRecord]) -> Result<(), String> {
+    let wal = wal_path(path);
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&wal)
+        .map_err(|e| format!("Fail to open WAL '{}': {}", wal, e))?;
+
+    let mut writer = BufWriter::new(file);
+    for record in records {
+        let payload = bincode_options()
+            .serialize(record)
+            .map_err(|e| format!("Serialization failed: {}", e))?;
+        writer
+            .write_all(&(payload.len() as u64).to_le_bytes())
+            .and_then(|_| writer.write_all(&payload))
+            .map_err(|e| format!("Fail to write WAL '{}': {}", wal, e))?;
+    }
+    // Don't report success while the records may still sit in the OS cache
+    writer
+        .into_inner()
+        .map_err(|e| format!("Fail to write WAL '{}': {}", wal, e.error()))?
+        .sync_all()
+        .map_err(|e| format!("Fail to sync WAL '{}': {}", wal, e))?;
+
+    Ok(())
+}
+
+/// Reads every complete record from the WAL of the database file at `path`.
+///
+/// Returns an empty list if there is no WAL. A trailing record that was cut
+/// short (e.g. by a crash mid-write), or whose length prefix claims more bytes
+/// than are left in the file, is ignored.
+pub fn read_all(path: &str) -> Result<Vec<WalRecord>, String> {
+    let wal = wal_path(path);
+    let file = match File::open(&wal) {
+        Ok(file) => file,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Fail to open WAL '{}': {}", wal, e)),
+    };
+
+    // Bytes not read yet, so a garbage length can't trigger a huge allocation
+    let mut remaining = file
+        .metadata()
+        .map_err(|e| format!("Fail to read WAL '{}': {}", wal, e))?
+        .len();
+    let mut reader = BufReader::new(file);
+    let mut records = Vec::new();
+
+    loop {
+        let mut len_bytes = [0u8; 8];
+        if reader.read_exact(&mut len_bytes).is_err() {
+            break;
+        }
+        remaining = remaining.saturating_sub(8);
+
+        let len = u64::from_le_bytes(len_bytes);
+        if len > remaining {
+            break;
+        }
+        remaining -= len;
+
+        let mut payload = vec![0u8; len as usize];
+        if reader.read_exact(&mut payload).is_err() {
+            break;
+        }
+
+        let record = bincode_options()
+            .deserialize(&payload)
+            .map_err(|e| format!("Deserialization failed: {}", e))?;
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Empties the WAL of the database file at `path`, if it exists.
+pub fn truncate(path: &str) -> Result<(), String> {
+    let wal = wal_path(path);
+    if !std::path::Path::new(&wal).exists() {
+        return Ok(());
+    }
+
+    File::create(&wal).map_err(|e| format!("Fail to truncate WAL '{}': {}", wal, e))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod wal_test {
+    use super::*;
+
+    #[test]
+    fn test_append_and_read_all() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.db");
+        let path_str = path.to_str().unwrap();
+
+        let insert = WalRecord::Insert {
+            id: "vec1".to_string(),
+            vector: vec![1.0, 2.0],
+        };
+        let delete = WalRecord::Delete {
+            id: "vec0".to_string(),
+        };
+        append(path_str, &insert).unwrap();
+        append(path_str, &delete).unwrap();
+
+        assert_eq!(read_all(path_str).unwrap(), vec![insert, delete]);
+    }
+
+    #[test]
+    fn test_read_all_without_wal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("none.db");
+
+        assert!(read_all(path.to_str().unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_read_all_ignores_torn_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("torn.db");
+        let path_str = path.to_str().unwrap();
+
+        let record = WalRecord::Delete {
+            id: "vec1".to_string(),
+        };
+        append(path_str, &record).unwrap();
+
+        // Simulate a crash halfway through writing a second record
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(wal_path(path_str))
+            .unwrap();
+        file.write_all(&100u64.to_le_bytes()).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+
+        assert_eq!(read_all(path_str).unwrap(), vec![record]);
+    }
+}
@@ -1,4 +1,5 @@
 use actix_web::{App, HttpServer};
+use futures_util::future::join_all;
 use reqwest::Client;
 use serde_json::json;
 use std::net::TcpListener;
@@ -205,6 +206,81 @@ async fn test_delete_and_verify() {
     handle.stop(true).await;
 }
 
+#[actix_web::test]
+async fn test_rename() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [
+                {"id": "a", "values": [1.0, 0.0]},
+                {"id": "b", "values": [0.0, 1.0]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let resp = client
+        .post(format!("{}/rename", base))
+        .json(&json!({"db": db_path, "old": "a", "new": "c"}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert_eq!(body, json!({"old": "a", "new": "c"}));
+
+    // Missing old ID and colliding new ID are rejected
+    for (old, new) in [("a", "d"), ("c", "b")] {
+        let resp = client
+            .post(format!("{}/rename", base))
+            .json(&json!({"db": db_path, "old": old, "new": new}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400);
+        let body: serde_json::Value = resp.json().await.unwrap();
+        assert!(body["error"].is_string());
+    }
+
+    let resp = client
+        .post(format!("{}/get", base))
+        .json(&json!({"db": db_path, "ids": ["a", "c"]}))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let results = body["results"].as_array().unwrap();
+    assert!(results[0]["values"].is_null()); // old ID is gone
+    assert_eq!(results[1]["values"], json!([1.0, 0.0]));
+
+    // The rename was saved
+    assert!(kvdb::VecDB::load(&db_path).unwrap().contains("c"));
+
+    handle.stop(true).await;
+}
+
 #[actix_web::test]
 async fn test_insert_duplicate_id() {
     let port = free_port();
@@ -271,12 +347,12 @@ async fn test_insert_duplicate_id() {
 }
 
 #[actix_web::test]
-async fn test_search_empty_db() {
+async fn test_insert_strict_batch() {
     let port = free_port();
     let temp_dir = TempDir::new().unwrap();
     let db_path = temp_dir
         .path()
-        .join("empty.db")
+        .join("strict.db")
         .to_str()
         .unwrap()
         .to_string();
@@ -292,12 +368,86 @@ async fn test_search_empty_db() {
     let client = Client::new();
     let base = format!("http://127.0.0.1:{}", port);
 
-    // Search on a db that doesn't exist yet (will be created empty)
+    // One vector is off by one dimension: the whole batch is rejected
+    let resp = client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "strict_batch": true,
+            "vectors": [
+                {"id": "vec1", "values": [1.0, 0.0, 0.0]},
+                {"id": "vec2", "values": [0.0, 1.0, 0.0]},
+                {"id": "odd", "values": [0.0, 0.0, 1.0, 0.0]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("odd (4)"));
+
+    // Nothing was stored
+    let resp = client
+        .post(format!("{}/get", base))
+        .json(&json!({"db": db_path, "ids": ["vec1"]}))
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["results"][0]["values"].is_null());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_search_uses_cached_db() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("cached.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    let resp = client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [
+                {"id": "vec1", "values": [1.0, 0.0]},
+                {"id": "vec2", "values": [0.0, 1.0]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    // The insert was still persisted
+    assert_eq!(kvdb::VecDB::load(&db_path).unwrap().count(), 2);
+
+    // Remove the file: the search can only succeed from memory
+    std::fs::remove_file(&db_path).unwrap();
+
     let resp = client
         .post(format!("{}/search", base))
         .json(&json!({
             "db": db_path,
-            "queries": [{"value": [1.0, 0.0], "top_k": 5}]
+            "queries": [{"value": [1.0, 0.1], "top_k": 2}]
         }))
         .send()
         .await
@@ -305,9 +455,795 @@ async fn test_search_empty_db() {
 
     assert_eq!(resp.status(), 200);
     let body: serde_json::Value = resp.json().await.unwrap();
-    // Should return results with an error message (empty db)
-    let group = &body["results"][0];
-    assert!(group["matches"].as_array().unwrap().is_empty());
+    let matches = body["results"][0]["matches"].as_array().unwrap();
+    assert_eq!(matches.len(), 2);
+    assert_eq!(matches[0]["id"], "vec1");
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_failed_save_is_not_served_from_cache() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_dir = temp_dir.path().join("data");
+    std::fs::create_dir(&db_dir).unwrap();
+    let db_path = db_dir.join("unsaved.db").to_str().unwrap().to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    let resp = client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [{"id": "vec1", "values": [1.0, 0.0]}]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    // Remove the directory so the next save fails
+    let saved = std::fs::read(&db_path).unwrap();
+    std::fs::remove_dir_all(&db_dir).unwrap();
+
+    let resp = client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [{"id": "vec2", "values": [0.0, 1.0]}]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 500);
+
+    // Once the file is back, only what was saved is served
+    std::fs::create_dir(&db_dir).unwrap();
+    std::fs::write(&db_path, saved).unwrap();
+
+    let resp = client
+        .post(format!("{}/get", base))
+        .json(&json!({
+            "db": db_path,
+            "ids": ["vec1", "vec2"]
+        }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let results = body["results"].as_array().unwrap();
+    assert!(!results[0]["values"].is_null());
+    assert!(results[1]["values"].is_null());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_search_min_score() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("min_score.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    // Five vectors close to [1, 0], one far away
+    client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [
+                {"id": "near3", "values": [1.0, 0.3]},
+                {"id": "near1", "values": [1.0, 0.1]},
+                {"id": "far", "values": [0.0, 1.0]},
+                {"id": "near5", "values": [1.0, 0.5]},
+                {"id": "near2", "values": [1.0, 0.2]},
+                {"id": "near4", "values": [1.0, 0.4]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let resp = client
+        .post(format!("{}/search", base))
+        .json(&json!({
+            "db": db_path,
+            "queries": [
+                {"value": [1.0, 0.0], "min_score": 0.8},
+                {"value": [1.0, 0.0], "min_score": 0.8, "top_k": 2},
+                {"value": [1.0, 0.0]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let ids: Vec<&str> = body["results"][0]["matches"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|m| m["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, vec!["near1", "near2", "near3", "near4", "near5"]);
+
+    assert_eq!(body["results"][1]["matches"].as_array().unwrap().len(), 2);
+
+    // Neither top_k nor min_score
+    assert!(body["results"][2]["matches"].as_array().unwrap().is_empty());
+    assert!(
+        body["results"][2]["message"]
+            .as_str()
+            .unwrap()
+            .contains("top_k")
+    );
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_cors_preflight() {
+    let port = free_port();
+
+    let server = HttpServer::new(|| {
+        App::new().configure(|cfg| {
+            let cors = kvdb::server::CorsConfig::new().allow_origin("http://allowed.example");
+            kvdb::server::config_with_cors(cfg, &cors)
+        })
+    })
+    .bind(format!("127.0.0.1:{}", port))
+    .unwrap()
+    .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    let resp = client
+        .request(reqwest::Method::OPTIONS, format!("{}/search", base))
+        .header("Origin", "http://allowed.example")
+        .header("Access-Control-Request-Method", "POST")
+        .header("Access-Control-Request-Headers", "content-type")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["access-control-allow-origin"],
+        "http://allowed.example"
+    );
+
+    // Other origins get no CORS headers
+    let resp = client
+        .request(reqwest::Method::OPTIONS, format!("{}/search", base))
+        .header("Origin", "http://evil.example")
+        .header("Access-Control-Request-Method", "POST")
+        .send()
+        .await
+        .unwrap();
+    assert!(resp.headers().get("access-control-allow-origin").is_none());
+
+    // Regular requests still work through the CORS scope
+    let resp = client
+        .post(format!("{}/similarity", base))
+        .header("Origin", "http://allowed.example")
+        .json(&json!({"a": [1.0, 0.0], "b": [1.0, 0.0]}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["access-control-allow-origin"],
+        "http://allowed.example"
+    );
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_malformed_json() {
+    let port = free_port();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    let resp = client
+        .post(format!("{}/search", base))
+        .header("Content-Type", "application/json")
+        .body("{ invalid json")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"].is_string());
+
+    // Valid JSON with the wrong shape is reported the same way
+    let resp = client
+        .post(format!("{}/insert", base))
+        .json(&json!({"db": "x.db", "vectors": "not a list"}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"].as_str().unwrap().contains("invalid type"));
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_api_key_required() {
+    let port = free_port();
+
+    let server = HttpServer::new(|| {
+        App::new().configure(|cfg| {
+            let options = kvdb::server::ServerOptions::new().with_api_key("secret");
+            kvdb::server::config_with(cfg, &options)
+        })
+    })
+    .bind(format!("127.0.0.1:{}", port))
+    .unwrap()
+    .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let url = format!("http://127.0.0.1:{}/similarity", port);
+    let body = json!({"a": [1.0, 0.0], "b": [1.0, 0.0]});
+
+    // Missing key
+    let resp = client.post(&url).json(&body).send().await.unwrap();
+    assert_eq!(resp.status(), 401);
+    let err: serde_json::Value = resp.json().await.unwrap();
+    assert!(err["error"].is_string());
+
+    // Wrong key
+    let resp = client
+        .post(&url)
+        .bearer_auth("guess")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_empty_api_key_is_rejected() {
+    let port = free_port();
+
+    let server = HttpServer::new(|| {
+        App::new().configure(|cfg| {
+            let options = kvdb::server::ServerOptions::new().with_api_key("");
+            kvdb::server::config_with(cfg, &options)
+        })
+    })
+    .bind(format!("127.0.0.1:{}", port))
+    .unwrap()
+    .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let url = format!("http://127.0.0.1:{}/similarity", port);
+    let body = json!({"a": [1.0, 0.0], "b": [1.0, 0.0]});
+
+    let resp = client
+        .post(&url)
+        .header("X-API-Key", "")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    let resp = client
+        .post(&url)
+        .header("Authorization", "Bearer ")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 401);
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_api_key_authorized() {
+    let port = free_port();
+
+    let server = HttpServer::new(|| {
+        App::new().configure(|cfg| {
+            let options = kvdb::server::ServerOptions::new()
+                .with_cors(kvdb::server::CorsConfig::new())
+                .with_api_key("secret");
+            kvdb::server::config_with(cfg, &options)
+        })
+    })
+    .bind(format!("127.0.0.1:{}", port))
+    .unwrap()
+    .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let url = format!("http://127.0.0.1:{}/similarity", port);
+    let body = json!({"a": [1.0, 0.0], "b": [1.0, 0.0]});
+
+    let resp = client
+        .post(&url)
+        .bearer_auth("secret")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    let resp = client
+        .post(&url)
+        .header("X-API-Key", "secret")
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    // Browsers send preflight requests without credentials
+    let resp = client
+        .request(reqwest::Method::OPTIONS, &url)
+        .header("Origin", "http://localhost:3000")
+        .header("Access-Control-Request-Method", "POST")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 200);
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_concurrent_reads_during_writes() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("concurrent.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .workers(4)
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    // Vectors are always inserted in pairs, so a consistent view holds an even count
+    let insert_pair = |i: usize| {
+        client
+            .post(format!("{}/insert", base))
+            .json(&json!({
+                "db": db_path,
+                "vectors": [
+                    {"id": format!("a{}", i), "values": [1.0, i as f32]},
+                    {"id": format!("b{}", i), "values": [i as f32, 1.0]}
+                ]
+            }))
+            .send()
+    };
+    insert_pair(0).await.unwrap();
+
+    let search = || {
+        client
+            .post(format!("{}/search", base))
+            .json(&json!({
+                "db": db_path,
+                "queries": [{"value": [1.0, 1.0], "top_k": 1000}]
+            }))
+            .send()
+    };
+
+    let writer = async {
+        for i in 1..20 {
+            assert_eq!(insert_pair(i).await.unwrap().status(), 200);
+        }
+    };
+    let readers = join_all((0..100).map(|_| async {
+        let body: serde_json::Value = search().await.unwrap().json().await.unwrap();
+        body["results"][0]["matches"].as_array().unwrap().len()
+    }));
+    let ((), counts) = futures_util::join!(writer, readers);
+
+    for count in counts {
+        assert!(
+            (2..=40).contains(&count) && count % 2 == 0,
+            "torn read: {}",
+            count
+        );
+    }
+
+    let body: serde_json::Value = search().await.unwrap().json().await.unwrap();
+    assert_eq!(body["results"][0]["matches"].as_array().unwrap().len(), 40);
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_search_empty_db() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("empty.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    // Search on a db that doesn't exist yet (will be created empty)
+    let resp = client
+        .post(format!("{}/search", base))
+        .json(&json!({
+            "db": db_path,
+            "queries": [{"value": [1.0, 0.0], "top_k": 5}]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    // Should return results with an error message (empty db)
+    let group = &body["results"][0];
+    assert!(group["matches"].as_array().unwrap().is_empty());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_similarity() {
+    let port = free_port();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    // Orthogonal vectors
+    let resp = client
+        .post(format!("{}/similarity", base))
+        .json(&json!({"a": [1.0, 0.0], "b": [0.0, 3.0]}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["score"].as_f64().unwrap().abs() < 0.01);
+
+    // Identical vectors
+    let resp = client
+        .post(format!("{}/similarity", base))
+        .json(&json!({"a": [1.0, 2.0, 3.0], "b": [1.0, 2.0, 3.0]}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!((body["score"].as_f64().unwrap() - 1.0).abs() < 0.01);
+
+    // Mismatched dimensions are rejected
+    let resp = client
+        .post(format!("{}/similarity", base))
+        .json(&json!({"a": [1.0, 2.0, 3.0], "b": [1.0, 2.0]}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 400);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["error"].is_string());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_score_pairs() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [{"id": "a", "values": [1.0, 0.0]}]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // One matching pair, one pair with a missing id
+    let resp = client
+        .post(format!("{}/score_pairs", base))
+        .json(&json!({
+            "db": db_path,
+            "pairs": [
+                {"query": [1.0, 0.0], "id": "a"},
+                {"query": [1.0, 0.0], "id": "missing"}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let scores = body["scores"].as_array().unwrap();
+    assert_eq!(scores.len(), 2);
+    assert!((scores[0].as_f64().unwrap() - 1.0).abs() < 0.01);
+    assert!(scores[1].is_null());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_search_with_hash() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [
+                {"id": "vec1", "values": [1.0, 0.0]},
+                {"id": "vec2", "values": [0.0, 1.0]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    // Hash included on request
+    let resp = client
+        .post(format!("{}/search", base))
+        .json(&json!({
+            "db": db_path,
+            "queries": [{"value": [1.0, 0.0], "top_k": 1}],
+            "with_hash": true
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let first = &body["results"][0]["matches"][0];
+    assert_eq!(first["hash"].as_u64().unwrap(), kvdb::id_hash("vec1"));
+
+    // Omitted by default
+    let resp = client
+        .post(format!("{}/search", base))
+        .json(&json!({
+            "db": db_path,
+            "queries": [{"value": [1.0, 0.0], "top_k": 1}]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let body: serde_json::Value = resp.json().await.unwrap();
+    assert!(body["results"][0]["matches"][0].get("hash").is_none());
+    assert!(body["results"][0]["matches"][0]["values"].is_array());
+
+    // IDs and scores only
+    let resp = client
+        .post(format!("{}/search", base))
+        .json(&json!({
+            "db": db_path,
+            "queries": [{"value": [1.0, 0.0], "top_k": 1}],
+            "ids_only": true
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let first = &body["results"][0]["matches"][0];
+    assert_eq!(first["id"], "vec1");
+    assert!(first["score"].is_number());
+    assert!(first.get("values").is_none());
+
+    // Values for the best match only
+    let resp = client
+        .post(format!("{}/search", base))
+        .json(&json!({
+            "db": db_path,
+            "queries": [{"value": [1.0, 0.0], "top_k": 2, "values_for_top": 1}]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let body: serde_json::Value = resp.json().await.unwrap();
+    let matches = body["results"][0]["matches"].as_array().unwrap();
+    assert_eq!(matches.len(), 2);
+    assert!(matches[0]["values"].is_array());
+    assert!(matches[1].get("values").is_none());
+
+    handle.stop(true).await;
+}
+
+#[actix_web::test]
+async fn test_search_stream() {
+    let port = free_port();
+    let temp_dir = TempDir::new().unwrap();
+    let db_path = temp_dir
+        .path()
+        .join("test.db")
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    let server = HttpServer::new(|| App::new().configure(kvdb::server::config))
+        .bind(format!("127.0.0.1:{}", port))
+        .unwrap()
+        .run();
+    let handle = server.handle();
+    tokio::spawn(server);
+    sleep(Duration::from_millis(200)).await;
+
+    let client = Client::new();
+    let base = format!("http://127.0.0.1:{}", port);
+
+    client
+        .post(format!("{}/insert", base))
+        .json(&json!({
+            "db": db_path,
+            "vectors": [
+                {"id": "vec1", "values": [1.0, 0.0, 0.0]},
+                {"id": "vec2", "values": [0.0, 1.0, 0.0]},
+                {"id": "vec3", "values": [0.7, 0.7, 0.0]},
+                {"id": "vec4", "values": [0.0, 0.0, 1.0]}
+            ]
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    let resp = client
+        .post(format!("{}/search_stream", base))
+        .json(&json!({"db": db_path, "value": [1.0, 0.1, 0.0], "top_k": 3}))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(resp.status(), 200);
+    assert_eq!(
+        resp.headers()["content-type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+
+    // Consume the whole stream: one JSON match per line, in ranked order
+    let body = resp.text().await.unwrap();
+    let matches: Vec<serde_json::Value> = body
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let ids: Vec<&str> = matches.iter().map(|m| m["id"].as_str().unwrap()).collect();
+    assert_eq!(ids, vec!["vec1", "vec3", "vec2"]);
+
+    // Bad query: plain JSON error instead of a stream
+    let resp = client
+        .post(format!("{}/search_stream", base))
+        .json(&json!({"db": db_path, "value": [1.0], "top_k": 3}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(resp.status(), 400);
 
     handle.stop(true).await;
 }
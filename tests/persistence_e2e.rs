@@ -77,7 +77,7 @@ fn test_save_load_10k_vectors_and_search() {
         assert_eq!(results.len(), 10);
         // Verify results are sorted by score descending
         for w in results.windows(2) {
-            assert!(w[0].2 >= w[1].2, "Results not sorted by score");
+            assert!(w[0].score >= w[1].score, "Results not sorted by score");
         }
     }
     let search_time = start.elapsed();